@@ -6,4 +6,9 @@ public void SetGpuPower(GpuPowerData data) {
 // Updates the GPU power settings to one of the presets
 public void SetGpuPower(GpuPowerLevel value) {
     SetGpuPower(new GpuPowerData(value));
-}
\ No newline at end of file
+}
+
+// Updates the CPU power limits to those passed in a structure
+public void SetCpuPower(CpuPowerData data) {
+    Check(Send(Cmd.Default, 0x29, Conv.GetByteArray(data)));
+}
@@ -13,4 +13,31 @@ public struct GpuPowerData {
         DState = GpuDState.D1;
         PeakTemperature = 0;
     }
-}
\ No newline at end of file
+}
+// CPU power limits data structure
+[StructLayout(LayoutKind.Sequential, Pack = 1, Size = 4)]
+public struct CpuPowerData {
+    public byte Pl1;       // Sustained power limit [W]
+    public byte Pl2;       // Turbo power limit [W]
+    public byte Pl4;       // Peak power limit [W]
+    public byte Reserved;  // Always 0xFF
+
+    public const byte Unchanged = 0xFF;  // Leaves the current limit in place
+    public const byte MinWatts = 15;     // Board-safe range for Victus & OMEN CPUs
+    public const byte MaxWatts = 140;
+
+    // Initializes the CPU power limits, clamped to the board-safe range and ordered PL1 <= PL2 <= PL4
+    public CpuPowerData(byte pl1 = Unchanged, byte pl2 = Unchanged, byte pl4 = Unchanged) {
+        Pl1 = Clamp(pl1, 0);
+        Pl2 = Clamp(pl2, Pl1);
+        Pl4 = Clamp(pl4, Pl2 != Unchanged ? Pl2 : Pl1);
+        Reserved = Unchanged;
+    }
+
+    private static byte Clamp(byte value, byte floor) {
+        if(value == Unchanged)
+            return Unchanged;
+        byte min = floor != Unchanged && floor > MinWatts ? floor : MinWatts;
+        return value < min ? min : value > MaxWatts ? MaxWatts : value;
+    }
+}
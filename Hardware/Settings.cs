@@ -13,4 +13,9 @@ public void SetGpuPower(BiosData.GpuPowerData value) {
 // Queries the Processing Power AI Boost state
 public BiosData.GpuPpab GetGpuPpab(bool forceUpdate = false) {
     return GetGpuPower(forceUpdate).Ppab;
-}
\ No newline at end of file
+}
+
+// Sets the CPU power limits (PL1, PL2 & PL4)
+public void SetCpuPower(BiosData.CpuPowerData value) {
+    Hw.BiosSetStruct(Hw.Bios.SetCpuPower, value);
+}
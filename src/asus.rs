@@ -0,0 +1,288 @@
+//! ASUS fan control through the `asus-wmi` platform driver (`asus-nb-wmi`).
+//! Uses `throttle_thermal_policy` (newer models) or `fan_boost_mode` (older models) for profiles
+//! and the hwmon `pwm1_enable` attribute for unregulated full speed.
+
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::sysfs;
+use crate::thermal;
+
+/// Profile id for full speed via `pwm1_enable`; policy values 0..=2 map to ids directly.
+pub const PROFILE_FULL_SPEED: ProfileId = 3;
+
+/// `pwm1_enable` values understood by asus-wmi.
+const PWM_FULL_SPEED: &str = "0";
+const PWM_AUTO: &str = "2";
+
+/// Which asus-wmi attributes the running platform exposes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AsusAttributes {
+    pub throttle_thermal_policy: bool,
+    pub fan_boost_mode: bool,
+    pub pwm1_enable: bool,
+}
+
+pub struct AsusWmiDriver {
+    sys_root: PathBuf,
+    /// `throttle_thermal_policy` if present, else `fan_boost_mode`.
+    policy: Option<PathBuf>,
+    attributes: AsusAttributes,
+    hwmon: Option<PathBuf>,
+}
+
+impl AsusWmiDriver {
+    /// Probe the live `/sys` tree.
+    pub fn new() -> Result<Self, DriverError> {
+        Self::probe(Path::new("/sys"))
+    }
+
+    /// Probe a sysfs tree rooted at `root` for the asus-nb-wmi platform device.
+    pub fn probe(root: &Path) -> Result<Self, DriverError> {
+        let platform = root.join("devices/platform/asus-nb-wmi");
+        if !platform.is_dir() {
+            return Err(DriverError::NotReady(format!(
+                "{} not found - asus-nb-wmi not loaded or not an ASUS system?",
+                platform.display()
+            )));
+        }
+
+        let throttle = platform.join("throttle_thermal_policy");
+        let boost = platform.join("fan_boost_mode");
+        let hwmon = sysfs::numbered_entries(&platform.join("hwmon"), "hwmon")
+            .into_iter()
+            .map(|(_, path)| path)
+            .find(|path| path.join("pwm1_enable").exists());
+
+        let attributes = AsusAttributes {
+            throttle_thermal_policy: throttle.exists(),
+            fan_boost_mode: boost.exists(),
+            pwm1_enable: hwmon.is_some(),
+        };
+        let policy = if attributes.throttle_thermal_policy {
+            Some(throttle)
+        } else if attributes.fan_boost_mode {
+            Some(boost)
+        } else {
+            None
+        };
+
+        if policy.is_none() && hwmon.is_none() {
+            return Err(DriverError::NotReady(
+                "asus-nb-wmi exposes neither throttle_thermal_policy, fan_boost_mode nor pwm1_enable".into(),
+            ));
+        }
+
+        Ok(Self {
+            sys_root: root.to_path_buf(),
+            policy,
+            attributes,
+            hwmon,
+        })
+    }
+
+    pub fn attributes(&self) -> AsusAttributes {
+        self.attributes
+    }
+
+    fn pwm_enable_path(&self) -> Option<PathBuf> {
+        self.hwmon.as_ref().map(|hwmon| hwmon.join("pwm1_enable"))
+    }
+
+    fn is_full_speed(&self) -> Result<bool, DriverError> {
+        match self.pwm_enable_path() {
+            Some(path) => Ok(sysfs::read_trimmed(&path)? == PWM_FULL_SPEED),
+            None => Ok(false),
+        }
+    }
+
+    /// Attribute writes that select `profile`, in order.
+    fn profile_writes(&self, profile: ProfileId) -> Result<Vec<(PathBuf, String)>, DriverError> {
+        match (profile, &self.policy, self.pwm_enable_path()) {
+            (PROFILE_FULL_SPEED, _, Some(pwm)) => Ok(vec![(pwm, PWM_FULL_SPEED.into())]),
+            (0..=2, Some(policy), pwm) => {
+                let mut writes = Vec::new();
+                // Leaving full speed: hand the fan back to the firmware curve first.
+                if let Some(pwm) = pwm {
+                    if self.is_full_speed()? {
+                        writes.push((pwm, PWM_AUTO.into()));
+                    }
+                }
+                writes.push((policy.clone(), profile.to_string()));
+                Ok(writes)
+            }
+            _ => Err(DriverError::Unsupported(format!("Unknown profile: {}", profile))),
+        }
+    }
+}
+
+impl FanDriver for AsusWmiDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        let mut profiles = Vec::new();
+
+        if self.policy.is_some() {
+            // Without pwm1_enable the boost policy is the strongest cooling available.
+            let boost_is_max = !self.attributes.pwm1_enable;
+            profiles.push(FanProfile {
+                id: 0,
+                name: "Balanced (Default)".into(),
+                is_maximum: false,
+            });
+            profiles.push(FanProfile {
+                id: 1,
+                name: "Turbo".into(),
+                is_maximum: boost_is_max,
+            });
+            profiles.push(FanProfile {
+                id: 2,
+                name: "Silent".into(),
+                is_maximum: false,
+            });
+        }
+
+        if self.attributes.pwm1_enable {
+            profiles.push(FanProfile {
+                id: PROFILE_FULL_SPEED,
+                name: "Full Speed (Max)".into(),
+                is_maximum: true,
+            });
+        }
+
+        Ok(FanCapabilities {
+            supported: true,
+            profiles,
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        if self.is_full_speed()? {
+            return Ok(Some(PROFILE_FULL_SPEED));
+        }
+        match &self.policy {
+            Some(path) => Ok(sysfs::read_trimmed(path)?.parse::<ProfileId>().ok()),
+            None => Ok(None),
+        }
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        for (path, value) in self.profile_writes(profile)? {
+            sysfs::write_value(&path, &value)?;
+        }
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        Ok(Explanation::new(profile).writes(self.profile_writes(profile)?))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        let rpm = self
+            .hwmon
+            .as_ref()
+            .and_then(|hwmon| sysfs::read_i64(&hwmon.join("fan1_input")).ok())
+            .and_then(|rpm| u32::try_from(rpm).ok());
+
+        Ok(FanTelemetry {
+            rpm,
+            temperature_c: thermal::package_temperature_c(&self.sys_root),
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    const PLATFORM: &str = "devices/platform/asus-nb-wmi";
+
+    fn asus(throttle: bool, boost: bool, pwm: bool) -> Fixture {
+        let sys = Fixture::new();
+        sys.write(&format!("{}/uevent", PLATFORM), "DRIVER=asus-nb-wmi\n");
+        if throttle {
+            sys.write(&format!("{}/throttle_thermal_policy", PLATFORM), "0\n");
+        }
+        if boost {
+            sys.write(&format!("{}/fan_boost_mode", PLATFORM), "0\n");
+        }
+        if pwm {
+            sys.write(&format!("{}/hwmon/hwmon5/pwm1_enable", PLATFORM), "2\n")
+                .write(&format!("{}/hwmon/hwmon5/fan1_input", PLATFORM), "4100\n");
+        }
+        sys
+    }
+
+    fn ids(driver: &AsusWmiDriver) -> Vec<ProfileId> {
+        driver.capabilities().unwrap().profiles.iter().map(|p| p.id).collect()
+    }
+
+    fn maximum(driver: &AsusWmiDriver) -> Vec<ProfileId> {
+        let caps = driver.capabilities().unwrap();
+        caps.profiles.iter().filter(|p| p.is_maximum).map(|p| p.id).collect()
+    }
+
+    #[test]
+    fn probe_needs_a_control_attribute() {
+        assert!(matches!(AsusWmiDriver::probe(Fixture::new().root()), Err(DriverError::NotReady(_))));
+        assert!(matches!(
+            AsusWmiDriver::probe(asus(false, false, false).root()),
+            Err(DriverError::NotReady(_))
+        ));
+    }
+
+    #[test]
+    fn throttle_policy_wins_over_fan_boost_mode() {
+        let sys = asus(true, true, false);
+        let driver = AsusWmiDriver::probe(sys.root()).unwrap();
+        assert!(driver.attributes().throttle_thermal_policy && driver.attributes().fan_boost_mode);
+        assert_eq!(ids(&driver), [0, 1, 2]);
+        assert_eq!(maximum(&driver), [1], "Turbo is the maximum without pwm1_enable");
+
+        driver.set_profile(2).unwrap();
+        assert_eq!(sys.read(&format!("{}/throttle_thermal_policy", PLATFORM)), "2");
+        assert_eq!(sys.read(&format!("{}/fan_boost_mode", PLATFORM)), "0");
+        assert_eq!(driver.current_profile().unwrap(), Some(2));
+        assert!(matches!(driver.set_profile(PROFILE_FULL_SPEED), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn older_models_use_fan_boost_mode() {
+        let sys = asus(false, true, false);
+        let driver = AsusWmiDriver::probe(sys.root()).unwrap();
+        driver.set_profile(1).unwrap();
+        assert_eq!(sys.read(&format!("{}/fan_boost_mode", PLATFORM)), "1");
+    }
+
+    #[test]
+    fn full_speed_is_undone_before_changing_policy() {
+        let sys = asus(true, false, true);
+        let driver = AsusWmiDriver::probe(sys.root()).unwrap();
+        assert_eq!(ids(&driver), [0, 1, 2, PROFILE_FULL_SPEED]);
+        assert_eq!(maximum(&driver), [PROFILE_FULL_SPEED]);
+
+        driver.set_profile(PROFILE_FULL_SPEED).unwrap();
+        assert_eq!(sys.read(&format!("{}/hwmon/hwmon5/pwm1_enable", PLATFORM)), PWM_FULL_SPEED);
+        assert_eq!(driver.current_profile().unwrap(), Some(PROFILE_FULL_SPEED));
+
+        let explanation = driver.explain_profile(1).unwrap();
+        assert_eq!(explanation.actions.len(), 2);
+        driver.set_profile(1).unwrap();
+        assert_eq!(sys.read(&format!("{}/hwmon/hwmon5/pwm1_enable", PLATFORM)), PWM_AUTO);
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+        assert_eq!(driver.explain_profile(1).unwrap().actions.len(), 1);
+    }
+
+    #[test]
+    fn pwm_only_offers_full_speed() {
+        let sys = asus(false, false, true);
+        let driver = AsusWmiDriver::probe(sys.root()).unwrap();
+        assert_eq!(ids(&driver), [PROFILE_FULL_SPEED]);
+        assert!(matches!(driver.set_profile(0), Err(DriverError::Unsupported(_))));
+        assert_eq!(driver.telemetry().unwrap().rpm, Some(4100));
+    }
+}
@@ -0,0 +1,1002 @@
+//! `edgeopt`: inspect and switch fan profiles on the driver picked by `select_driver`.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use edge_optimizer::doctor::{Doctor, DoctorReport};
+use edge_optimizer::driver::{DriverError, FanDriver};
+use edge_optimizer::explain::Explanation;
+use edge_optimizer::manager::FanManager;
+use edge_optimizer::model::{FanCapabilities, FanProfile, FanTelemetry, MaxFanPolicy, ProfileId};
+use edge_optimizer::powercfg::PowerScheme;
+use edge_optimizer::report::JsonDocument;
+use edge_optimizer::state;
+use edge_optimizer::select::{probe_all, select_driver, ProbeAttempt, ProbeReport, SelectedDriver};
+use edge_optimizer::windows_power::{DedicatedPlan, PlanInstall, WindowsPowerPlanDriver, EDGE_OPTIMIZER_PLAN};
+
+const USAGE: &str = "\
+Usage: edgeopt [--driver <name>] [--json] [--dry-run] <command>
+
+Commands:
+  list                 Show the driver's profiles and capabilities
+  get                  Show the current profile and telemetry
+  set <profile>        Switch to a profile by id or name
+  max                  Switch to the maximum-cooling profile (undo with `restore`)
+  restore              Go back to the profile active before `max`, or the default one; on Linux
+                       also puts back the CPU frequency settings found before the first change
+  monitor [--interval <ms>] [--count <n>]
+                       Print telemetry repeatedly (default every 1000 ms until interrupted)
+  drivers              Probe every driver and show which ones work here
+  doctor [--output <file>]
+                       Collect a redacted diagnostics report to attach to issues
+  install-plan         Windows: create (or re-tune) the dedicated Edge Optimizer power plan
+  uninstall-plan       Windows: delete the dedicated power plan; run before removing edgeopt
+
+Options:
+  --driver <name>      Only consider this driver (see `edgeopt drivers`)
+  --json               Print versioned JSON documents (see `report::JsonDocument`)
+  --dry-run            With set/max/restore: show what would be written and checked, change nothing
+  -h, --help           Show this help
+
+Exit codes: 0 success, 1 operation failed, 2 usage error, 3 no usable driver";
+
+/// The requested operation failed.
+const EXIT_FAILED: u8 = 1;
+/// Bad command line.
+const EXIT_USAGE: u8 = 2;
+/// No driver initialized (or the named one did not).
+const EXIT_NO_DRIVER: u8 = 3;
+
+#[derive(Debug)]
+enum Command {
+    List,
+    Get,
+    Set(String),
+    Max,
+    Restore,
+    Monitor { interval: Duration, count: Option<u64> },
+    Drivers,
+    Doctor { output: Option<PathBuf> },
+    InstallPlan,
+    UninstallPlan,
+}
+
+impl Command {
+    /// Name reported as `command` in JSON documents.
+    fn name(&self) -> &'static str {
+        match self {
+            Command::List => "list",
+            Command::Get => "get",
+            Command::Set(_) => "set",
+            Command::Max => "max",
+            Command::Restore => "restore",
+            Command::Monitor { .. } => "monitor",
+            Command::Drivers => "drivers",
+            Command::Doctor { .. } => "doctor",
+            Command::InstallPlan => "install-plan",
+            Command::UninstallPlan => "uninstall-plan",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    driver: Option<String>,
+    json: bool,
+    dry_run: bool,
+    command: Command,
+}
+
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, UsageError> {
+    let mut driver = None;
+    let mut json = false;
+    let mut dry_run = false;
+    let mut interval = Duration::from_millis(1000);
+    let mut count = None;
+    let mut output = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| UsageError(format!("{} needs a value", flag)));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--json" => json = true,
+            "--dry-run" => dry_run = true,
+            "--driver" => driver = Some(value("--driver")?),
+            "--interval" => {
+                let ms = value("--interval")?;
+                let ms: u64 = ms.parse().map_err(|_| UsageError(format!("invalid --interval: {}", ms)))?;
+                interval = Duration::from_millis(ms.max(100));
+            }
+            "--count" => {
+                let n = value("--count")?;
+                // `--count 0` would still print once; leave the flag out to monitor until interrupted.
+                let parsed: u64 = n.parse().map_err(|_| UsageError(format!("invalid --count: {}", n)))?;
+                if parsed == 0 {
+                    return Err(UsageError("--count must be at least 1".into()));
+                }
+                count = Some(parsed);
+            }
+            "--output" => output = Some(PathBuf::from(value("--output")?)),
+            flag if flag.starts_with('-') => return Err(UsageError(format!("unknown option: {}", flag))),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        Some("list") => Command::List,
+        Some("get") => Command::Get,
+        Some("set") => Command::Set(
+            positional
+                .next()
+                .ok_or_else(|| UsageError("set needs a profile id or name".into()))?,
+        ),
+        Some("max") => Command::Max,
+        Some("restore") => Command::Restore,
+        Some("monitor") => Command::Monitor { interval, count },
+        Some("drivers") => Command::Drivers,
+        Some("doctor") => Command::Doctor { output: output.take() },
+        Some("install-plan") => Command::InstallPlan,
+        Some("uninstall-plan") => Command::UninstallPlan,
+        Some(other) => return Err(UsageError(format!("unknown command: {}", other))),
+        None => return Err(UsageError("missing command".into())),
+    };
+    if let Some(extra) = positional.next() {
+        return Err(UsageError(format!("unexpected argument: {}", extra)));
+    }
+
+    if output.is_some() {
+        return Err(UsageError("--output only applies to doctor".into()));
+    }
+    if dry_run && !matches!(command, Command::Set(_) | Command::Max | Command::Restore) {
+        return Err(UsageError("--dry-run only applies to set, max and restore".into()));
+    }
+
+    Ok(Some(Options {
+        driver,
+        json,
+        dry_run,
+        command,
+    }))
+}
+
+/// Parsed options, or the exit code to stop with: success after `--help`, `EXIT_USAGE` after a
+/// bad command line.
+fn options_or_exit(args: impl IntoIterator<Item = String>) -> Result<Options, ExitCode> {
+    match parse_args(args) {
+        Ok(Some(options)) => Ok(options),
+        Ok(None) => {
+            println!("{}", USAGE);
+            Err(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            eprintln!("edgeopt: {}\n\n{}", e, USAGE);
+            Err(ExitCode::from(EXIT_USAGE))
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match options_or_exit(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+
+    if let Command::Drivers = options.command {
+        let report = probe_all(options.driver.as_deref());
+        print_drivers(&report, options.json);
+        return if report.attempts.iter().any(|a| a.result.is_ok()) {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::from(EXIT_NO_DRIVER)
+        };
+    }
+
+    if let Command::Doctor { output } = &options.command {
+        let report = Doctor::new().collect(options.driver.as_deref()).redacted();
+        return match output {
+            Some(path) => match std::fs::write(path, JsonDocument::new("doctor", &report).to_pretty_string() + "\n") {
+                Ok(()) => {
+                    println!("Wrote diagnostics to {}; please attach it to your issue", path.display());
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("edgeopt: cannot write {}: {}", path.display(), e);
+                    ExitCode::from(EXIT_FAILED)
+                }
+            },
+            None => {
+                print_doctor(&report, options.json);
+                ExitCode::SUCCESS
+            }
+        };
+    }
+
+    if let Command::InstallPlan | Command::UninstallPlan = options.command {
+        return run_plan(&options);
+    }
+
+    let (selected, report) = select_driver(options.driver.as_deref());
+    if let (Some(e), false) = (&report.power_config_error, options.json) {
+        eprintln!("edgeopt: warning: power.toml ignored: {}", e);
+    }
+    let Some(selected) = selected else {
+        let message = match &options.driver {
+            Some(name) if report.attempts.is_empty() => format!("unknown driver {:?}", name),
+            _ => "no usable driver".to_string(),
+        };
+        state::record_error(options.command.name(), &DriverError::NotReady(message.clone()));
+        if options.json {
+            let data = DriversData::new(&report);
+            emit(JsonDocument::new(options.command.name(), data).with_error(DriverError::NotReady(message)));
+        } else {
+            eprintln!("edgeopt: {}", message);
+            for attempt in &report.attempts {
+                if let Err(e) = &attempt.result {
+                    eprintln!("  {}: {}", attempt.name, e);
+                }
+            }
+        }
+        return ExitCode::from(EXIT_NO_DRIVER);
+    };
+
+    match run(&options, selected, &report) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            state::record_error(options.command.name(), &e);
+            if options.json {
+                emit(JsonDocument::<()>::failed(options.command.name(), e));
+            } else {
+                eprintln!("edgeopt: {}", e);
+            }
+            ExitCode::from(EXIT_FAILED)
+        }
+    }
+}
+
+fn run(options: &Options, selected: SelectedDriver, report: &ProbeReport) -> Result<(), DriverError> {
+    let driver = selected.name.as_str();
+    let manager = FanManager::new(selected.driver, MaxFanPolicy::default());
+    let command = options.command.name();
+    let previous = PreviousProfile::new();
+
+    match &options.command {
+        Command::List => {
+            let capabilities = manager.capabilities()?;
+            if options.json {
+                let description = selected.description.as_str();
+                emit(JsonDocument::new(command, ListData { driver, description, capabilities }));
+            } else {
+                println!("Driver: {} ({})", driver, selected.description);
+                if !capabilities.supported {
+                    println!("Fan control not supported");
+                }
+                for profile in &capabilities.profiles {
+                    let marker = if profile.is_maximum { " (max)" } else { "" };
+                    println!("  {:>3}  {}{}", profile.id, profile.name, marker);
+                }
+            }
+        }
+        Command::Get => {
+            let caps = manager.capabilities()?;
+            let current = manager.current_profile()?;
+            let telemetry = manager.telemetry().ok();
+            if options.json {
+                let data = GetData {
+                    driver,
+                    profile_id: current,
+                    profile: current.and_then(|id| find_profile(&caps, id)),
+                    telemetry,
+                };
+                emit(JsonDocument::new(command, data));
+            } else {
+                match current {
+                    Some(id) => println!("Profile: {}", profile_label(&caps, id)),
+                    None => println!("Profile: unknown"),
+                }
+                if let Some(telemetry) = &telemetry {
+                    print_telemetry(telemetry);
+                }
+            }
+        }
+        Command::Set(wanted) => {
+            let caps = manager.capabilities()?;
+            let profile = resolve_profile(&caps, wanted)?;
+            if options.dry_run {
+                report_explained(options, &selected.description, driver, profile, manager.explain_profile(profile.id)?, report);
+                return Ok(());
+            }
+            manager.set_profile(profile.id)?;
+            // An explicit choice replaces whatever `max` remembered.
+            previous.clear();
+            report_applied(options.json, command, driver, profile);
+        }
+        Command::Max if options.dry_run => {
+            let explanation = manager.explain_max_profile()?;
+            let caps = manager.capabilities()?;
+            let profile = applied_profile(&caps, explanation.profile)?;
+            report_explained(options, &selected.description, driver, profile, explanation, report);
+        }
+        Command::Restore if options.dry_run => {
+            let explanation = explain_cpufreq_restore(manager.explain_restore_profile(previous.load(driver))?)?;
+            let caps = manager.capabilities()?;
+            let profile = applied_profile(&caps, explanation.profile)?;
+            report_explained(options, &selected.description, driver, profile, explanation, report);
+        }
+        Command::Max => {
+            let caps = manager.capabilities()?;
+            let applied = apply_max(&manager, &previous, driver)?;
+            report_applied(options.json, command, driver, applied_profile(&caps, applied)?);
+        }
+        Command::Restore => {
+            let applied = apply_restore(&manager, &previous, driver)?;
+            let cpufreq_restored = restore_cpufreq()?;
+            let caps = manager.capabilities()?;
+            report_applied(options.json, command, driver, applied_profile(&caps, applied)?);
+            if cpufreq_restored && !options.json {
+                println!("Original CPU frequency settings restored");
+            }
+        }
+        Command::Monitor { interval, count } => {
+            let mut remaining = *count;
+            loop {
+                let telemetry = manager.telemetry()?;
+                if options.json {
+                    // One document per line so the stream can be consumed incrementally.
+                    println!("{}", JsonDocument::new(command, MonitorData { driver, telemetry }).to_line());
+                } else {
+                    print_telemetry_line(&telemetry);
+                }
+                if let Some(n) = remaining.as_mut() {
+                    *n = n.saturating_sub(1);
+                    if *n == 0 {
+                        break;
+                    }
+                }
+                thread::sleep(*interval);
+            }
+        }
+        Command::Drivers | Command::Doctor { .. } | Command::InstallPlan | Command::UninstallPlan => {
+            unreachable!("handled before driver selection")
+        }
+    }
+    Ok(())
+}
+
+/// `max`. The current profile is saved first: a failed ramp check still leaves the max profile
+/// applied. A repeated `max` keeps the profile remembered by the first one instead of saving
+/// max itself.
+fn apply_max<D: FanDriver>(manager: &FanManager<D>, previous: &PreviousProfile, driver: &str) -> Result<ProfileId, DriverError> {
+    let caps = manager.capabilities()?;
+    let current = manager.current_profile().ok().flatten();
+    let already_max = current.and_then(|id| find_profile(&caps, id)).is_some_and(|p| p.is_maximum);
+    if !already_max && previous.load(driver).is_none() {
+        if let Err(e) = previous.save(driver, current) {
+            eprintln!("edgeopt: warning: could not remember the previous profile: {}", e);
+        }
+    }
+    manager.set_max_profile()
+}
+
+/// `restore`: back to the profile `max` remembered for this driver, else the default.
+fn apply_restore<D: FanDriver>(manager: &FanManager<D>, previous: &PreviousProfile, driver: &str) -> Result<ProfileId, DriverError> {
+    let applied = manager.restore_profile(previous.load(driver))?;
+    previous.clear();
+    Ok(applied)
+}
+
+/// `install-plan` and `uninstall-plan`: manage the dedicated power plan directly through
+/// powercfg, whichever driver would be selected.
+fn run_plan(options: &Options) -> ExitCode {
+    let command = options.command.name();
+    let result = WindowsPowerPlanDriver::new().and_then(|powercfg| match options.command {
+        Command::InstallPlan => powercfg.install_plan(&DedicatedPlan::max_cooling()).map(|outcome| {
+            let base = match outcome {
+                PlanInstall::Created { base } => Some(base),
+                PlanInstall::Retuned => None,
+            };
+            PlanData {
+                plan: EDGE_OPTIMIZER_PLAN,
+                installed: true,
+                changed: true,
+                base,
+            }
+        }),
+        _ => powercfg.uninstall_plan().map(|removed| PlanData {
+            plan: EDGE_OPTIMIZER_PLAN,
+            installed: false,
+            changed: removed,
+            base: None,
+        }),
+    });
+
+    match result {
+        Ok(data) => {
+            if options.json {
+                emit(JsonDocument::new(command, &data));
+            } else {
+                match (&data.base, data.installed, data.changed) {
+                    (Some(base), ..) => println!("Installed power plan {} (copy of {})", data.plan, base.name),
+                    (None, true, _) => println!("Updated power plan {}", data.plan),
+                    (None, false, true) => println!("Removed power plan {}", data.plan),
+                    (None, false, false) => println!("Power plan {} is not installed", data.plan),
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            state::record_error(command, &e);
+            let code = if matches!(e, DriverError::NotReady(_)) { EXIT_NO_DRIVER } else { EXIT_FAILED };
+            if options.json {
+                emit(JsonDocument::<()>::failed(command, e));
+            } else {
+                eprintln!("edgeopt: {}", e);
+            }
+            ExitCode::from(code)
+        }
+    }
+}
+
+/// Match by numeric id first, then by case-insensitive name.
+fn resolve_profile<'a>(caps: &'a FanCapabilities, wanted: &str) -> Result<&'a FanProfile, DriverError> {
+    let by_id = wanted
+        .parse::<ProfileId>()
+        .ok()
+        .and_then(|id| caps.profiles.iter().find(|p| p.id == id));
+    by_id
+        .or_else(|| caps.profiles.iter().find(|p| p.name.eq_ignore_ascii_case(wanted)))
+        .ok_or_else(|| {
+            let known: Vec<String> = caps.profiles.iter().map(|p| format!("{} ({})", p.name, p.id)).collect();
+            DriverError::Unsupported(format!("no profile {:?}; available: {}", wanted, known.join(", ")))
+        })
+}
+
+fn find_profile(caps: &FanCapabilities, id: ProfileId) -> Option<&FanProfile> {
+    caps.profiles.iter().find(|p| p.id == id)
+}
+
+/// The profile a `set`/`max`/`restore` applied. A driver that applies a profile it doesn't
+/// advertise is broken; report that rather than guessing a name.
+fn applied_profile(caps: &FanCapabilities, id: ProfileId) -> Result<&FanProfile, DriverError> {
+    find_profile(caps, id).ok_or_else(|| {
+        DriverError::Internal(format!("driver applied profile {}, which its capabilities do not list", id))
+    })
+}
+
+fn profile_label(caps: &FanCapabilities, id: ProfileId) -> String {
+    match find_profile(caps, id) {
+        Some(profile) => format!("{} (id={})", profile.name, id),
+        None => format!("id={}", id),
+    }
+}
+
+fn report_applied(json: bool, command: &str, driver: &str, profile: &FanProfile) {
+    if json {
+        emit(JsonDocument::new(command, AppliedData { driver, profile }));
+    } else {
+        println!("Profile set to {} (id={})", profile.name, profile.id);
+    }
+}
+
+fn report_explained(options: &Options, description: &str, driver: &str, profile: &FanProfile, mut explanation: Explanation, report: &ProbeReport) {
+    // Selecting the driver already ran these probes; say so rather than claim nothing happened.
+    let mut notes = report.notes();
+    notes.append(&mut explanation.notes);
+    explanation.notes = notes;
+
+    if options.json {
+        let data = DryRunData {
+            driver,
+            description,
+            dry_run: true,
+            profile,
+            explanation,
+            probes: &report.attempts,
+        };
+        emit(JsonDocument::new(options.command.name(), data));
+        return;
+    }
+
+    println!("Dry run: no profile was changed");
+    println!("Driver: {} ({})", driver, description);
+    println!("Profile: {} (id={})", profile.name, profile.id);
+    for note in &explanation.notes {
+        println!("Note: {}", note);
+    }
+    println!("Actions:");
+    if explanation.actions.is_empty() {
+        println!("  (none)");
+    }
+    for (index, action) in explanation.actions.iter().enumerate() {
+        println!("  {}. {}", index + 1, action);
+    }
+    if !explanation.verification.is_empty() {
+        println!("Verification:");
+        for check in &explanation.verification {
+            println!("  - {}", check);
+        }
+    }
+}
+
+fn print_telemetry(telemetry: &FanTelemetry) {
+    if let Some(rpm) = telemetry.rpm {
+        println!("Fan speed: {} RPM", rpm);
+    }
+    if let Some(duty) = telemetry.fan_duty_percent {
+        println!("Fan duty: {}%", duty);
+    }
+    if let Some(temp) = telemetry.temperature_c {
+        println!("Temperature: {:.1}°C", temp);
+    }
+}
+
+fn print_telemetry_line(telemetry: &FanTelemetry) {
+    let field = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+    println!(
+        "rpm={}  duty={}  temp={}",
+        field(telemetry.rpm.map(|rpm| rpm.to_string())),
+        field(telemetry.fan_duty_percent.map(|duty| format!("{}%", duty))),
+        field(telemetry.temperature_c.map(|temp| format!("{:.1}°C", temp))),
+    );
+}
+
+fn print_drivers(report: &ProbeReport, json: bool) {
+    if json {
+        emit(JsonDocument::new("drivers", DriversData::new(report)));
+        return;
+    }
+    print_probes(&report.attempts);
+    if let Some(e) = &report.power_config_error {
+        println!("power.toml ignored: {}", e);
+    }
+}
+
+fn print_probes(attempts: &[ProbeAttempt]) {
+    for attempt in attempts {
+        match &attempt.result {
+            Ok(()) => println!("  ok    {:<16} {}", attempt.name, attempt.description),
+            Err(e) => println!("  --    {:<16} {}: {}", attempt.name, attempt.description, e),
+        }
+        for line in &attempt.details {
+            println!("          {}", line);
+        }
+    }
+}
+
+fn print_doctor(report: &DoctorReport, json: bool) {
+    if json {
+        emit(JsonDocument::new("doctor", report));
+        return;
+    }
+    let kernel = report.platform.kernel.as_deref().unwrap_or("-");
+    println!("Platform: {} {} (kernel {})", report.platform.os, report.platform.arch, kernel);
+    let dmi = |field: &str| report.dmi.get(field).map(String::as_str).unwrap_or("-");
+    println!("Machine: {} {} (BIOS {})", dmi("sys_vendor"), dmi("product_name"), dmi("bios_version"));
+    match report.permissions.elevated {
+        Some(true) => println!("Privileges: elevated"),
+        Some(false) => println!("Privileges: not elevated"),
+        None => println!("Privileges: unknown"),
+    }
+
+    println!("Drivers:");
+    print_probes(&report.probes);
+    if !report.modules.is_empty() {
+        let present: Vec<&str> = report
+            .modules
+            .iter()
+            .filter(|m| m.loaded || m.builtin)
+            .map(|m| m.name.as_str())
+            .collect();
+        println!("Modules: {}", if present.is_empty() { "none of the watched modules".to_string() } else { present.join(", ") });
+    }
+    println!("Attributes:");
+    if report.attributes.is_empty() {
+        println!("  (none found)");
+    }
+    for attribute in &report.attributes {
+        let access = match (attribute.readable, attribute.writable) {
+            (true, true) => "rw",
+            (true, false) => "r-",
+            (false, true) => "-w",
+            (false, false) => "--",
+        };
+        let value = attribute.value.as_deref().unwrap_or("-");
+        println!("  {} {} = {}", access, attribute.path, value);
+    }
+    if !report.recent_errors.is_empty() {
+        println!("Recent errors:");
+        for line in &report.recent_errors {
+            println!("  {}", line);
+        }
+    }
+    if !report.kernel_messages.is_empty() {
+        println!("Kernel messages:");
+        for line in &report.kernel_messages {
+            println!("  {}", line);
+        }
+    }
+    println!("Findings:");
+    if report.findings.is_empty() {
+        println!("  (none)");
+    }
+    for finding in &report.findings {
+        println!("  - {}", finding);
+    }
+}
+
+fn emit<T: Serialize>(document: JsonDocument<T>) {
+    println!("{}", document.to_pretty_string());
+}
+
+/// `data` of `list`.
+#[derive(Serialize)]
+struct ListData<'a> {
+    driver: &'a str,
+    description: &'a str,
+    capabilities: FanCapabilities,
+}
+
+/// `data` of `get`; `profile` is null when the id is not among the driver's profiles.
+#[derive(Serialize)]
+struct GetData<'a> {
+    driver: &'a str,
+    profile_id: Option<ProfileId>,
+    profile: Option<&'a FanProfile>,
+    telemetry: Option<FanTelemetry>,
+}
+
+/// `data` of `set`, `max` and `restore`.
+#[derive(Serialize)]
+struct AppliedData<'a> {
+    driver: &'a str,
+    profile: &'a FanProfile,
+}
+
+/// `data` of `set`, `max` and `restore` with `--dry-run`. `probes` lists the candidates driver
+/// selection initialized to get there.
+#[derive(Serialize)]
+struct DryRunData<'a> {
+    driver: &'a str,
+    description: &'a str,
+    dry_run: bool,
+    profile: &'a FanProfile,
+    explanation: Explanation,
+    probes: &'a [ProbeAttempt],
+}
+
+/// `data` of each `monitor` line.
+#[derive(Serialize)]
+struct MonitorData<'a> {
+    driver: &'a str,
+    telemetry: FanTelemetry,
+}
+
+/// `data` of `install-plan` and `uninstall-plan`. `base` is the scheme a new plan was copied
+/// from; `changed` is false when there was no plan to uninstall.
+#[derive(Serialize)]
+struct PlanData {
+    plan: &'static str,
+    installed: bool,
+    changed: bool,
+    base: Option<PowerScheme>,
+}
+
+/// `data` of `drivers`, and of any command that found no usable driver.
+#[derive(Serialize)]
+struct DriversData<'a> {
+    probes: &'a [ProbeAttempt],
+    power_config_error: Option<&'a DriverError>,
+}
+
+impl<'a> DriversData<'a> {
+    fn new(report: &'a ProbeReport) -> Self {
+        Self {
+            probes: &report.attempts,
+            power_config_error: report.power_config_error.as_ref(),
+        }
+    }
+}
+
+/// Where `max` remembers the profile to go back to: the driver name and profile id, one per line.
+struct PreviousProfile {
+    path: Option<PathBuf>,
+}
+
+impl PreviousProfile {
+    fn new() -> Self {
+        Self {
+            path: state::state_dir().map(|dir| dir.join("previous_profile")),
+        }
+    }
+
+    #[cfg(test)]
+    fn at(path: &std::path::Path) -> Self {
+        Self {
+            path: Some(path.to_path_buf()),
+        }
+    }
+
+    fn save(&self, driver: &str, previous: Option<ProfileId>) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        // Nothing to remember if the driver can't report its profile; restore uses the default.
+        let Some(previous) = previous else {
+            self.clear();
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, format!("{}\n{}\n", driver, previous))
+    }
+
+    /// The remembered profile, if it was saved for the same driver.
+    fn load(&self, driver: &str) -> Option<ProfileId> {
+        let text = std::fs::read_to_string(self.path.as_ref()?).ok()?;
+        let mut lines = text.lines();
+        (lines.next()? == driver).then_some(())?;
+        lines.next()?.trim().parse().ok()
+    }
+
+    fn clear(&self) {
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Put back the cpufreq settings saved before the first cpufreq change, whichever driver is
+/// selected now. `true` if there were any.
+#[cfg(target_os = "linux")]
+fn restore_cpufreq() -> Result<bool, DriverError> {
+    match state::state_dir() {
+        Some(dir) => edge_optimizer::cpufreq::restore_saved(&dir),
+        None => Ok(false),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn restore_cpufreq() -> Result<bool, DriverError> {
+    Ok(false)
+}
+
+/// Dry run of `restore_cpufreq`, added to the driver's explanation.
+#[cfg(target_os = "linux")]
+fn explain_cpufreq_restore(explanation: Explanation) -> Result<Explanation, DriverError> {
+    let saved = match state::state_dir() {
+        Some(dir) => edge_optimizer::cpufreq::saved_snapshot(&dir)?,
+        None => None,
+    };
+    Ok(match saved {
+        Some(snapshot) => explanation
+            .note("restoring the CPU frequency settings saved before the first change")
+            .writes(snapshot.writes()),
+        None => explanation,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn explain_cpufreq_restore(explanation: Explanation) -> Result<Explanation, DriverError> {
+    Ok(explanation)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, UsageError> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn options(args: &[&str]) -> Options {
+        parse(args).expect("valid command line").expect("not --help")
+    }
+
+    fn usage_error(args: &[&str]) -> String {
+        parse(args).expect_err("usage error").0
+    }
+
+    /// Profiles 0 "Default", 1 "Quiet" and 2 "Max"; remembers the last one set.
+    struct StubFan(Cell<Option<ProfileId>>);
+
+    impl FanDriver for StubFan {
+        fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+            let profile = |id, name: &str| FanProfile {
+                id,
+                name: name.into(),
+                is_maximum: id == 2,
+            };
+            Ok(FanCapabilities {
+                supported: true,
+                profiles: vec![profile(0, "Default"), profile(1, "Quiet"), profile(2, "Max")],
+            })
+        }
+
+        fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+            Ok(self.0.get())
+        }
+
+        fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+            self.0.set(Some(profile));
+            Ok(())
+        }
+
+        fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+            Err(DriverError::Unsupported("no telemetry".into()))
+        }
+    }
+
+    fn manager(current: Option<ProfileId>) -> FanManager<StubFan> {
+        let policy = MaxFanPolicy {
+            settle_timeout: Duration::ZERO,
+            min_rpm_delta: None,
+            max_safe_temp_c: None,
+        };
+        FanManager::new(StubFan(Cell::new(current)), policy)
+    }
+
+    /// A `previous_profile` file in a fresh directory, removed on drop.
+    struct StateFile(PathBuf);
+
+    impl StateFile {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("edgeopt-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            Self(dir.join("previous_profile"))
+        }
+    }
+
+    impl Drop for StateFile {
+        fn drop(&mut self) {
+            if let Some(dir) = self.0.parent() {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_every_command() {
+        assert!(matches!(options(&["list"]).command, Command::List));
+        assert!(matches!(options(&["get"]).command, Command::Get));
+        assert!(matches!(options(&["set", "quiet"]).command, Command::Set(ref name) if name == "quiet"));
+        assert!(matches!(options(&["max"]).command, Command::Max));
+        assert!(matches!(options(&["restore"]).command, Command::Restore));
+        assert!(matches!(
+            options(&["monitor"]).command,
+            Command::Monitor { interval, count: None } if interval == Duration::from_millis(1000)
+        ));
+        assert!(matches!(options(&["drivers"]).command, Command::Drivers));
+        assert!(matches!(options(&["doctor"]).command, Command::Doctor { output: None }));
+        assert!(matches!(options(&["install-plan"]).command, Command::InstallPlan));
+        assert!(matches!(options(&["uninstall-plan"]).command, Command::UninstallPlan));
+    }
+
+    #[test]
+    fn driver_and_json_apply_to_every_command() {
+        for command in ["list", "get", "max", "restore", "monitor", "drivers", "doctor", "install-plan", "uninstall-plan"] {
+            let parsed = options(&["--driver", "thinkpad", command, "--json"]);
+            assert_eq!(parsed.driver.as_deref(), Some("thinkpad"), "{}", command);
+            assert!(parsed.json, "{}", command);
+            assert!(!parsed.dry_run, "{}", command);
+            assert_eq!(parsed.command.name(), command);
+        }
+
+        let parsed = options(&["set", "1"]);
+        assert_eq!(parsed.driver, None);
+        assert!(!parsed.json);
+    }
+
+    #[test]
+    fn parses_command_options() {
+        let parsed = options(&["monitor", "--interval", "500", "--count", "3"]);
+        assert!(matches!(
+            parsed.command,
+            Command::Monitor { interval, count: Some(3) } if interval == Duration::from_millis(500)
+        ));
+        // Intervals are clamped so a typo can't spin the telemetry reads.
+        let parsed = options(&["monitor", "--interval", "1"]);
+        assert!(matches!(parsed.command, Command::Monitor { interval, .. } if interval == Duration::from_millis(100)));
+
+        let parsed = options(&["doctor", "--output", "report.json"]);
+        assert!(matches!(parsed.command, Command::Doctor { output: Some(ref path) } if path == &PathBuf::from("report.json")));
+
+        for command in ["set", "max", "restore"] {
+            let mut args = vec!["--dry-run", command];
+            if command == "set" {
+                args.push("quiet");
+            }
+            assert!(options(&args).dry_run, "{}", command);
+        }
+    }
+
+    #[test]
+    fn rejects_zero_count() {
+        assert_eq!(usage_error(&["monitor", "--count", "0"]), "--count must be at least 1");
+    }
+
+    #[test]
+    fn reports_usage_errors() {
+        assert_eq!(usage_error(&[]), "missing command");
+        assert_eq!(usage_error(&["spin"]), "unknown command: spin");
+        assert_eq!(usage_error(&["set"]), "set needs a profile id or name");
+        assert_eq!(usage_error(&["list", "extra"]), "unexpected argument: extra");
+        assert_eq!(usage_error(&["--fast", "list"]), "unknown option: --fast");
+        assert_eq!(usage_error(&["list", "--driver"]), "--driver needs a value");
+        assert_eq!(usage_error(&["monitor", "--interval", "soon"]), "invalid --interval: soon");
+        assert_eq!(usage_error(&["monitor", "--count", "-1"]), "invalid --count: -1");
+        assert_eq!(usage_error(&["list", "--output", "x.json"]), "--output only applies to doctor");
+        assert_eq!(usage_error(&["--dry-run", "list"]), "--dry-run only applies to set, max and restore");
+    }
+
+    #[test]
+    fn maps_usage_errors_and_help_to_exit_codes() {
+        let code = |args: &[&str]| options_or_exit(args.iter().map(|arg| arg.to_string())).err();
+
+        assert_eq!(code(&["spin"]), Some(ExitCode::from(EXIT_USAGE)));
+        assert_eq!(code(&["monitor", "--count", "0"]), Some(ExitCode::from(EXIT_USAGE)));
+        assert_eq!(code(&["--help"]), Some(ExitCode::SUCCESS));
+        assert_eq!(code(&["list"]), None);
+        assert_eq!(EXIT_USAGE, 2);
+        assert_eq!(EXIT_FAILED, 1);
+        assert_eq!(EXIT_NO_DRIVER, 3);
+    }
+
+    #[test]
+    fn max_then_restore_round_trips_through_the_state_file() {
+        let state = StateFile::new("round-trip");
+        let previous = PreviousProfile::at(&state.0);
+        let manager = manager(Some(1));
+
+        assert_eq!(apply_max(&manager, &previous, "stub").unwrap(), 2);
+        assert_eq!(std::fs::read_to_string(&state.0).unwrap(), "stub\n1\n");
+        // A second `max` must not overwrite the remembered profile with max itself.
+        assert_eq!(apply_max(&manager, &previous, "stub").unwrap(), 2);
+        assert_eq!(previous.load("stub"), Some(1));
+
+        assert_eq!(apply_restore(&manager, &previous, "stub").unwrap(), 1);
+        assert_eq!(manager.current_profile().unwrap(), Some(1));
+        assert!(!state.0.exists());
+    }
+
+    #[test]
+    fn restore_ignores_profiles_saved_for_another_driver() {
+        let state = StateFile::new("other-driver");
+        let previous = PreviousProfile::at(&state.0);
+        previous.save("other", Some(1)).unwrap();
+
+        let manager = manager(Some(2));
+        assert_eq!(previous.load("stub"), None);
+        assert_eq!(apply_restore(&manager, &previous, "stub").unwrap(), 0);
+        assert!(!state.0.exists());
+    }
+
+    #[test]
+    fn max_without_a_readable_profile_remembers_nothing() {
+        let state = StateFile::new("unknown-current");
+        let previous = PreviousProfile::at(&state.0);
+
+        assert_eq!(apply_max(&manager(None), &previous, "stub").unwrap(), 2);
+        assert!(!state.0.exists());
+    }
+}
@@ -1,17 +1,6 @@
 use edge_optimizer::manager::FanManager;
 use edge_optimizer::model::MaxFanPolicy;
-
-#[cfg(windows)]
-use edge_optimizer::hp::HpOmenDriver;
-
-#[cfg(windows)]
-use edge_optimizer::hp_wmi::HpWmiDriver;
-
-#[cfg(windows)]
-use edge_optimizer::windows_power::WindowsPowerPlanDriver;
-
-#[cfg(not(windows))]
-use edge_optimizer::driver::UnsupportedDriver;
+use edge_optimizer::select::select_driver;
 
 #[cfg(windows)]
 fn is_elevated() -> bool {
@@ -43,21 +32,24 @@ fn is_elevated() -> bool {
 
 #[cfg(windows)]
 fn elevate_and_restart() -> ! {
+    use edge_optimizer::command::{CommandRunner, CommandSpec, SystemRunner};
     use std::env;
-    use std::process::Command;
 
     println!("⚠ Administrator privileges required for HP WMI BIOS access");
     println!("→ Attempting to restart with elevation...\n");
 
     let exe_path = env::current_exe().expect("Failed to get exe path");
     
-    let status = Command::new("powershell")
-        .args(&[
-            "-NoProfile",
-            "-Command",
-            &format!("Start-Process -FilePath '{}' -Verb RunAs -Wait", exe_path.display()),
-        ])
-        .status();
+    // No timeout: -Wait blocks until the elevated instance finishes its whole run.
+    let status = SystemRunner.run(
+        &CommandSpec::new("powershell")
+            .args([
+                "-NoProfile".to_string(),
+                "-Command".to_string(),
+                format!("Start-Process -FilePath '{}' -Verb RunAs -Wait", exe_path.display()),
+            ])
+            .timeout(None),
+    );
 
     match status {
         Ok(_) => std::process::exit(0),
@@ -78,48 +70,25 @@ fn main() {
         elevate_and_restart();
     }
 
-    #[cfg(windows)]
-    {
-        // Try HP WMI BIOS method first (works on HP Victus/OMEN)
-        match HpWmiDriver::new() {
-            Ok(driver) => {
-                println!("✓ Using HP WMI BIOS thermal control\n");
-                run_with_driver(driver);
-            }
-            Err(wmi_err) => {
-                println!("⚠ HP WMI not available: {}", wmi_err);
-                
-                // Try HP-specific DLL driver
-                match HpOmenDriver::new() {
-                    Ok(driver) => {
-                        println!("✓ Using HP thermal control (NativeRpcClient.dll)\n");
-                        run_with_driver(driver);
-                    }
-                    Err(hp_err) => {
-                        println!("⚠ HP DLL control not available: {}", hp_err);
-                        println!("→ Falling back to Windows Power Plans\n");
-                        
-                        match WindowsPowerPlanDriver::new() {
-                            Ok(driver) => run_with_driver(driver),
-                            Err(e) => {
-                                eprintln!("✗ Failed to initialize any driver: {e}");
-                                std::process::exit(1);
-                            }
-                        }
-                    }
-                }
-            }
+    let (selected, report) = select_driver(None);
+    for attempt in &report.attempts {
+        if let Err(e) = &attempt.result {
+            println!("⚠ {} not available: {}", attempt.description, e);
         }
     }
 
-    #[cfg(not(windows))]
-    {
-        eprintln!("✗ Only Windows is supported");
-        std::process::exit(1);
+    match selected {
+        Some(selected) => {
+            println!("✓ Using {}\n", selected.description);
+            run_with_driver(selected.driver);
+        }
+        None => {
+            eprintln!("✗ Failed to initialize any driver");
+            std::process::exit(1);
+        }
     }
 }
 
-#[cfg(windows)]
 fn run_with_driver<D: edge_optimizer::driver::FanDriver>(driver: D) {
     let policy = MaxFanPolicy::default();
     let manager = FanManager::new(driver, policy);
@@ -158,6 +127,9 @@ fn run_with_driver<D: edge_optimizer::driver::FanDriver>(driver: D) {
                 if let Some(rpm) = telemetry.rpm {
                     println!("  Fan speed: {} RPM", rpm);
                 }
+                if let Some(duty) = telemetry.fan_duty_percent {
+                    println!("  Fan duty: {}%", duty);
+                }
                 if let Some(temp) = telemetry.temperature_c {
                     println!("  Temperature: {:.1}°C", temp);
                 }
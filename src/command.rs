@@ -0,0 +1,243 @@
+//! Process execution behind a trait so shelled-out drivers (PowerShell, powercfg) can enforce
+//! timeouts in production and be driven by scripted output in tests.
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::driver::DriverError;
+
+/// Default limit for a single command; PowerShell can stall for many seconds on WMI calls.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A command to run: program, arguments, optional stdin and timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub stdin: Option<String>,
+    /// `None` waits indefinitely (e.g. an elevated relaunch that runs the whole tool).
+    pub timeout: Option<Duration>,
+}
+
+impl CommandSpec {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            stdin: None,
+            timeout: Some(DEFAULT_TIMEOUT),
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn stdin(mut self, input: impl Into<String>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Captured result of a finished command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// Exit code; `None` if the process was terminated by a signal.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Exit code 0 with the given stdout.
+    pub fn ok(stdout: impl Into<String>) -> Self {
+        Self {
+            code: Some(0),
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+
+    /// Non-zero exit with the given stderr.
+    pub fn failed(code: i32, stderr: impl Into<String>) -> Self {
+        Self {
+            code: Some(code),
+            stdout: String::new(),
+            stderr: stderr.into(),
+        }
+    }
+}
+
+/// Runs commands. Spawn failures are `NotReady`; timeouts are `Io`.
+pub trait CommandRunner {
+    fn run(&self, command: &CommandSpec) -> Result<CommandOutput, DriverError>;
+}
+
+/// Runs real processes, killing them if they exceed their timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, command: &CommandSpec) -> Result<CommandOutput, DriverError> {
+        let mut child = Command::new(&command.program)
+            .args(&command.args)
+            .stdin(if command.stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| DriverError::NotReady(format!("failed to run {}: {}", command.program, e)))?;
+
+        // Feed stdin and drain both pipes on threads so a chatty child can't block on a full pipe.
+        if let (Some(input), Some(mut stdin)) = (command.stdin.clone(), child.stdin.take()) {
+            thread::spawn(move || {
+                let _ = stdin.write_all(input.as_bytes());
+            });
+        }
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+
+        let started = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => {}
+                Err(e) => return Err(DriverError::Io(format!("waiting for {}: {}", command.program, e))),
+            }
+            if command.timeout.is_some_and(|limit| started.elapsed() >= limit) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(DriverError::Io(format!(
+                    "{} timed out after {} ms",
+                    command.program,
+                    started.elapsed().as_millis()
+                )));
+            }
+            thread::sleep(Duration::from_millis(20));
+        };
+
+        Ok(CommandOutput {
+            code: status.code(),
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    })
+}
+
+/// Replays queued results in order and records every command it was asked to run.
+#[derive(Debug, Default)]
+pub struct ScriptedRunner {
+    script: Mutex<VecDeque<(String, Result<CommandOutput, DriverError>)>>,
+    calls: Mutex<Vec<CommandSpec>>,
+}
+
+impl ScriptedRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the result for the next command, which must invoke `program`.
+    pub fn expect(self, program: &str, result: Result<CommandOutput, DriverError>) -> Self {
+        self.script.lock().unwrap().push_back((program.to_string(), result));
+        self
+    }
+
+    /// Commands run so far, in order.
+    pub fn calls(&self) -> Vec<CommandSpec> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Whether every queued result has been consumed.
+    pub fn is_exhausted(&self) -> bool {
+        self.script.lock().unwrap().is_empty()
+    }
+}
+
+impl CommandRunner for ScriptedRunner {
+    fn run(&self, command: &CommandSpec) -> Result<CommandOutput, DriverError> {
+        self.calls.lock().unwrap().push(command.clone());
+        match self.script.lock().unwrap().pop_front() {
+            Some((program, result)) if program == command.program => result,
+            Some((program, _)) => Err(DriverError::Internal(format!(
+                "scripted runner expected {}, got {}",
+                program, command.program
+            ))),
+            None => Err(DriverError::Internal(format!("unexpected command: {}", command.program))),
+        }
+    }
+}
+
+impl<R: CommandRunner + ?Sized> CommandRunner for &R {
+    fn run(&self, command: &CommandSpec) -> Result<CommandOutput, DriverError> {
+        (**self).run(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn system_runner_kills_commands_past_their_timeout() {
+        let started = Instant::now();
+        let result = SystemRunner.run(&CommandSpec::new("sleep").arg("10").timeout(Some(Duration::from_millis(200))));
+        assert!(matches!(result, Err(DriverError::Io(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn system_runner_feeds_stdin_and_captures_output() {
+        let output = SystemRunner.run(&CommandSpec::new("cat").stdin("piped\n")).unwrap();
+        assert_eq!(output, CommandOutput::ok("piped\n"));
+        let failed = SystemRunner.run(&CommandSpec::new("sh").args(["-c", "echo oops >&2; exit 3"])).unwrap();
+        assert_eq!(failed, CommandOutput::failed(3, "oops\n"));
+    }
+
+    #[test]
+    fn missing_program_is_not_ready() {
+        let result = SystemRunner.run(&CommandSpec::new("edge-optimizer-no-such-program"));
+        assert!(matches!(result, Err(DriverError::NotReady(_))));
+    }
+
+    #[test]
+    fn scripted_runner_rejects_unexpected_programs() {
+        let runner = ScriptedRunner::new().expect("powercfg", Ok(CommandOutput::ok("")));
+        assert!(matches!(runner.run(&CommandSpec::new("powershell")), Err(DriverError::Internal(_))));
+        assert!(matches!(runner.run(&CommandSpec::new("powercfg")), Err(DriverError::Internal(_))));
+        assert_eq!(runner.calls().len(), 2);
+    }
+}
@@ -0,0 +1,447 @@
+//! Declarative sysfs-mapped driver described by a TOML file.
+//! Covers boards whose fan control is just "write value X to file Y for profile Z":
+//!
+//! ```toml
+//! name = "Raspberry Pi 5 active cooler"
+//!
+//! [probe]
+//! path = "/sys/firmware/devicetree/base/model"
+//! contains = "Raspberry Pi 5"
+//!
+//! [[profiles]]
+//! id = 0
+//! name = "Auto"
+//! writes = [{ path = "/sys/class/hwmon/hwmon2/pwm1_enable", value = "2" }]
+//!
+//! [[profiles]]
+//! id = 1
+//! name = "Max"
+//! maximum = true
+//! writes = [
+//!     { path = "/sys/class/hwmon/hwmon2/pwm1_enable", value = "1" },
+//!     { path = "/sys/class/hwmon/hwmon2/pwm1", value = "255" },
+//! ]
+//!
+//! [readback]
+//! path = "/sys/class/hwmon/hwmon2/pwm1_enable"
+//! values = { "2" = 0, "1" = 1 }
+//!
+//! [telemetry]
+//! rpm = { path = "/sys/class/hwmon/hwmon2/fan1_input" }
+//! temperature_c = { path = "/sys/class/thermal/thermal_zone0/temp", scale = 0.001 }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::sysfs;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read driver config {0}: {1}")]
+    Read(PathBuf, String),
+    #[error("invalid driver config: {0}")]
+    Parse(String),
+    #[error("driver config rejected: {0}")]
+    Invalid(String),
+}
+
+impl From<ConfigError> for DriverError {
+    fn from(e: ConfigError) -> Self {
+        match e {
+            ConfigError::Read(path, msg) => DriverError::Io(format!("{}: {}", path.display(), msg)),
+            ConfigError::Parse(msg) | ConfigError::Invalid(msg) => DriverError::Internal(msg),
+        }
+    }
+}
+
+/// Top-level layout of a driver config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DriverConfig {
+    pub name: String,
+    pub probe: ProbeConfig,
+    pub profiles: Vec<ProfileConfig>,
+    pub readback: Option<ReadbackConfig>,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+}
+
+/// The driver applies only if `path` exists (and, if given, its contents contain `contains`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProbeConfig {
+    pub path: PathBuf,
+    pub contains: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    pub id: ProfileId,
+    pub name: String,
+    #[serde(default)]
+    pub maximum: bool,
+    /// Applied in order when the profile is selected.
+    pub writes: Vec<WriteConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WriteConfig {
+    pub path: PathBuf,
+    pub value: String,
+}
+
+/// Maps the trimmed contents of `path` back to a profile id.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReadbackConfig {
+    pub path: PathBuf,
+    pub values: BTreeMap<String, ProfileId>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetryConfig {
+    pub rpm: Option<ScaledPath>,
+    pub temperature_c: Option<ScaledPath>,
+    pub fan_duty_percent: Option<ScaledPath>,
+}
+
+/// A numeric attribute; the raw value is multiplied by `scale` (default 1.0).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScaledPath {
+    pub path: PathBuf,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl DriverConfig {
+    pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e.to_string()))?;
+        Self::from_toml_str(&text)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.profiles.is_empty() {
+            return Err(ConfigError::Invalid(format!("{}: no profiles declared", self.name)));
+        }
+        for (index, profile) in self.profiles.iter().enumerate() {
+            if self.profiles[..index].iter().any(|p| p.id == profile.id) {
+                return Err(ConfigError::Invalid(format!("{}: duplicate profile id {}", self.name, profile.id)));
+            }
+            if profile.writes.is_empty() {
+                return Err(ConfigError::Invalid(format!("{}: profile {} has no writes", self.name, profile.id)));
+            }
+        }
+        if self.profiles.iter().filter(|p| p.maximum).count() > 1 {
+            return Err(ConfigError::Invalid(format!("{}: more than one maximum profile", self.name)));
+        }
+        if let Some(readback) = &self.readback {
+            if let Some(id) = readback.values.values().find(|id| !self.profiles.iter().any(|p| p.id == **id)) {
+                return Err(ConfigError::Invalid(format!("{}: readback maps to unknown profile {}", self.name, id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `FanDriver` backed by a `DriverConfig`.
+pub struct ConfigDriver {
+    config: DriverConfig,
+    /// Prefix joined onto every configured path; `/` on a live system.
+    root: PathBuf,
+}
+
+impl ConfigDriver {
+    /// Probe a config against the live filesystem.
+    pub fn new(config: DriverConfig) -> Result<Self, DriverError> {
+        Self::probe(config, Path::new("/"))
+    }
+
+    /// Load a config file and probe it against the live filesystem.
+    pub fn from_file(path: &Path) -> Result<Self, DriverError> {
+        Self::new(DriverConfig::from_file(path)?)
+    }
+
+    /// Probe a config with every configured path resolved under `root` (e.g. a fixture tree).
+    pub fn probe(config: DriverConfig, root: &Path) -> Result<Self, DriverError> {
+        let driver = Self {
+            config,
+            root: root.to_path_buf(),
+        };
+
+        let probe = &driver.config.probe;
+        let path = driver.resolve(&probe.path);
+        let matched = match &probe.contains {
+            Some(needle) => sysfs::read_optional(&path).is_some_and(|contents| contents.contains(needle.as_str())),
+            None => path.exists(),
+        };
+        if !matched {
+            return Err(DriverError::NotReady(format!(
+                "{}: probe {} did not match",
+                driver.config.name,
+                path.display()
+            )));
+        }
+
+        Ok(driver)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        // Strip the leading `/` so absolute config paths land inside `root`.
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    fn profile(&self, id: ProfileId) -> Result<&ProfileConfig, DriverError> {
+        self.config
+            .profiles
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| DriverError::Unsupported(format!("Unknown profile: {}", id)))
+    }
+
+    fn read_scaled(&self, attr: &Option<ScaledPath>) -> Option<f64> {
+        let attr = attr.as_ref()?;
+        let raw = sysfs::read_trimmed(&self.resolve(&attr.path)).ok()?;
+        raw.parse::<f64>().ok().map(|value| value * attr.scale)
+    }
+}
+
+impl FanDriver for ConfigDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        Ok(FanCapabilities {
+            supported: true,
+            profiles: self
+                .config
+                .profiles
+                .iter()
+                .map(|p| FanProfile {
+                    id: p.id,
+                    name: p.name.clone(),
+                    is_maximum: p.maximum,
+                })
+                .collect(),
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        let Some(readback) = &self.config.readback else {
+            return Ok(None);
+        };
+        let value = sysfs::read_trimmed(&self.resolve(&readback.path))?;
+        Ok(readback.values.get(&value).copied())
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        for write in &self.profile(profile)?.writes {
+            sysfs::write_value(&self.resolve(&write.path), &write.value)?;
+        }
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let writes = self.profile(profile)?.writes.iter();
+        Ok(Explanation::new(profile).writes(writes.map(|write| (self.resolve(&write.path), write.value.clone()))))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        let telemetry = &self.config.telemetry;
+        Ok(FanTelemetry {
+            rpm: self.read_scaled(&telemetry.rpm).map(|rpm| rpm.round() as u32),
+            temperature_c: self.read_scaled(&telemetry.temperature_c).map(|t| t as f32),
+            fan_duty_percent: self
+                .read_scaled(&telemetry.fan_duty_percent)
+                .map(|duty| duty.round().clamp(0.0, 100.0) as u8),
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+/// Directory scanned for `*.toml` driver configs: `EDGE_OPTIMIZER_DRIVER_DIR` if set,
+/// else `/etc/edge_optimizer/drivers.d` (`%ProgramData%\EdgeOptimizer\drivers.d` on Windows).
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("EDGE_OPTIMIZER_DRIVER_DIR") {
+        return PathBuf::from(dir);
+    }
+    #[cfg(windows)]
+    {
+        let base = std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into());
+        PathBuf::from(base).join("EdgeOptimizer").join("drivers.d")
+    }
+    #[cfg(not(windows))]
+    {
+        PathBuf::from("/etc/edge_optimizer/drivers.d")
+    }
+}
+
+/// `*.toml` files in `dir`, sorted by name so probe order is predictable.
+pub fn config_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    const RPI5: &str = r#"
+name = "Raspberry Pi 5 active cooler"
+
+[probe]
+path = "/sys/firmware/devicetree/base/model"
+contains = "Raspberry Pi 5"
+
+[[profiles]]
+id = 0
+name = "Auto"
+writes = [{ path = "/sys/class/hwmon/hwmon2/pwm1_enable", value = "2" }]
+
+[[profiles]]
+id = 1
+name = "Max"
+maximum = true
+writes = [
+    { path = "/sys/class/hwmon/hwmon2/pwm1_enable", value = "1" },
+    { path = "/sys/class/hwmon/hwmon2/pwm1", value = "255" },
+]
+
+[readback]
+path = "/sys/class/hwmon/hwmon2/pwm1_enable"
+values = { "2" = 0, "1" = 1 }
+
+[telemetry]
+rpm = { path = "/sys/class/hwmon/hwmon2/fan1_input" }
+temperature_c = { path = "/sys/class/thermal/thermal_zone0/temp", scale = 0.001 }
+"#;
+
+    fn rpi5(model: &str) -> Fixture {
+        let root = Fixture::new();
+        root.write("sys/firmware/devicetree/base/model", model)
+            .write("sys/class/hwmon/hwmon2/pwm1_enable", "2\n")
+            .write("sys/class/hwmon/hwmon2/pwm1", "80\n")
+            .write("sys/class/hwmon/hwmon2/fan1_input", "2745\n")
+            .write("sys/class/thermal/thermal_zone0/temp", "61350\n");
+        root
+    }
+
+    fn invalid(text: &str) -> String {
+        match DriverConfig::from_toml_str(text) {
+            Err(ConfigError::Invalid(message)) => message,
+            other => panic!("expected Invalid, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn probe_matches_the_model_string() {
+        let config = DriverConfig::from_toml_str(RPI5).unwrap();
+        assert!(matches!(
+            ConfigDriver::probe(config.clone(), rpi5("Raspberry Pi 4 Model B Rev 1.5\0").root()),
+            Err(DriverError::NotReady(_))
+        ));
+        let driver = ConfigDriver::probe(config, rpi5("Raspberry Pi 5 Model B Rev 1.0\0").root()).unwrap();
+        assert_eq!(driver.name(), "Raspberry Pi 5 active cooler");
+    }
+
+    #[test]
+    fn profiles_write_in_order_and_read_back() {
+        let root = rpi5("Raspberry Pi 5 Model B Rev 1.0\0");
+        let driver = ConfigDriver::probe(DriverConfig::from_toml_str(RPI5).unwrap(), root.root()).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(0));
+
+        let explanation = driver.explain_profile(1).unwrap();
+        assert_eq!(explanation.actions.len(), 2);
+        driver.set_profile(1).unwrap();
+        assert_eq!(root.read("sys/class/hwmon/hwmon2/pwm1_enable"), "1");
+        assert_eq!(root.read("sys/class/hwmon/hwmon2/pwm1"), "255");
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+        assert!(matches!(driver.set_profile(2), Err(DriverError::Unsupported(_))));
+
+        // Values the readback table doesn't list mean "unknown", not an error.
+        root.write("sys/class/hwmon/hwmon2/pwm1_enable", "0\n");
+        assert_eq!(driver.current_profile().unwrap(), None);
+    }
+
+    #[test]
+    fn telemetry_applies_scale() {
+        let root = rpi5("Raspberry Pi 5 Model B Rev 1.0\0");
+        let driver = ConfigDriver::probe(DriverConfig::from_toml_str(RPI5).unwrap(), root.root()).unwrap();
+        let telemetry = driver.telemetry().unwrap();
+        assert_eq!(telemetry.rpm, Some(2745));
+        assert_eq!(telemetry.temperature_c, Some(61.35));
+        assert_eq!(telemetry.fan_duty_percent, None);
+    }
+
+    #[test]
+    fn rejects_inconsistent_configs() {
+        let probe = "name = \"x\"\n[probe]\npath = \"/x\"\n";
+        let profile = |id: u32, maximum: bool| {
+            format!(
+                "[[profiles]]\nid = {}\nname = \"p{}\"\nmaximum = {}\nwrites = [{{ path = \"/a\", value = \"1\" }}]\n",
+                id, id, maximum
+            )
+        };
+
+        assert!(invalid("name = \"x\"\nprofiles = []\n[probe]\npath = \"/x\"\n").contains("no profiles"));
+        assert!(invalid(&format!("{}{}{}", probe, profile(0, false), profile(0, false))).contains("duplicate"));
+        assert!(invalid(&format!("{}{}{}", probe, profile(0, true), profile(1, true))).contains("maximum"));
+        assert!(invalid(&format!("{}[[profiles]]\nid = 0\nname = \"p\"\nwrites = []\n", probe)).contains("no writes"));
+        assert!(invalid(&format!(
+            "{}{}[readback]\npath = \"/a\"\nvalues = {{ \"1\" = 7 }}\n",
+            probe,
+            profile(0, false)
+        ))
+        .contains("unknown profile 7"));
+
+        assert!(matches!(
+            DriverConfig::from_toml_str(&format!("{}{}speed = 3\n", probe, profile(0, false))),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!(matches!(DriverConfig::from_toml_str("name = \"x\"\n"), Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn config_files_are_sorted_toml_only() {
+        let dir = Fixture::new();
+        dir.write("20-b.toml", "").write("10-a.toml", "").write("README", "").write("30-c.toml.bak", "");
+        let names: Vec<String> = config_files(dir.root())
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["10-a.toml", "20-b.toml"]);
+        assert!(config_files(&dir.path("missing")).is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+//! Linux thermal `cooling_deviceN` backend for SBCs and edge boards (pwm-fan, gpio-fan, ACPI fans).
+//! Each cooling state becomes a profile; the highest state is the maximum-cooling profile.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::sysfs;
+
+/// Cooling device `type` values that drive a physical fan (as opposed to CPU frequency or devfreq).
+const FAN_TYPES: &[&str] = &["pwm-fan", "gpio-fan", "Fan", "fan", "tmp103-fan", "emc2305"];
+
+/// Driver for a single fan exposed through `/sys/class/thermal/cooling_deviceN`.
+///
+/// Note: a thermal zone governor (e.g. `step_wise`) may override `cur_state` on its next poll;
+/// boards that want a pinned state should switch the zone `policy` to `user_space`.
+pub struct CoolingDeviceDriver {
+    device: PathBuf,
+    zone: Option<PathBuf>,
+}
+
+impl CoolingDeviceDriver {
+    /// Probe the live `/sys` tree for the first fan cooling device.
+    pub fn new() -> Result<Self, DriverError> {
+        Self::probe(Path::new("/sys"))
+    }
+
+    /// Probe a sysfs tree rooted at `root` for the first fan cooling device.
+    pub fn probe(root: &Path) -> Result<Self, DriverError> {
+        let class = root.join("class/thermal");
+        let device = sysfs::numbered_entries(&class, "cooling_device")
+            .into_iter()
+            .map(|(_, path)| path)
+            .find(|path| {
+                sysfs::read_optional(&path.join("type"))
+                    .is_some_and(|t| FAN_TYPES.contains(&t.as_str()))
+            })
+            .ok_or_else(|| {
+                DriverError::NotReady(format!("no fan cooling device under {}", class.display()))
+            })?;
+
+        let zone = paired_zone(&class, &device);
+        Ok(Self { device, zone })
+    }
+
+    /// Path of the cooling device in use, e.g. `/sys/class/thermal/cooling_device0`.
+    pub fn device_path(&self) -> &Path {
+        &self.device
+    }
+
+    fn max_state(&self) -> Result<ProfileId, DriverError> {
+        let max = sysfs::read_i64(&self.device.join("max_state"))?;
+        ProfileId::try_from(max)
+            .map_err(|_| DriverError::Internal(format!("invalid max_state: {}", max)))
+    }
+
+    fn read_rpm(&self) -> Option<u32> {
+        // pwm-fan registers a hwmon child under its platform device when a tachometer is wired.
+        let hwmon_dir = self.device.join("device/hwmon");
+        sysfs::numbered_entries(&hwmon_dir, "hwmon")
+            .into_iter()
+            .find_map(|(_, hwmon)| sysfs::read_i64(&hwmon.join("fan1_input")).ok())
+            .and_then(|rpm| u32::try_from(rpm).ok())
+    }
+}
+
+impl FanDriver for CoolingDeviceDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        let max = self.max_state()?;
+        if max == 0 {
+            return Ok(FanCapabilities::unsupported());
+        }
+
+        let profiles = (0..=max)
+            .map(|state| FanProfile {
+                id: state,
+                name: match state {
+                    0 => "Off".into(),
+                    s if s == max => format!("Level {} (Max)", s),
+                    s => format!("Level {}", s),
+                },
+                is_maximum: state == max,
+            })
+            .collect();
+
+        Ok(FanCapabilities {
+            supported: true,
+            profiles,
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        let state = sysfs::read_i64(&self.device.join("cur_state"))?;
+        Ok(ProfileId::try_from(state).ok())
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        let max = self.max_state()?;
+        if profile > max {
+            return Err(DriverError::Unsupported(format!(
+                "cooling state {} out of range 0..={}",
+                profile, max
+            )));
+        }
+        sysfs::write_value(&self.device.join("cur_state"), &profile.to_string())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let max = self.max_state()?;
+        if profile > max {
+            return Err(DriverError::Unsupported(format!(
+                "cooling state {} out of range 0..={}",
+                profile, max
+            )));
+        }
+        Ok(Explanation::new(profile).write(self.device.join("cur_state"), profile.to_string()))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        let temperature_c = self
+            .zone
+            .as_ref()
+            .and_then(|zone| sysfs::read_i64(&zone.join("temp")).ok())
+            .map(|milli| milli as f32 / 1000.0);
+
+        Ok(FanTelemetry {
+            rpm: self.read_rpm(),
+            temperature_c,
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+/// Find the thermal zone bound to `device` through its `cdevN` link, falling back to the first zone.
+fn paired_zone(class: &Path, device: &Path) -> Option<PathBuf> {
+    let zones: Vec<PathBuf> = sysfs::numbered_entries(class, "thermal_zone")
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect();
+    let device = fs::canonicalize(device).ok();
+
+    zones
+        .iter()
+        .find(|zone| {
+            sysfs::numbered_entries(zone, "cdev")
+                .into_iter()
+                .any(|(_, link)| fs::canonicalize(link).ok() == device)
+        })
+        .or_else(|| zones.first())
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    const FAN: &str = "class/thermal/cooling_device1";
+
+    /// A CPU frequency cooling device first, then a three-state pwm-fan with a tachometer, and
+    /// two thermal zones of which the second is bound to the fan.
+    fn sbc() -> Fixture {
+        let sys = Fixture::new();
+        sys.write("class/thermal/cooling_device0/type", "Processor\n")
+            .write("class/thermal/cooling_device0/max_state", "10\n")
+            .write(&format!("{}/type", FAN), "pwm-fan\n")
+            .write(&format!("{}/max_state", FAN), "3\n")
+            .write(&format!("{}/cur_state", FAN), "1\n")
+            .write(&format!("{}/device/hwmon/hwmon2/fan1_input", FAN), "3120\n")
+            .write("class/thermal/thermal_zone0/temp", "41000\n")
+            .write("class/thermal/thermal_zone1/temp", "57500\n");
+        std::os::unix::fs::symlink(sys.path(FAN), sys.path("class/thermal/thermal_zone1/cdev0")).unwrap();
+        sys
+    }
+
+    #[test]
+    fn probe_skips_non_fan_devices() {
+        let sys = Fixture::new();
+        sys.write("class/thermal/cooling_device0/type", "Processor\n")
+            .write("class/thermal/cooling_device1/type", "devfreq-gpu\n");
+        assert!(matches!(CoolingDeviceDriver::probe(sys.root()), Err(DriverError::NotReady(_))));
+
+        let sys = sbc();
+        let driver = CoolingDeviceDriver::probe(sys.root()).unwrap();
+        assert_eq!(driver.device_path(), sys.path(FAN));
+    }
+
+    #[test]
+    fn each_cooling_state_is_a_profile() {
+        let sys = sbc();
+        let driver = CoolingDeviceDriver::probe(sys.root()).unwrap();
+        let caps = driver.capabilities().unwrap();
+        let names: Vec<&str> = caps.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Off", "Level 1", "Level 2", "Level 3 (Max)"]);
+        assert_eq!(caps.profiles.iter().filter(|p| p.is_maximum).count(), 1);
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+
+        driver.set_profile(3).unwrap();
+        assert_eq!(sys.read(&format!("{}/cur_state", FAN)), "3");
+        assert!(matches!(driver.set_profile(4), Err(DriverError::Unsupported(_))));
+        assert!(matches!(driver.explain_profile(4), Err(DriverError::Unsupported(_))));
+        assert_eq!(driver.explain_profile(0).unwrap().actions.len(), 1);
+    }
+
+    #[test]
+    fn single_state_device_is_unsupported() {
+        let sys = Fixture::new();
+        sys.write("class/thermal/cooling_device0/type", "gpio-fan\n")
+            .write("class/thermal/cooling_device0/max_state", "0\n");
+        let driver = CoolingDeviceDriver::probe(sys.root()).unwrap();
+        assert!(!driver.capabilities().unwrap().supported);
+    }
+
+    #[test]
+    fn telemetry_uses_the_bound_zone_and_tachometer() {
+        let sys = sbc();
+        let telemetry = CoolingDeviceDriver::probe(sys.root()).unwrap().telemetry().unwrap();
+        assert_eq!(telemetry.rpm, Some(3120));
+        assert_eq!(telemetry.temperature_c, Some(57.5));
+
+        // Without a cdev link the first zone stands in.
+        std::fs::remove_file(sys.path("class/thermal/thermal_zone1/cdev0")).unwrap();
+        let telemetry = CoolingDeviceDriver::probe(sys.root()).unwrap().telemetry().unwrap();
+        assert_eq!(telemetry.temperature_c, Some(41.0));
+    }
+}
@@ -0,0 +1,537 @@
+//! Linux cpufreq/EPP power backend, the Linux analogue of `windows_power`.
+//! Maps Quiet/Balanced/Max to `scaling_governor`, `energy_performance_preference`, boost and
+//! `scaling_max_freq` across all online CPUs. The values found before the first change are saved
+//! in the state directory so `edgeopt restore` can put them back in a later run.
+
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::state;
+use crate::sysfs;
+use crate::thermal;
+
+pub const QUIET: ProfileId = 0;
+pub const BALANCED: ProfileId = 1;
+pub const MAX: ProfileId = 2;
+
+/// File in the state directory holding the snapshot taken before the first change.
+const SNAPSHOT_FILE: &str = "cpufreq_original";
+
+/// Stands in for an attribute the CPU doesn't have; no cpufreq attribute takes this value.
+const ABSENT: &str = "-";
+
+/// What a profile asks of each CPU. Preference lists are tried in order against what the
+/// scaling driver offers (intel_pstate only has `performance`/`powersave`, acpi-cpufreq more).
+struct CpuTuning {
+    governors: &'static [&'static str],
+    epp: &'static [&'static str],
+    boost: bool,
+    /// `scaling_max_freq` as a percentage of `cpuinfo_max_freq`.
+    max_freq_percent: u64,
+}
+
+fn tuning(profile: ProfileId) -> Option<CpuTuning> {
+    match profile {
+        QUIET => Some(CpuTuning {
+            governors: &["powersave", "conservative"],
+            epp: &["power", "balance_power"],
+            boost: false,
+            max_freq_percent: 60,
+        }),
+        BALANCED => Some(CpuTuning {
+            governors: &["schedutil", "ondemand", "powersave"],
+            epp: &["balance_performance", "default"],
+            boost: true,
+            max_freq_percent: 100,
+        }),
+        MAX => Some(CpuTuning {
+            governors: &["performance"],
+            epp: &["performance"],
+            boost: true,
+            max_freq_percent: 100,
+        }),
+        _ => None,
+    }
+}
+
+/// Global turbo switch: `cpufreq/boost` (acpi-cpufreq, amd-pstate) or intel_pstate's inverted
+/// `no_turbo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BoostControl {
+    Boost(PathBuf),
+    NoTurbo(PathBuf),
+}
+
+impl BoostControl {
+    fn path(&self) -> &Path {
+        match self {
+            Self::Boost(path) | Self::NoTurbo(path) => path,
+        }
+    }
+
+    fn value(&self, enabled: bool) -> &'static str {
+        match (self, enabled) {
+            (Self::Boost(_), true) | (Self::NoTurbo(_), false) => "1",
+            _ => "0",
+        }
+    }
+}
+
+/// Values of one CPU's cpufreq attributes at snapshot time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuState {
+    pub cpufreq: PathBuf,
+    pub governor: Option<String>,
+    pub epp: Option<String>,
+    pub max_freq_khz: Option<String>,
+}
+
+/// Original settings, taken before the first change so they can be put back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuSnapshot {
+    pub cpus: Vec<CpuState>,
+    /// Boost control file and its raw contents.
+    pub boost: Option<(PathBuf, String)>,
+}
+
+impl CpuSnapshot {
+    /// Attribute writes that put the snapshot back. Governor goes first since EPP writes
+    /// depend on it.
+    pub fn writes(&self) -> Vec<(PathBuf, String)> {
+        let mut writes = Vec::new();
+        for cpu in &self.cpus {
+            let attributes = [
+                ("scaling_governor", &cpu.governor),
+                ("energy_performance_preference", &cpu.epp),
+                ("scaling_max_freq", &cpu.max_freq_khz),
+            ];
+            for (name, value) in attributes {
+                if let Some(value) = value {
+                    writes.push((cpu.cpufreq.join(name), value.clone()));
+                }
+            }
+        }
+        writes.extend(self.boost.clone());
+        writes
+    }
+
+    /// One tab-separated line per CPU (`cpu`, directory, governor, EPP, max frequency) and one
+    /// for boost (`boost`, file, value).
+    fn to_text(&self) -> String {
+        let field = |value: &Option<String>| value.clone().unwrap_or_else(|| ABSENT.to_string());
+        let mut text = String::new();
+        for cpu in &self.cpus {
+            text += &format!(
+                "cpu\t{}\t{}\t{}\t{}\n",
+                cpu.cpufreq.display(),
+                field(&cpu.governor),
+                field(&cpu.epp),
+                field(&cpu.max_freq_khz)
+            );
+        }
+        if let Some((path, value)) = &self.boost {
+            text += &format!("boost\t{}\t{}\n", path.display(), value);
+        }
+        text
+    }
+
+    fn from_text(text: &str) -> Result<Self, DriverError> {
+        let field = |value: &str| (value != ABSENT).then(|| value.to_string());
+        let mut snapshot = Self {
+            cpus: Vec::new(),
+            boost: None,
+        };
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            match line.split('\t').collect::<Vec<_>>()[..] {
+                ["cpu", cpufreq, governor, epp, max] => snapshot.cpus.push(CpuState {
+                    cpufreq: PathBuf::from(cpufreq),
+                    governor: field(governor),
+                    epp: field(epp),
+                    max_freq_khz: field(max),
+                }),
+                ["boost", path, value] => snapshot.boost = Some((PathBuf::from(path), value.to_string())),
+                _ => return Err(DriverError::Internal(format!("malformed cpufreq snapshot line: {:?}", line))),
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+/// Snapshot saved in `state_dir` by the first profile change, if any.
+pub fn saved_snapshot(state_dir: &Path) -> Result<Option<CpuSnapshot>, DriverError> {
+    match std::fs::read_to_string(state_dir.join(SNAPSHOT_FILE)) {
+        Ok(text) => CpuSnapshot::from_text(&text).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(DriverError::Io(format!("{}: {}", state_dir.join(SNAPSHOT_FILE).display(), e))),
+    }
+}
+
+/// Write back the snapshot saved in `state_dir` and forget it. `false` if nothing was saved.
+pub fn restore_saved(state_dir: &Path) -> Result<bool, DriverError> {
+    let Some(snapshot) = saved_snapshot(state_dir)? else {
+        return Ok(false);
+    };
+    write_all(snapshot.writes())?;
+    std::fs::remove_file(state_dir.join(SNAPSHOT_FILE))
+        .map_err(|e| DriverError::Io(format!("{}: {}", state_dir.join(SNAPSHOT_FILE).display(), e)))?;
+    Ok(true)
+}
+
+fn write_all(writes: Vec<(PathBuf, String)>) -> Result<(), DriverError> {
+    for (path, value) in writes {
+        sysfs::write_value(&path, &value)?;
+    }
+    Ok(())
+}
+
+pub struct CpufreqDriver {
+    sys_root: PathBuf,
+    /// `cpuN/cpufreq` directories of online CPUs.
+    cpus: Vec<PathBuf>,
+    boost: Option<BoostControl>,
+    original: CpuSnapshot,
+    /// Where the original settings are saved before the first change; not saved if `None`.
+    state_dir: Option<PathBuf>,
+}
+
+impl CpufreqDriver {
+    /// Probe the live `/sys` tree, saving the original settings in the user's state directory.
+    pub fn new() -> Result<Self, DriverError> {
+        let driver = Self::probe(Path::new("/sys"))?;
+        Ok(match state::state_dir() {
+            Some(dir) => driver.with_state_dir(dir),
+            None => driver,
+        })
+    }
+
+    /// Probe a sysfs tree rooted at `root` for `devices/system/cpu/cpuN/cpufreq`.
+    pub fn probe(root: &Path) -> Result<Self, DriverError> {
+        let cpu_root = root.join("devices/system/cpu");
+        let cpus: Vec<PathBuf> = sysfs::numbered_entries(&cpu_root, "cpu")
+            .into_iter()
+            .map(|(_, cpu)| cpu.join("cpufreq"))
+            .filter(|cpufreq| cpufreq.join("scaling_governor").exists())
+            .collect();
+        if cpus.is_empty() {
+            return Err(DriverError::NotReady(format!(
+                "no cpufreq policies under {}",
+                cpu_root.display()
+            )));
+        }
+
+        let boost = [
+            BoostControl::Boost(cpu_root.join("cpufreq/boost")),
+            BoostControl::NoTurbo(cpu_root.join("intel_pstate/no_turbo")),
+        ]
+        .into_iter()
+        .find(|control| control.path().exists());
+
+        let mut driver = Self {
+            sys_root: root.to_path_buf(),
+            cpus,
+            boost,
+            original: CpuSnapshot {
+                cpus: Vec::new(),
+                boost: None,
+            },
+            state_dir: None,
+        };
+        driver.original = driver.snapshot();
+        Ok(driver)
+    }
+
+    /// Save the settings found at probe time in `dir` before the first change, unless an
+    /// earlier run already saved them there.
+    pub fn with_state_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.state_dir = Some(dir.into());
+        self
+    }
+
+    /// Read the current settings of every CPU and the boost switch.
+    pub fn snapshot(&self) -> CpuSnapshot {
+        CpuSnapshot {
+            cpus: self
+                .cpus
+                .iter()
+                .map(|cpufreq| CpuState {
+                    cpufreq: cpufreq.clone(),
+                    governor: sysfs::read_optional(&cpufreq.join("scaling_governor")),
+                    epp: sysfs::read_optional(&cpufreq.join("energy_performance_preference")),
+                    max_freq_khz: sysfs::read_optional(&cpufreq.join("scaling_max_freq")),
+                })
+                .collect(),
+            boost: self
+                .boost
+                .as_ref()
+                .and_then(|b| sysfs::read_optional(b.path()).map(|value| (b.path().to_path_buf(), value))),
+        }
+    }
+
+    /// Settings found when the driver was created.
+    pub fn original(&self) -> &CpuSnapshot {
+        &self.original
+    }
+
+    /// Write back a snapshot.
+    pub fn restore(&self, snapshot: &CpuSnapshot) -> Result<(), DriverError> {
+        write_all(snapshot.writes())
+    }
+
+    /// Restore the settings found when the driver was created.
+    pub fn restore_original(&self) -> Result<(), DriverError> {
+        self.restore(&self.original)
+    }
+
+    /// Attribute writes that apply `tuning` to every CPU, then boost.
+    fn tuning_writes(&self, tuning: &CpuTuning) -> Vec<(PathBuf, String)> {
+        let mut writes = Vec::new();
+        for cpufreq in &self.cpus {
+            let governors = sysfs::read_optional(&cpufreq.join("scaling_available_governors")).unwrap_or_default();
+            if let Some(governor) = pick(tuning.governors, &governors) {
+                writes.push((cpufreq.join("scaling_governor"), governor.to_string()));
+            }
+
+            let epp_path = cpufreq.join("energy_performance_preference");
+            if epp_path.exists() {
+                let choices = sysfs::read_optional(&cpufreq.join("energy_performance_available_preferences"))
+                    .unwrap_or_default();
+                if let Some(epp) = pick(tuning.epp, &choices) {
+                    writes.push((epp_path, epp.to_string()));
+                }
+            }
+
+            if let Ok(hw_max) = sysfs::read_i64(&cpufreq.join("cpuinfo_max_freq")) {
+                let hw_min = sysfs::read_i64(&cpufreq.join("cpuinfo_min_freq")).unwrap_or(0);
+                let target = (hw_max as u64 * tuning.max_freq_percent / 100).max(hw_min as u64);
+                writes.push((cpufreq.join("scaling_max_freq"), target.to_string()));
+            }
+        }
+
+        if let Some(control) = &self.boost {
+            writes.push((control.path().to_path_buf(), control.value(tuning.boost).to_string()));
+        }
+        writes
+    }
+
+    /// Save the probe-time settings unless a snapshot from an earlier change is already there:
+    /// that one holds the values from before edgeopt touched anything.
+    fn save_original(&self) -> Result<(), DriverError> {
+        let Some(dir) = &self.state_dir else {
+            return Ok(());
+        };
+        let path = dir.join(SNAPSHOT_FILE);
+        if path.exists() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&path, self.original.to_text()))
+            .map_err(|e| DriverError::Io(format!("saving original cpufreq settings to {}: {}", path.display(), e)))
+    }
+
+    fn apply(&self, tuning: &CpuTuning) -> Result<(), DriverError> {
+        self.save_original()?;
+        write_all(self.tuning_writes(tuning))
+    }
+
+    fn matches(&self, tuning: &CpuTuning) -> Result<bool, DriverError> {
+        let cpufreq = &self.cpus[0];
+        let governor = sysfs::read_trimmed(&cpufreq.join("scaling_governor"))?;
+        let governors = sysfs::read_optional(&cpufreq.join("scaling_available_governors")).unwrap_or_default();
+        if pick(tuning.governors, &governors) != Some(governor.as_str()) {
+            return Ok(false);
+        }
+
+        if let Some(epp) = sysfs::read_optional(&cpufreq.join("energy_performance_preference")) {
+            let choices = sysfs::read_optional(&cpufreq.join("energy_performance_available_preferences"))
+                .unwrap_or_default();
+            if pick(tuning.epp, &choices).is_some_and(|wanted| wanted != epp) {
+                return Ok(false);
+            }
+        }
+
+        match &self.boost {
+            Some(control) => Ok(sysfs::read_trimmed(control.path())? == control.value(tuning.boost)),
+            None => Ok(true),
+        }
+    }
+}
+
+/// First preference that appears in the whitespace-separated `available` list.
+fn pick<'a>(preferences: &[&'a str], available: &str) -> Option<&'a str> {
+    preferences
+        .iter()
+        .copied()
+        .find(|wanted| available.split_whitespace().any(|a| a == *wanted))
+}
+
+impl FanDriver for CpufreqDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        Ok(FanCapabilities {
+            supported: true,
+            profiles: vec![
+                FanProfile {
+                    id: QUIET,
+                    name: "Quiet".into(),
+                    is_maximum: false,
+                },
+                FanProfile {
+                    id: BALANCED,
+                    name: "Balanced (Default)".into(),
+                    is_maximum: false,
+                },
+                FanProfile {
+                    id: MAX,
+                    name: "Max Performance".into(),
+                    is_maximum: true,
+                },
+            ],
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        // Check Max first: its settings are the most specific.
+        for profile in [MAX, QUIET, BALANCED] {
+            if self.matches(&tuning(profile).expect("known profile"))? {
+                return Ok(Some(profile));
+            }
+        }
+        Ok(None)
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        let tuning = tuning(profile).ok_or_else(|| DriverError::Unsupported(format!("Unknown profile: {}", profile)))?;
+        self.apply(&tuning)
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let tuning = tuning(profile).ok_or_else(|| DriverError::Unsupported(format!("Unknown profile: {}", profile)))?;
+        Ok(Explanation::new(profile).writes(self.tuning_writes(&tuning)))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        Ok(FanTelemetry {
+            rpm: None,
+            temperature_c: thermal::package_temperature_c(&self.sys_root),
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    /// Two acpi-cpufreq-style CPUs on `schedutil`/`balance_performance` at full speed, with the
+    /// global `cpufreq/boost` switch on.
+    fn cpu_tree() -> Fixture {
+        let fixture = Fixture::new();
+        for cpu in ["cpu0", "cpu1"] {
+            let dir = format!("devices/system/cpu/{}/cpufreq", cpu);
+            fixture
+                .write(&format!("{}/scaling_governor", dir), "schedutil\n")
+                .write(
+                    &format!("{}/scaling_available_governors", dir),
+                    "conservative ondemand userspace powersave performance schedutil\n",
+                )
+                .write(&format!("{}/energy_performance_preference", dir), "balance_performance\n")
+                .write(
+                    &format!("{}/energy_performance_available_preferences", dir),
+                    "default performance balance_performance balance_power power\n",
+                )
+                .write(&format!("{}/cpuinfo_max_freq", dir), "4000000\n")
+                .write(&format!("{}/cpuinfo_min_freq", dir), "800000\n")
+                .write(&format!("{}/scaling_max_freq", dir), "4000000\n");
+        }
+        fixture.write("devices/system/cpu/cpufreq/boost", "1\n");
+        fixture
+    }
+
+    #[test]
+    fn probe_needs_a_cpufreq_policy() {
+        let fixture = Fixture::new();
+        fixture.write("devices/system/cpu/cpu0/online", "1\n");
+        assert!(matches!(CpufreqDriver::probe(fixture.root()), Err(DriverError::NotReady(_))));
+    }
+
+    #[test]
+    fn quiet_tunes_every_cpu_and_boost() {
+        let fixture = cpu_tree();
+        let driver = CpufreqDriver::probe(fixture.root()).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(BALANCED));
+
+        driver.set_profile(QUIET).unwrap();
+        for cpu in ["cpu0", "cpu1"] {
+            let dir = format!("devices/system/cpu/{}/cpufreq", cpu);
+            assert_eq!(fixture.read(&format!("{}/scaling_governor", dir)), "powersave");
+            assert_eq!(fixture.read(&format!("{}/energy_performance_preference", dir)), "power");
+            assert_eq!(fixture.read(&format!("{}/scaling_max_freq", dir)), "2400000");
+        }
+        assert_eq!(fixture.read("devices/system/cpu/cpufreq/boost"), "0");
+        assert_eq!(driver.current_profile().unwrap(), Some(QUIET));
+        assert!(matches!(driver.set_profile(7), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn intel_pstate_no_turbo_is_inverted() {
+        let fixture = Fixture::new();
+        fixture
+            .write("devices/system/cpu/cpu0/cpufreq/scaling_governor", "powersave\n")
+            .write("devices/system/cpu/cpu0/cpufreq/scaling_available_governors", "performance powersave\n")
+            .write("devices/system/cpu/intel_pstate/no_turbo", "0\n");
+        let driver = CpufreqDriver::probe(fixture.root()).unwrap();
+
+        driver.set_profile(QUIET).unwrap();
+        assert_eq!(fixture.read("devices/system/cpu/intel_pstate/no_turbo"), "1");
+        driver.set_profile(MAX).unwrap();
+        assert_eq!(fixture.read("devices/system/cpu/cpu0/cpufreq/scaling_governor"), "performance");
+        assert_eq!(fixture.read("devices/system/cpu/intel_pstate/no_turbo"), "0");
+    }
+
+    #[test]
+    fn first_change_saves_originals_for_a_later_restore() {
+        let fixture = cpu_tree();
+        let state = fixture.path("state");
+        let driver = CpufreqDriver::probe(fixture.root()).unwrap().with_state_dir(&state);
+        assert!(driver.explain_profile(MAX).is_ok());
+        assert_eq!(saved_snapshot(&state).unwrap(), None, "a dry run saves nothing");
+
+        driver.set_profile(MAX).unwrap();
+        assert_eq!(saved_snapshot(&state).unwrap().as_ref(), Some(driver.original()));
+
+        // A later run probes the already-changed tree; the first snapshot must survive it.
+        let later = CpufreqDriver::probe(fixture.root()).unwrap().with_state_dir(&state);
+        later.set_profile(QUIET).unwrap();
+        assert_eq!(saved_snapshot(&state).unwrap().as_ref(), Some(driver.original()));
+
+        assert!(restore_saved(&state).unwrap());
+        for cpu in ["cpu0", "cpu1"] {
+            let dir = format!("devices/system/cpu/{}/cpufreq", cpu);
+            assert_eq!(fixture.read(&format!("{}/scaling_governor", dir)), "schedutil");
+            assert_eq!(fixture.read(&format!("{}/energy_performance_preference", dir)), "balance_performance");
+            assert_eq!(fixture.read(&format!("{}/scaling_max_freq", dir)), "4000000");
+        }
+        assert_eq!(fixture.read("devices/system/cpu/cpufreq/boost"), "1");
+        assert_eq!(saved_snapshot(&state).unwrap(), None);
+        assert!(!restore_saved(&state).unwrap());
+    }
+
+    #[test]
+    fn snapshot_text_round_trips_missing_attributes() {
+        let snapshot = CpuSnapshot {
+            cpus: vec![CpuState {
+                cpufreq: PathBuf::from("/sys/devices/system/cpu/cpu0/cpufreq"),
+                governor: Some("powersave".into()),
+                epp: None,
+                max_freq_khz: Some("3000000".into()),
+            }],
+            boost: None,
+        };
+        assert_eq!(CpuSnapshot::from_text(&snapshot.to_text()).unwrap(), snapshot);
+        assert!(CpuSnapshot::from_text("cpu\tonly-a-path\n").is_err());
+    }
+}
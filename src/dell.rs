@@ -0,0 +1,244 @@
+//! Dell fan control through the `dell-smm-hwmon` driver (hwmon name `dell_smm`).
+//! Fans take discrete states 0 (off), 1 (low) and 2 (high), written as scaled `pwmN` values.
+
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::sysfs;
+
+/// Highest fan state `dell-smm-hwmon` exposes by default (`fan_max=2`).
+const MAX_STATE: ProfileId = 2;
+/// Profile id that hands fans back to BIOS control, offered when `pwm1_enable` exists.
+pub const PROFILE_AUTO: ProfileId = 3;
+
+/// `pwmN_enable` values: 1 disables BIOS fan control, 2 restores it.
+const PWM_MANUAL: &str = "1";
+const PWM_AUTO: &str = "2";
+
+pub struct DellSmmDriver {
+    hwmon: PathBuf,
+    fans: Vec<u32>,
+}
+
+impl DellSmmDriver {
+    /// Probe the live `/sys` tree.
+    pub fn new() -> Result<Self, DriverError> {
+        Self::probe(Path::new("/sys"))
+    }
+
+    /// Probe a sysfs tree rooted at `root` for the `dell_smm` hwmon device.
+    pub fn probe(root: &Path) -> Result<Self, DriverError> {
+        let hwmon = sysfs::numbered_entries(&root.join("class/hwmon"), "hwmon")
+            .into_iter()
+            .map(|(_, path)| path)
+            .find(|path| sysfs::read_optional(&path.join("name")).as_deref() == Some("dell_smm"))
+            .ok_or_else(|| {
+                DriverError::NotReady("dell_smm hwmon not found - dell-smm-hwmon not loaded or not a Dell system?".into())
+            })?;
+
+        // Only pwmN itself; pwmN_enable and friends fail the numeric parse.
+        let fans: Vec<u32> = sysfs::numbered_entries(&hwmon, "pwm")
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect();
+        if fans.is_empty() {
+            return Err(DriverError::NotReady(format!("{} exposes no pwm attributes", hwmon.display())));
+        }
+
+        Ok(Self { hwmon, fans })
+    }
+
+    fn has_bios_control(&self) -> bool {
+        self.hwmon.join("pwm1_enable").exists()
+    }
+
+    /// Map a state to the pwm value the driver rounds back to that state (0, 128, 255).
+    fn state_to_pwm(state: ProfileId) -> u32 {
+        (state * 255).div_ceil(MAX_STATE)
+    }
+
+    fn pwm_to_state(pwm: i64) -> ProfileId {
+        ((pwm.clamp(0, 255) as ProfileId * MAX_STATE) + 127) / 255
+    }
+
+    fn bios_control_writes(&self, value: &str) -> Vec<(PathBuf, String)> {
+        self.fans
+            .iter()
+            .map(|fan| self.hwmon.join(format!("pwm{}_enable", fan)))
+            .filter(|enable| enable.exists())
+            .map(|enable| (enable, value.to_string()))
+            .collect()
+    }
+
+    /// Attribute writes that select `profile`, in order.
+    fn profile_writes(&self, profile: ProfileId) -> Result<Vec<(PathBuf, String)>, DriverError> {
+        if profile == PROFILE_AUTO && self.has_bios_control() {
+            return Ok(self.bios_control_writes(PWM_AUTO));
+        }
+        if profile > MAX_STATE {
+            return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile)));
+        }
+
+        // Otherwise the BIOS overrides manual states within seconds.
+        let mut writes = self.bios_control_writes(PWM_MANUAL);
+        let value = Self::state_to_pwm(profile).to_string();
+        writes.extend(self.fans.iter().map(|fan| (self.hwmon.join(format!("pwm{}", fan)), value.clone())));
+        Ok(writes)
+    }
+}
+
+impl FanDriver for DellSmmDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        let mut profiles = vec![
+            FanProfile {
+                id: 0,
+                name: "Off".into(),
+                is_maximum: false,
+            },
+            FanProfile {
+                id: 1,
+                name: "Low".into(),
+                is_maximum: false,
+            },
+            FanProfile {
+                id: MAX_STATE,
+                name: "High (Max)".into(),
+                is_maximum: true,
+            },
+        ];
+        if self.has_bios_control() {
+            profiles.push(FanProfile {
+                id: PROFILE_AUTO,
+                name: "Auto (BIOS)".into(),
+                is_maximum: false,
+            });
+        }
+
+        Ok(FanCapabilities {
+            supported: true,
+            profiles,
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        let enable = self.hwmon.join("pwm1_enable");
+        if enable.exists() && sysfs::read_trimmed(&enable)? == PWM_AUTO {
+            return Ok(Some(PROFILE_AUTO));
+        }
+        let pwm = sysfs::read_i64(&self.hwmon.join(format!("pwm{}", self.fans[0])))?;
+        Ok(Some(Self::pwm_to_state(pwm)))
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        for (path, value) in self.profile_writes(profile)? {
+            sysfs::write_value(&path, &value)?;
+        }
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        Ok(Explanation::new(profile).writes(self.profile_writes(profile)?))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        // Report the fastest fan; dell_smm temp1 is the CPU sensor.
+        let rpm = sysfs::attribute_indices(&self.hwmon, "fan", "_input")
+            .into_iter()
+            .filter_map(|i| sysfs::read_i64(&self.hwmon.join(format!("fan{}_input", i))).ok())
+            .filter_map(|rpm| u32::try_from(rpm).ok())
+            .max();
+        let temperature_c = sysfs::read_i64(&self.hwmon.join("temp1_input"))
+            .ok()
+            .map(|milli| milli as f32 / 1000.0);
+
+        Ok(FanTelemetry {
+            rpm,
+            temperature_c,
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    const HWMON: &str = "class/hwmon/hwmon4";
+
+    fn dell(with_enable: bool) -> Fixture {
+        let sys = Fixture::new();
+        sys.write("class/hwmon/hwmon0/name", "acpitz\n")
+            .write(&format!("{}/name", HWMON), "dell_smm\n")
+            .write(&format!("{}/pwm1", HWMON), "128\n")
+            .write(&format!("{}/pwm2", HWMON), "128\n")
+            .write(&format!("{}/fan1_input", HWMON), "2400\n")
+            .write(&format!("{}/fan2_input", HWMON), "2650\n")
+            .write(&format!("{}/temp1_input", HWMON), "54000\n");
+        if with_enable {
+            sys.write(&format!("{}/pwm1_enable", HWMON), "2\n")
+                .write(&format!("{}/pwm2_enable", HWMON), "2\n");
+        }
+        sys
+    }
+
+    fn ids(driver: &DellSmmDriver) -> Vec<ProfileId> {
+        driver.capabilities().unwrap().profiles.iter().map(|p| p.id).collect()
+    }
+
+    #[test]
+    fn probe_needs_the_dell_smm_hwmon() {
+        let sys = Fixture::new();
+        sys.write("class/hwmon/hwmon0/name", "coretemp\n");
+        assert!(matches!(DellSmmDriver::probe(sys.root()), Err(DriverError::NotReady(_))));
+
+        sys.write("class/hwmon/hwmon1/name", "dell_smm\n");
+        assert!(matches!(DellSmmDriver::probe(sys.root()), Err(DriverError::NotReady(_))));
+    }
+
+    #[test]
+    fn maps_states_to_pwm_and_back() {
+        assert_eq!([0, 1, 2].map(DellSmmDriver::state_to_pwm), [0, 128, 255]);
+        assert_eq!([0, 127, 128, 255, 300].map(DellSmmDriver::pwm_to_state), [0, 1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn manual_state_disables_bios_control_first() {
+        let sys = dell(true);
+        let driver = DellSmmDriver::probe(sys.root()).unwrap();
+        assert_eq!(ids(&driver), [0, 1, 2, PROFILE_AUTO]);
+        assert_eq!(driver.current_profile().unwrap(), Some(PROFILE_AUTO));
+
+        driver.set_profile(2).unwrap();
+        assert_eq!(sys.read(&format!("{}/pwm1_enable", HWMON)), PWM_MANUAL);
+        assert_eq!(sys.read(&format!("{}/pwm2", HWMON)), "255");
+        assert_eq!(driver.current_profile().unwrap(), Some(2));
+
+        driver.set_profile(PROFILE_AUTO).unwrap();
+        assert_eq!(sys.read(&format!("{}/pwm2_enable", HWMON)), PWM_AUTO);
+    }
+
+    #[test]
+    fn without_enable_there_is_no_auto_profile() {
+        let sys = dell(false);
+        let driver = DellSmmDriver::probe(sys.root()).unwrap();
+        assert_eq!(ids(&driver), [0, 1, 2]);
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+        assert!(matches!(driver.set_profile(PROFILE_AUTO), Err(DriverError::Unsupported(_))));
+
+        driver.set_profile(0).unwrap();
+        assert_eq!(sys.read(&format!("{}/pwm1", HWMON)), "0");
+    }
+
+    #[test]
+    fn telemetry_reports_the_fastest_fan() {
+        let sys = dell(true);
+        let telemetry = DellSmmDriver::probe(sys.root()).unwrap().telemetry().unwrap();
+        assert_eq!(telemetry.rpm, Some(2650));
+        assert_eq!(telemetry.temperature_c, Some(54.0));
+    }
+}
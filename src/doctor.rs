@@ -0,0 +1,774 @@
+//! `doctor`: one report with what is needed to debug fan control on a machine, replacing the
+//! manual troubleshooting checklists. It covers:
+//!
+//! - DMI identity
+//! - the probe result of every driver
+//! - the kernel modules drivers rely on
+//! - the control attributes they read and write, with permissions
+//! - errors recorded by earlier runs, and recent kernel messages
+//! - findings: hints derived from all of the above
+//!
+//! Collecting only reads. `DoctorReport::redacted` removes serial numbers, the user name, home
+//! directory and host name so the report can be attached to a public issue.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::command::{CommandRunner, CommandSpec, SystemRunner};
+use crate::driver::DriverError;
+use crate::select::{self, ProbeAttempt};
+use crate::state;
+use crate::sysfs;
+
+/// DMI fields identifying the model, read from `/sys/class/dmi/id`.
+#[cfg(not(windows))]
+const DMI_FIELDS: [&str; 11] = [
+    "sys_vendor",
+    "product_name",
+    "product_family",
+    "product_version",
+    "product_sku",
+    "board_vendor",
+    "board_name",
+    "board_version",
+    "bios_vendor",
+    "bios_version",
+    "bios_date",
+];
+
+/// DMI fields unique to one machine; reported only as present.
+#[cfg(not(windows))]
+const DMI_SERIAL_FIELDS: [&str; 4] = ["product_serial", "product_uuid", "board_serial", "chassis_serial"];
+
+/// Kernel modules behind the Linux drivers.
+pub const WATCHED_MODULES: [&str; 12] = [
+    "hp_wmi",
+    "thinkpad_acpi",
+    "asus_wmi",
+    "asus_nb_wmi",
+    "dell_smm_hwmon",
+    "ideapad_laptop",
+    "legion_laptop",
+    "msi_ec",
+    "intel_rapl_common",
+    "intel_rapl_msr",
+    "acpi_cpufreq",
+    "amd_pstate",
+];
+
+/// Vendor string (as in `sys_vendor`) and the modules that drive its fans; any one of them is
+/// enough (Lenovo ships ThinkPads, IdeaPads and Legions under the same vendor string).
+const VENDOR_MODULES: [(&str, &[&str]); 5] = [
+    ("HP", &["hp_wmi"]),
+    ("ASUSTeK", &["asus_nb_wmi"]),
+    ("Dell", &["dell_smm_hwmon"]),
+    ("LENOVO", &["thinkpad_acpi", "ideapad_laptop", "legion_laptop"]),
+    ("Micro-Star", &["msi_ec"]),
+];
+
+/// Kernel log lines kept in the report.
+const KERNEL_MESSAGE_LIMIT: usize = 40;
+
+/// Attribute values longer than this are left out.
+const MAX_VALUE_LEN: usize = 256;
+
+#[cfg(not(windows))]
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct PlatformInfo {
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// Kernel release on Linux.
+    pub kernel: Option<String>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct ModuleStatus {
+    pub name: String,
+    /// Listed in `/proc/modules`.
+    pub loaded: bool,
+    /// Present in `/sys/module` without being a loadable module.
+    pub builtin: bool,
+}
+
+/// A control or status attribute one of the drivers uses.
+#[derive(Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct AttributeStatus {
+    pub path: String,
+    /// Octal permission bits, e.g. "0644".
+    pub mode: Option<String>,
+    pub readable: bool,
+    /// Whether the current user may write it (judged from mode, owner and groups).
+    pub writable: bool,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct Permissions {
+    /// Effective uid on Unix.
+    pub euid: Option<u32>,
+    pub groups: Vec<u32>,
+    /// Root on Unix, an elevated (Administrator) process on Windows.
+    pub elevated: Option<bool>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct DoctorReport {
+    pub platform: PlatformInfo,
+    pub dmi: BTreeMap<String, String>,
+    pub probes: Vec<ProbeAttempt>,
+    /// Why `power.toml` was ignored while probing, if it was.
+    pub power_config_error: Option<DriverError>,
+    pub modules: Vec<ModuleStatus>,
+    pub attributes: Vec<AttributeStatus>,
+    pub permissions: Permissions,
+    /// Entries from `state::recent_errors`, oldest first.
+    pub recent_errors: Vec<String>,
+    /// Kernel log lines mentioning fans, thermals or the watched modules.
+    pub kernel_messages: Vec<String>,
+    pub findings: Vec<String>,
+}
+
+/// Collects a `DoctorReport` from a filesystem root (`/` on a live system).
+pub struct Doctor<R: CommandRunner = SystemRunner> {
+    root: PathBuf,
+    runner: R,
+}
+
+impl Doctor {
+    pub fn new() -> Self {
+        Self::with_runner(Path::new("/"), SystemRunner)
+    }
+}
+
+impl Default for Doctor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: CommandRunner> Doctor<R> {
+    /// Read sysfs/procfs under `root` and run `dmesg` (or PowerShell on Windows) through
+    /// `runner`, e.g. a fixture tree and a `ScriptedRunner`.
+    pub fn with_runner(root: &Path, runner: R) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            runner,
+        }
+    }
+
+    /// Gather everything. Driver probes always run against the live system; `only` restricts
+    /// them to one candidate.
+    pub fn collect(&self, only: Option<&str>) -> DoctorReport {
+        let probes = select::probe_all(only);
+        let mut report = DoctorReport {
+            platform: self.platform(),
+            dmi: self.dmi(),
+            probes: probes.attempts,
+            power_config_error: probes.power_config_error,
+            modules: self.modules(),
+            attributes: self.attributes(),
+            permissions: self.permissions(),
+            recent_errors: state::recent_errors(),
+            kernel_messages: self.kernel_messages(),
+            findings: Vec::new(),
+        };
+        report.findings = findings(&report);
+        report
+    }
+
+    fn path(&self, relative: &str) -> PathBuf {
+        self.root.join(relative)
+    }
+
+    fn platform(&self) -> PlatformInfo {
+        PlatformInfo {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            kernel: sysfs::read_optional(&self.path("proc/sys/kernel/osrelease")),
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn dmi(&self) -> BTreeMap<String, String> {
+        let dir = self.path("sys/class/dmi/id");
+        let mut dmi: BTreeMap<String, String> = DMI_FIELDS
+            .iter()
+            .filter_map(|field| Some((field.to_string(), sysfs::read_optional(&dir.join(field))?)))
+            .collect();
+        // Serial files are root-only; note that they exist without reading them.
+        for field in DMI_SERIAL_FIELDS {
+            if dir.join(field).exists() {
+                dmi.insert(field.to_string(), REDACTED.to_string());
+            }
+        }
+        dmi
+    }
+
+    /// The same fields from CIM, as `key=value` lines. Serial numbers are never queried.
+    #[cfg(windows)]
+    fn dmi(&self) -> BTreeMap<String, String> {
+        let script = "$cs = Get-CimInstance Win32_ComputerSystem; \
+                      $bios = Get-CimInstance Win32_BIOS; \
+                      $board = Get-CimInstance Win32_BaseBoard; \
+                      'sys_vendor=' + $cs.Manufacturer; 'product_name=' + $cs.Model; \
+                      'product_family=' + $cs.SystemFamily; 'product_sku=' + $cs.SystemSKUNumber; \
+                      'board_vendor=' + $board.Manufacturer; 'board_name=' + $board.Product; \
+                      'bios_vendor=' + $bios.Manufacturer; 'bios_version=' + $bios.SMBIOSBIOSVersion";
+        let spec = CommandSpec::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", script]);
+        match self.runner.run(&spec) {
+            Ok(output) if output.success() => output
+                .stdout
+                .lines()
+                .filter_map(|line| line.trim().split_once('='))
+                .filter(|(_, value)| !value.trim().is_empty())
+                .map(|(key, value)| (key.to_string(), value.trim().to_string()))
+                .collect(),
+            _ => BTreeMap::new(),
+        }
+    }
+
+    fn modules(&self) -> Vec<ModuleStatus> {
+        if !cfg!(target_os = "linux") {
+            return Vec::new();
+        }
+        let loaded: Vec<String> = sysfs::read_optional(&self.path("proc/modules"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_whitespace().next().map(String::from))
+            .collect();
+        WATCHED_MODULES
+            .iter()
+            .map(|name| {
+                let is_loaded = loaded.iter().any(|m| m == name);
+                ModuleStatus {
+                    name: name.to_string(),
+                    loaded: is_loaded,
+                    builtin: !is_loaded && self.path("sys/module").join(name).exists(),
+                }
+            })
+            .collect()
+    }
+
+    /// Attributes of every Linux driver, whether or not it probed successfully.
+    fn attributes(&self) -> Vec<AttributeStatus> {
+        let credentials = self.credentials();
+        let mut paths: Vec<PathBuf> = Vec::new();
+
+        paths.extend(["firmware/acpi/platform_profile", "firmware/acpi/platform_profile_choices"].map(|p| self.path("sys").join(p)));
+        paths.extend(["fan", "thermal"].map(|p| self.path("proc/acpi/ibm").join(p)));
+        for platform in ["asus-nb-wmi", "hp-wmi", "msi-ec"] {
+            let dir = self.path("sys/devices/platform").join(platform);
+            paths.extend(files_in(&dir, |name| name != "uevent" && name != "modalias" && name != "driver_override"));
+            for (_, hwmon) in sysfs::numbered_entries(&dir.join("hwmon"), "hwmon") {
+                paths.extend(files_in(&hwmon, is_fan_attribute));
+            }
+        }
+        for (driver, attribute) in [("legion", "fan_fullspeed"), ("ideapad_acpi", "fan_mode")] {
+            let dir = self.path("sys/bus/platform/drivers").join(driver);
+            let devices = fs::read_dir(&dir).into_iter().flatten().filter_map(|e| e.ok());
+            paths.extend(devices.map(|e| e.path().join(attribute)).filter(|p| p.is_file()));
+        }
+        for (_, hwmon) in sysfs::numbered_entries(&self.path("sys/class/hwmon"), "hwmon") {
+            paths.extend(files_in(&hwmon, |name| name == "name" || is_fan_attribute(name)));
+        }
+        for (_, device) in sysfs::numbered_entries(&self.path("sys/class/thermal"), "cooling_device") {
+            paths.extend(files_in(&device, |name| matches!(name, "type" | "cur_state" | "max_state")));
+        }
+        for (_, zone) in sysfs::numbered_entries(&self.path("sys/class/powercap"), "intel-rapl:") {
+            paths.extend(files_in(&zone, |name| name == "name" || name.starts_with("constraint_")));
+        }
+        let cpu = self.path("sys/devices/system/cpu");
+        paths.extend(files_in(&cpu.join("cpu0/cpufreq"), |name| {
+            name.starts_with("scaling_") || name.starts_with("energy_performance") || name.starts_with("cpuinfo_")
+        }));
+        paths.extend([cpu.join("cpufreq/boost"), cpu.join("intel_pstate/no_turbo")]);
+
+        paths
+            .into_iter()
+            .filter(|path| path.is_file())
+            .map(|path| attribute_status(&path, &self.root, &credentials))
+            .collect()
+    }
+
+    #[cfg(unix)]
+    fn credentials(&self) -> Permissions {
+        let status = sysfs::read_optional(&self.path("proc/self/status")).unwrap_or_default();
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(|rest| rest.split_whitespace().filter_map(|v| v.parse::<u32>().ok()).collect::<Vec<_>>())
+        };
+        // `Uid:` lists real, effective, saved and filesystem ids.
+        let euid = field("Uid:").and_then(|ids| ids.get(1).copied());
+        Permissions {
+            euid,
+            groups: field("Groups:").unwrap_or_default(),
+            elevated: euid.map(|uid| uid == 0),
+        }
+    }
+
+    /// `net session` only succeeds in an elevated process.
+    #[cfg(windows)]
+    fn credentials(&self) -> Permissions {
+        let elevated = self
+            .runner
+            .run(&CommandSpec::new("net").arg("session"))
+            .ok()
+            .map(|output| output.success());
+        Permissions {
+            elevated,
+            ..Permissions::default()
+        }
+    }
+
+    fn permissions(&self) -> Permissions {
+        self.credentials()
+    }
+
+    /// Recent kernel log lines about fans, thermals or the watched modules. `dmesg` may be
+    /// restricted to root (`kernel.dmesg_restrict`); then the list stays empty.
+    fn kernel_messages(&self) -> Vec<String> {
+        if !cfg!(target_os = "linux") {
+            return Vec::new();
+        }
+        let Ok(output) = self.runner.run(&CommandSpec::new("dmesg")) else {
+            return Vec::new();
+        };
+        if !output.success() {
+            return Vec::new();
+        }
+        let relevant = |line: &str| {
+            let lower = line.to_ascii_lowercase();
+            let mut words = lower.split(|c: char| !c.is_ascii_alphanumeric());
+            lower.contains("acpi error")
+                || words.any(|word| matches!(word, "fan" | "fans" | "thermal" | "wmi"))
+                || WATCHED_MODULES.iter().any(|module| lower.contains(module) || lower.contains(&module.replace('_', "-")))
+        };
+        let lines: Vec<String> = output.stdout.lines().filter(|l| relevant(l)).map(String::from).collect();
+        let skip = lines.len().saturating_sub(KERNEL_MESSAGE_LIMIT);
+        lines.into_iter().skip(skip).collect()
+    }
+}
+
+fn is_fan_attribute(name: &str) -> bool {
+    (name.starts_with("pwm") || name.starts_with("fan")) && !name.ends_with("_label")
+}
+
+/// Regular files directly in `dir` whose names pass `keep`, sorted.
+fn files_in(dir: &Path, keep: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter(|e| e.file_name().to_str().is_some_and(&keep))
+        .map(|e| e.path())
+        .collect();
+    files.sort();
+    files
+}
+
+fn attribute_status(path: &Path, root: &Path, credentials: &Permissions) -> AttributeStatus {
+    let value = fs::read_to_string(path).ok().map(|v| v.trim().to_string());
+    let display = Path::new("/").join(path.strip_prefix(root).unwrap_or(path));
+    AttributeStatus {
+        path: display.display().to_string(),
+        mode: mode_string(path),
+        readable: value.is_some(),
+        writable: may_write(path, credentials),
+        value: value.filter(|v| v.len() <= MAX_VALUE_LEN),
+    }
+}
+
+#[cfg(unix)]
+fn mode_string(path: &Path) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).ok().map(|m| format!("{:04o}", m.permissions().mode() & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn mode_string(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+fn may_write(path: &Path, credentials: &Permissions) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let Ok(meta) = fs::metadata(path) else {
+        return false;
+    };
+    let mode = meta.mode();
+    match credentials.euid {
+        Some(0) => mode & 0o222 != 0,
+        Some(uid) if uid == meta.uid() => mode & 0o200 != 0,
+        _ if credentials.groups.contains(&meta.gid()) => mode & 0o020 != 0,
+        _ => mode & 0o002 != 0,
+    }
+}
+
+#[cfg(not(unix))]
+fn may_write(path: &Path, _credentials: &Permissions) -> bool {
+    fs::metadata(path).is_ok_and(|m| !m.permissions().readonly())
+}
+
+/// Hints a person would otherwise work out from the checklist.
+fn findings(report: &DoctorReport) -> Vec<String> {
+    let mut findings = Vec::new();
+
+    if !report.probes.iter().any(|p| p.result.is_ok()) {
+        findings.push("no driver initialized; see the probe errors for what each one is missing".to_string());
+    }
+    if let Some(e) = &report.power_config_error {
+        findings.push(format!("power.toml was ignored: {}", e));
+    }
+
+    let vendor = report.dmi.get("sys_vendor").map(String::as_str).unwrap_or_default();
+    let module = |name: &str| report.modules.iter().find(|m| m.name == name);
+    for (prefix, candidates) in VENDOR_MODULES {
+        if !vendor.starts_with(prefix) {
+            continue;
+        }
+        let statuses: Vec<&ModuleStatus> = candidates.iter().filter_map(|name| module(name)).collect();
+        if statuses.iter().any(|m| m.loaded || m.builtin) {
+            continue;
+        }
+        match statuses[..] {
+            [] => {}
+            [status] => findings.push(format!(
+                "{} machine but the {} module is not loaded (try `modprobe {}`)",
+                vendor, status.name, status.name
+            )),
+            _ => {
+                let names: Vec<&str> = statuses.iter().map(|m| m.name.as_str()).collect();
+                findings.push(format!(
+                    "{} machine but none of the {} modules is loaded (try `modprobe` with the one for this model)",
+                    vendor,
+                    names.join("/")
+                ))
+            }
+        }
+    }
+
+    let locked = report
+        .attributes
+        .iter()
+        // Owner-writable but not by us: the usual root-only control attribute.
+        .filter(|a| !a.writable && a.mode.as_deref().and_then(|m| u32::from_str_radix(m, 8).ok()).is_some_and(|m| m & 0o200 != 0))
+        .count();
+    match report.permissions.elevated {
+        Some(false) if locked > 0 => findings.push(format!(
+            "{} control attributes are writable only by root; run with sudo",
+            locked
+        )),
+        Some(false) if cfg!(windows) => {
+            findings.push("not running as Administrator; HP BIOS and power plan changes need elevation".to_string())
+        }
+        _ => {}
+    }
+
+    if let Some(last) = report.recent_errors.last() {
+        findings.push(format!("last recorded error: {}", last));
+    }
+    findings
+}
+
+impl DoctorReport {
+    /// Remove the user name, home directory and host name from every string. DMI serials are
+    /// never read in the first place.
+    pub fn redacted(self) -> Self {
+        self.redacted_with(&Redactor::current())
+    }
+
+    fn redacted_with(mut self, redactor: &Redactor) -> Self {
+        for value in self.dmi.values_mut() {
+            *value = redactor.apply(value);
+        }
+        if let Some(e) = &mut self.power_config_error {
+            *e = redact_error(e, redactor);
+        }
+        for attempt in &mut self.probes {
+            if let Err(e) = &mut attempt.result {
+                *e = redact_error(e, redactor);
+            }
+            attempt.description = redactor.apply(&attempt.description);
+            for line in &mut attempt.details {
+                *line = redactor.apply(line);
+            }
+        }
+        for attribute in &mut self.attributes {
+            attribute.path = redactor.apply(&attribute.path);
+            attribute.value = attribute.value.as_deref().map(|v| redactor.apply(v));
+        }
+        for line in self
+            .recent_errors
+            .iter_mut()
+            .chain(self.kernel_messages.iter_mut())
+            .chain(self.findings.iter_mut())
+        {
+            *line = redactor.apply(line);
+        }
+        self
+    }
+}
+
+fn redact_error(error: &DriverError, redactor: &Redactor) -> DriverError {
+    match error {
+        DriverError::NotReady(m) => DriverError::NotReady(redactor.apply(m)),
+        DriverError::Unsupported(m) => DriverError::Unsupported(redactor.apply(m)),
+        DriverError::Io(m) => DriverError::Io(redactor.apply(m)),
+        DriverError::Internal(m) => DriverError::Internal(redactor.apply(m)),
+    }
+}
+
+/// Replaces identifying strings of the current user and machine.
+struct Redactor {
+    replacements: Vec<(String, &'static str)>,
+}
+
+impl Redactor {
+    fn current() -> Self {
+        let env = |name: &str| std::env::var(name).ok();
+        let hostname = sysfs::read_optional(Path::new("/proc/sys/kernel/hostname")).or_else(|| env("COMPUTERNAME"));
+        Self::new(env("HOME").or_else(|| env("USERPROFILE")), env("USER").or_else(|| env("USERNAME")), hostname)
+    }
+
+    fn new(home: Option<String>, user: Option<String>, host: Option<String>) -> Self {
+        // Longest first, so the home directory is replaced before the user name inside it.
+        let mut replacements: Vec<(String, &'static str)> = [(home, "~"), (user, "<user>"), (host, "<host>")]
+            .into_iter()
+            .filter_map(|(value, label)| value.filter(|v| !v.is_empty() && v != "root").map(|v| (v, label)))
+            .collect();
+        replacements.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+        Self { replacements }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        self.replacements
+            .iter()
+            .fold(text.to_string(), |text, (value, label)| replace_whole(&text, value, label))
+    }
+}
+
+/// Characters that continue a name: an occurrence next to one is part of another word.
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// `text` with the occurrences of `value` that stand alone replaced by `label`: not inside a
+/// longer word, and for values under three characters (too common inside ordinary words) only
+/// as a path segment, e.g. the `al` of `/home/al`.
+fn replace_whole(text: &str, value: &str, label: &str) -> String {
+    let short = value.chars().count() < 3;
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, _) in text.match_indices(value) {
+        let end = start + value.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        let ends_word = after.is_none_or(|c| !is_name_char(c));
+        let standalone = if short {
+            matches!(before, Some('/' | '\\')) && ends_word
+        } else {
+            before.is_none_or(|c| !is_name_char(c)) && ends_word
+        };
+        if standalone {
+            out.push_str(&text[copied..start]);
+            out.push_str(label);
+            copied = end;
+        }
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A report for a `vendor` machine on which only `loaded` of the watched modules are loaded
+    /// and a driver initialized, so the vendor check is the only source of findings.
+    fn report(vendor: &str, loaded: &[&str]) -> DoctorReport {
+        DoctorReport {
+            platform: PlatformInfo {
+                os: "linux",
+                arch: "x86_64",
+                kernel: None,
+            },
+            dmi: BTreeMap::from([("sys_vendor".to_string(), vendor.to_string())]),
+            probes: vec![ProbeAttempt {
+                name: "cpufreq".into(),
+                description: "Linux cpufreq governor/EPP".into(),
+                result: Ok(()),
+                details: Vec::new(),
+            }],
+            power_config_error: None,
+            modules: WATCHED_MODULES
+                .iter()
+                .map(|name| ModuleStatus {
+                    name: name.to_string(),
+                    loaded: loaded.contains(name),
+                    builtin: false,
+                })
+                .collect(),
+            attributes: Vec::new(),
+            permissions: Permissions::default(),
+            recent_errors: Vec::new(),
+            kernel_messages: Vec::new(),
+            findings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn any_lenovo_fan_module_satisfies_the_vendor_check() {
+        for module in ["thinkpad_acpi", "ideapad_laptop", "legion_laptop"] {
+            assert_eq!(findings(&report("LENOVO", &[module])), Vec::<String>::new(), "{}", module);
+        }
+        let missing = findings(&report("LENOVO", &["intel_rapl_common"]));
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].contains("thinkpad_acpi/ideapad_laptop/legion_laptop"));
+    }
+
+    #[test]
+    fn single_module_vendors_name_the_module() {
+        assert_eq!(
+            findings(&report("Dell Inc.", &[])),
+            ["Dell Inc. machine but the dell_smm_hwmon module is not loaded (try `modprobe dell_smm_hwmon`)"]
+        );
+        assert!(findings(&report("Dell Inc.", &["dell_smm_hwmon"])).is_empty());
+        assert!(findings(&report("Framework", &[])).is_empty());
+    }
+
+    #[test]
+    fn redacts_names_only_as_whole_words() {
+        let redactor = Redactor::new(Some("/home/dan".into()), Some("dan".into()), Some("workbox".into()));
+
+        assert_eq!(redactor.apply("/home/dan/.config/edge_optimizer"), "~/.config/edge_optimizer");
+        assert_eq!(redactor.apply("owned by dan, on workbox.lan"), "owned by <user>, on <host>.lan");
+        assert_eq!(redactor.apply("/srv/dan/cache"), "/srv/<user>/cache");
+        assert_eq!(redactor.apply("redundant dance: /home/danny"), "redundant dance: /home/danny");
+        assert_eq!(redactor.apply("workbox-2 dan_x"), "workbox-2 dan_x");
+    }
+
+    #[test]
+    fn redacts_short_names_as_path_segments() {
+        let redactor = Redactor::new(None, Some("al".into()), Some("db".into()));
+
+        assert_eq!(redactor.apply("/home/al/.config and C:\\Users\\al"), "/home/<user>/.config and C:\\Users\\<user>");
+        assert_eq!(redactor.apply("total al /home/ally"), "total al /home/ally");
+        assert_eq!(redactor.apply("/net/db/share, db down"), "/net/<host>/share, db down");
+    }
+
+    #[test]
+    fn leaves_root_and_empty_names_alone() {
+        let redactor = Redactor::new(Some(String::new()), Some("root".into()), None);
+        assert_eq!(redactor.apply("/root/.config owned by root"), "/root/.config owned by root");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn collects_from_a_fixture_root() {
+        use std::os::unix::fs::PermissionsExt;
+
+        use crate::command::{CommandOutput, ScriptedRunner};
+        use crate::test_support::Fixture;
+
+        let fixture = Fixture::new();
+        fixture
+            .write("proc/sys/kernel/osrelease", "6.8.0-45-generic\n")
+            .write("sys/class/dmi/id/sys_vendor", "LENOVO\n")
+            .write("sys/class/dmi/id/product_name", "21AH\n")
+            .write("sys/class/dmi/id/product_serial", "PF3XYZ\n")
+            .write("proc/modules", "thinkpad_acpi 200704 0 - Live 0x0\nintel_rapl_common 40960 1 intel_rapl_msr, Live 0x0\n")
+            .write("proc/acpi/ibm/fan", "status:\t\tenabled\nlevel:\t\tauto\n")
+            .write("proc/self/status", "Name:\tedgeopt\nUid:\t4242\t4242\t4242\t4242\nGroups:\t4242 27\n");
+        fs::create_dir_all(fixture.path("sys/module/acpi_cpufreq")).unwrap();
+        fs::set_permissions(fixture.path("proc/acpi/ibm/fan"), fs::Permissions::from_mode(0o644)).unwrap();
+        let dmesg = "[    1.0] thinkpad_acpi: ThinkPad BIOS N3MET15W\n[    2.0] usb 1-1: new device\n[    3.0] ACPI Error: AE_NOT_FOUND\n";
+        let runner = ScriptedRunner::new().expect("dmesg", Ok(CommandOutput::ok(dmesg)));
+
+        let report = Doctor::with_runner(fixture.root(), &runner).collect(Some("thinkpad"));
+
+        assert_eq!(report.platform.kernel.as_deref(), Some("6.8.0-45-generic"));
+        assert_eq!(report.dmi.get("sys_vendor").map(String::as_str), Some("LENOVO"));
+        assert_eq!(report.dmi.get("product_name").map(String::as_str), Some("21AH"));
+        assert_eq!(report.dmi.get("product_serial").map(String::as_str), Some(REDACTED));
+        assert!(!report.dmi.contains_key("bios_version"));
+
+        let module = |name: &str| report.modules.iter().find(|m| m.name == name).unwrap();
+        assert!(module("thinkpad_acpi").loaded);
+        assert!(!module("acpi_cpufreq").loaded && module("acpi_cpufreq").builtin);
+        assert!(!module("ideapad_laptop").loaded && !module("ideapad_laptop").builtin);
+
+        assert_eq!(report.attributes.len(), 1);
+        let fan = &report.attributes[0];
+        assert_eq!(fan.path, "/proc/acpi/ibm/fan");
+        assert_eq!(fan.mode.as_deref(), Some("0644"));
+        assert!(fan.readable && !fan.writable);
+        assert_eq!(fan.value.as_deref(), Some("status:\t\tenabled\nlevel:\t\tauto"));
+
+        assert_eq!(report.permissions.euid, Some(4242));
+        assert_eq!(report.permissions.groups, vec![4242, 27]);
+        assert_eq!(report.permissions.elevated, Some(false));
+
+        assert_eq!(
+            report.kernel_messages,
+            vec!["[    1.0] thinkpad_acpi: ThinkPad BIOS N3MET15W", "[    3.0] ACPI Error: AE_NOT_FOUND"]
+        );
+        assert_eq!(report.probes.len(), 1);
+        assert!(report
+            .findings
+            .contains(&"1 control attributes are writable only by root; run with sudo".to_string()));
+        assert!(!report.findings.iter().any(|f| f.contains("LENOVO machine")));
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn redaction_covers_every_field() {
+        let home = "/home/dan/.config/edge_optimizer";
+        let mut report = report("workbox", &[]);
+        report.dmi.insert("product_name".into(), "dan's workbox".into());
+        report.probes.push(ProbeAttempt {
+            name: "config:fan".into(),
+            description: format!("config driver {}/drivers.d/fan.toml", home),
+            result: Err(DriverError::Io(format!("read {}/drivers.d/fan.toml: denied", home))),
+            details: vec!["helper running as dan".into()],
+        });
+        report.power_config_error = Some(DriverError::Internal(format!("{}/power.toml: bad", home)));
+        report.attributes.push(AttributeStatus {
+            path: "/sys/class/hwmon/hwmon3/name".into(),
+            mode: Some("0444".into()),
+            readable: true,
+            writable: false,
+            value: Some("workbox".into()),
+        });
+        report.recent_errors.push(format!("1700000000 max: {}/previous_profile: denied", home));
+        report.kernel_messages.push("[    5.0] workbox kernel: redundant fan check".into());
+        report.findings.push("last recorded error: dan on workbox".into());
+
+        let redactor = Redactor::new(Some("/home/dan".into()), Some("dan".into()), Some("workbox".into()));
+        let redacted = report.redacted_with(&redactor);
+
+        assert_eq!(redacted.dmi["sys_vendor"], "<host>");
+        assert_eq!(redacted.dmi["product_name"], "<user>'s <host>");
+        let probe = &redacted.probes[1];
+        assert_eq!(probe.description, "config driver ~/.config/edge_optimizer/drivers.d/fan.toml");
+        assert!(matches!(&probe.result, Err(DriverError::Io(m)) if m == "read ~/.config/edge_optimizer/drivers.d/fan.toml: denied"));
+        assert_eq!(probe.details, vec!["helper running as <user>"]);
+        assert!(matches!(&redacted.power_config_error, Some(DriverError::Internal(m)) if m == "~/.config/edge_optimizer/power.toml: bad"));
+        assert_eq!(redacted.attributes[0].value.as_deref(), Some("<host>"));
+        assert_eq!(redacted.recent_errors, vec!["1700000000 max: ~/.config/edge_optimizer/previous_profile: denied"]);
+        // "redundant" contains the user name but is not it.
+        assert_eq!(redacted.kernel_messages, vec!["[    5.0] <host> kernel: redundant fan check"]);
+        assert_eq!(redacted.findings, vec!["last recorded error: <user> on <host>"]);
+    }
+}
@@ -1,8 +1,12 @@
+use crate::explain::Explanation;
 use crate::model::{FanCapabilities, FanTelemetry, ProfileId};
 use thiserror::Error;
 
 /// Errors surfaced by the driver boundary.
+/// Serialized as `{"kind": "not_ready", "message": "..."}`.
 #[derive(Debug, Error)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "kind", content = "message", rename_all = "snake_case"))]
 pub enum DriverError {
     #[error("driver not ready: {0}")]
     NotReady(String),
@@ -27,6 +31,19 @@ pub trait FanDriver {
 
     /// Optional: fetch current telemetry to aid verification.
     fn telemetry(&self) -> Result<FanTelemetry, DriverError>;
+
+    /// Dry run of `set_profile`: the writes, commands or calls it would make and the checks it
+    /// would run, failing the same way for unknown profiles. May read state, never writes it.
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let _ = profile;
+        Err(DriverError::Unsupported("dry run not implemented by this driver".into()))
+    }
+
+    /// Facts about the hardware behind the driver (firmware features, fans), one per line, for
+    /// `edgeopt drivers` and `doctor`. Empty unless the driver has something to report.
+    fn diagnose(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 /// A no-op driver for platforms without support; allows graceful degradation.
@@ -48,4 +65,35 @@ impl FanDriver for UnsupportedDriver {
     fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
         Err(DriverError::Unsupported("telemetry not exposed".into()))
     }
+
+    fn explain_profile(&self, _profile: ProfileId) -> Result<Explanation, DriverError> {
+        Err(DriverError::Unsupported("fan profiles not exposed".into()))
+    }
+}
+
+/// Lets runtime-selected drivers (`Box<dyn FanDriver>`) be handed to `FanManager`.
+impl<D: FanDriver + ?Sized> FanDriver for Box<D> {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        (**self).capabilities()
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        (**self).current_profile()
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        (**self).set_profile(profile)
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        (**self).telemetry()
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        (**self).explain_profile(profile)
+    }
+
+    fn diagnose(&self) -> Vec<String> {
+        (**self).diagnose()
+    }
 }
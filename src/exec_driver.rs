@@ -0,0 +1,428 @@
+//! External-process driver: talks to a helper executable over stdin/stdout so fan backends can be
+//! written in any language.
+//!
+//! Protocol (version 1): one JSON object per line in each direction.
+//!
+//! ```text
+//! -> {"protocol":1,"id":1,"method":"hello","params":{}}
+//! <- {"id":1,"result":{"protocol":1,"name":"My board"}}
+//! -> {"protocol":1,"id":2,"method":"capabilities","params":{}}
+//! <- {"id":2,"result":{"supported":true,"profiles":[{"id":0,"name":"Auto","is_maximum":false}]}}
+//! -> {"protocol":1,"id":3,"method":"current_profile","params":{}}
+//! <- {"id":3,"result":{"profile":0}}
+//! -> {"protocol":1,"id":4,"method":"set_profile","params":{"profile":1}}
+//! <- {"id":4,"result":{}}
+//! -> {"protocol":1,"id":5,"method":"telemetry","params":{}}
+//! <- {"id":5,"result":{"rpm":4200,"temperature_c":61.5,"fan_duty_percent":null}}
+//! <- {"id":6,"error":{"kind":"unsupported","message":"no tachometer"}}
+//! ```
+//!
+//! Error kinds mirror `DriverError`: `not_ready`, `unsupported`, `io`, `internal`.
+//! The helper is spawned when the driver is created and kept running; a request that exceeds the
+//! timeout kills it, and the next request spawns a fresh one.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::{Action, Explanation};
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+
+/// Protocol version spoken by this build.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+/// How to launch a helper. Loadable from a `drivers.d` TOML file with an `[exec]` table:
+///
+/// ```toml
+/// [exec]
+/// command = "/usr/lib/edge_optimizer/helpers/my-board"
+/// args = ["--verbose"]
+/// timeout_ms = 3000
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExecConfig {
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    DEFAULT_TIMEOUT_MS
+}
+
+#[derive(Deserialize)]
+struct ExecConfigFile {
+    exec: ExecConfig,
+}
+
+impl ExecConfig {
+    pub fn new(command: impl Into<PathBuf>) -> Self {
+        Self {
+            command: command.into(),
+            args: Vec::new(),
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self, DriverError> {
+        toml::from_str::<ExecConfigFile>(text)
+            .map(|file| file.exec)
+            .map_err(|e| DriverError::Internal(format!("invalid exec config: {}", e)))
+    }
+
+    /// Whether a config file declares an `[exec]` helper rather than a sysfs mapping.
+    pub fn is_exec_config(text: &str) -> bool {
+        toml::from_str::<toml::Table>(text).is_ok_and(|table| table.contains_key("exec"))
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    protocol: u32,
+    id: u64,
+    method: &'a str,
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: u64,
+    result: Option<Value>,
+    error: Option<WireError>,
+}
+
+#[derive(Deserialize)]
+struct WireError {
+    kind: String,
+    message: String,
+}
+
+impl From<WireError> for DriverError {
+    fn from(e: WireError) -> Self {
+        match e.kind.as_str() {
+            "not_ready" => DriverError::NotReady(e.message),
+            "unsupported" => DriverError::Unsupported(e.message),
+            "io" => DriverError::Io(e.message),
+            _ => DriverError::Internal(e.message),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HelloResult {
+    protocol: u32,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WireProfile {
+    id: ProfileId,
+    name: String,
+    #[serde(default)]
+    is_maximum: bool,
+}
+
+#[derive(Deserialize)]
+struct CapabilitiesResult {
+    supported: bool,
+    profiles: Vec<WireProfile>,
+}
+
+#[derive(Deserialize)]
+struct CurrentProfileResult {
+    profile: Option<ProfileId>,
+}
+
+#[derive(Deserialize)]
+struct TelemetryResult {
+    rpm: Option<u32>,
+    temperature_c: Option<f32>,
+    fan_duty_percent: Option<u8>,
+}
+
+/// A running helper process.
+struct Helper {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Drop for Helper {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct ExecDriver {
+    config: ExecConfig,
+    helper: Mutex<Option<Helper>>,
+    next_id: AtomicU64,
+    name: Mutex<Option<String>>,
+}
+
+impl ExecDriver {
+    /// Spawn the helper and complete the `hello` handshake.
+    pub fn new(config: ExecConfig) -> Result<Self, DriverError> {
+        let driver = Self {
+            config,
+            helper: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+            name: Mutex::new(None),
+        };
+        let helper = driver.spawn()?;
+        *driver.helper.lock().unwrap() = Some(helper);
+        Ok(driver)
+    }
+
+    /// Load an `[exec]` config file and spawn its helper.
+    pub fn from_file(path: &Path) -> Result<Self, DriverError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| DriverError::Io(format!("read {}: {}", path.display(), e)))?;
+        Self::new(ExecConfig::from_toml_str(&text)?)
+    }
+
+    /// Name announced by the helper in its `hello` response.
+    pub fn name(&self) -> Option<String> {
+        self.name.lock().unwrap().clone()
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.config.timeout_ms)
+    }
+
+    fn spawn(&self) -> Result<Helper, DriverError> {
+        let mut child = Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                DriverError::NotReady(format!("failed to start helper {}: {}", self.config.command.display(), e))
+            })?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        // Reader thread so requests can time out; it ends when the helper closes stdout.
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut helper = Helper { child, stdin, lines };
+        let hello: HelloResult = decode(self.exchange(&mut helper, "hello", json!({}))?)?;
+        if hello.protocol != PROTOCOL_VERSION {
+            return Err(DriverError::NotReady(format!(
+                "helper speaks protocol {}, expected {}",
+                hello.protocol, PROTOCOL_VERSION
+            )));
+        }
+        *self.name.lock().unwrap() = hello.name;
+        Ok(helper)
+    }
+
+    /// Send one request and wait for the response with the matching id.
+    fn exchange(&self, helper: &mut Helper, method: &str, params: Value) -> Result<Value, DriverError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = Request {
+            protocol: PROTOCOL_VERSION,
+            id,
+            method,
+            params,
+        };
+        let mut line = serde_json::to_string(&request).map_err(|e| DriverError::Internal(e.to_string()))?;
+        line.push('\n');
+        helper
+            .stdin
+            .write_all(line.as_bytes())
+            .and_then(|_| helper.stdin.flush())
+            .map_err(|e| DriverError::Io(format!("helper stdin: {}", e)))?;
+
+        loop {
+            let raw = match helper.lines.recv_timeout(self.timeout()) {
+                Ok(raw) => raw,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(DriverError::Io(format!(
+                        "helper did not answer {} within {} ms",
+                        method, self.config.timeout_ms
+                    )))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(DriverError::Io("helper exited".into()));
+                }
+            };
+            let response: Response = serde_json::from_str(&raw)
+                .map_err(|e| DriverError::Internal(format!("malformed helper response {:?}: {}", raw, e)))?;
+            if response.id != id {
+                // Late answer to an earlier request; skip it.
+                continue;
+            }
+            return match (response.result, response.error) {
+                (_, Some(error)) => Err(error.into()),
+                (Some(result), None) => Ok(result),
+                (None, None) => Ok(Value::Null),
+            };
+        }
+    }
+
+    /// Issue a request, spawning the helper if needed. Transport failures discard the helper
+    /// (killing it) so the next call starts clean; protocol-level errors keep it running.
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, DriverError> {
+        let mut guard = self.helper.lock().unwrap();
+        if guard.is_none() {
+            *guard = Some(self.spawn()?);
+        }
+        let helper = guard.as_mut().expect("helper spawned above");
+
+        match self.exchange(helper, method, params) {
+            Ok(value) => decode(value),
+            Err(e @ DriverError::Io(_)) => {
+                *guard = None;
+                Err(e)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn decode<T: DeserializeOwned>(value: Value) -> Result<T, DriverError> {
+    serde_json::from_value(value).map_err(|e| DriverError::Internal(format!("unexpected helper result: {}", e)))
+}
+
+impl FanDriver for ExecDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        let caps: CapabilitiesResult = self.call("capabilities", json!({}))?;
+        Ok(FanCapabilities {
+            supported: caps.supported,
+            profiles: caps
+                .profiles
+                .into_iter()
+                .map(|p| FanProfile {
+                    id: p.id,
+                    name: p.name,
+                    is_maximum: p.is_maximum,
+                })
+                .collect(),
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        let current: CurrentProfileResult = self.call("current_profile", json!({}))?;
+        Ok(current.profile)
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        let _: Value = self.call("set_profile", json!({ "profile": profile }))?;
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        if !self.capabilities()?.profiles.iter().any(|p| p.id == profile) {
+            return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile)));
+        }
+        let endpoint = std::iter::once(self.config.command.display().to_string())
+            .chain(self.config.args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(Explanation::new(profile)
+            .action(Action::RpcCall {
+                endpoint,
+                method: "set_profile".into(),
+                params: json!({ "profile": profile }),
+            })
+            .verify(format!("helper answers without an error within {} ms", self.config.timeout_ms)))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        let telemetry: TelemetryResult = self.call("telemetry", json!({}))?;
+        Ok(FanTelemetry {
+            rpm: telemetry.rpm,
+            temperature_c: telemetry.temperature_c,
+            fan_duty_percent: telemetry.fan_duty_percent,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+// The stub helper is a Python script.
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// `ExecDriver` running `tests/fixtures/exec_helper.py` in `mode`.
+    fn stub(mode: &str, timeout_ms: u64) -> Result<ExecDriver, DriverError> {
+        let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/exec_helper.py");
+        ExecDriver::new(ExecConfig {
+            command: "python3".into(),
+            args: vec![script.display().to_string(), mode.into()],
+            timeout_ms,
+        })
+    }
+
+    #[test]
+    fn handshake_and_profile_round_trip() {
+        let driver = stub("normal", DEFAULT_TIMEOUT_MS).unwrap();
+        assert_eq!(driver.name().as_deref(), Some("Stub board"));
+        let caps = driver.capabilities().unwrap();
+        assert_eq!(caps.profiles.len(), 2);
+        assert!(caps.profiles[1].is_maximum);
+        driver.set_profile(1).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+        assert!(matches!(driver.telemetry(), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn protocol_mismatch_is_not_ready() {
+        assert!(matches!(stub("old-protocol", DEFAULT_TIMEOUT_MS), Err(DriverError::NotReady(_))));
+    }
+
+    #[test]
+    fn responses_with_other_ids_are_skipped() {
+        let driver = stub("stale-id", DEFAULT_TIMEOUT_MS).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(0));
+        driver.set_profile(1).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn timeout_kills_the_helper_and_the_next_call_respawns_it() {
+        let driver = stub("slow", 300).unwrap();
+        let started = Instant::now();
+        assert!(matches!(driver.set_profile(1), Err(DriverError::Io(_))));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(driver.helper.lock().unwrap().is_none());
+        // A fresh helper answers, and never saw the timed-out request.
+        assert_eq!(driver.current_profile().unwrap(), Some(0));
+    }
+
+    #[test]
+    fn explain_rejects_profiles_the_helper_does_not_offer() {
+        let driver = stub("normal", DEFAULT_TIMEOUT_MS).unwrap();
+        assert_eq!(driver.explain_profile(1).unwrap().actions.len(), 1);
+        assert!(matches!(driver.explain_profile(7), Err(DriverError::Unsupported(_))));
+    }
+}
@@ -0,0 +1,148 @@
+//! Dry runs: what a profile change would do, described without touching hardware.
+//!
+//! Drivers build an `Explanation` in `FanDriver::explain_profile` from the same data their
+//! `set_profile` uses, so the listed writes, commands and BIOS calls are the ones a real run
+//! would make. Explaining may read state (which mode is active, which files exist) but never
+//! writes it.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::command::CommandSpec;
+use crate::model::ProfileId;
+
+/// One side effect of a profile change.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type", rename_all = "snake_case"))]
+pub enum Action {
+    /// Write `value` to a sysfs or procfs attribute.
+    WriteFile { path: PathBuf, value: String },
+    /// Run a process; `stdin` holds the script piped to it, if any.
+    RunCommand {
+        program: String,
+        args: Vec<String>,
+        stdin: Option<String>,
+    },
+    /// Activate a Windows power scheme (`powercfg /setactive`).
+    SetPowerScheme { guid: String, name: String },
+    /// HP `hpqBIntM` call; `method` is the `hpqBIOSInt<N>` variant picked by the output size.
+    BiosCall {
+        method: String,
+        command: u32,
+        command_type: u32,
+        data: Vec<u8>,
+    },
+    /// Call an exported function of a loaded library.
+    LibraryCall {
+        library: PathBuf,
+        function: String,
+        argument: i64,
+    },
+    /// JSON-RPC request to a helper process or service.
+    RpcCall {
+        endpoint: String,
+        method: String,
+        params: Value,
+    },
+}
+
+impl From<&CommandSpec> for Action {
+    fn from(spec: &CommandSpec) -> Self {
+        Action::RunCommand {
+            program: spec.program.clone(),
+            args: spec.args.clone(),
+            stdin: spec.stdin.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::WriteFile { path, value } => write!(f, "write {:?} to {}", value, path.display()),
+            Action::RunCommand { program, args, stdin } => {
+                write!(f, "run {}", program)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                match stdin {
+                    Some(script) => write!(f, " with stdin:\n{}", script.trim_end()),
+                    None => Ok(()),
+                }
+            }
+            Action::SetPowerScheme { guid, name } => write!(f, "powercfg /setactive {} ({})", guid, name),
+            Action::BiosCall {
+                method,
+                command,
+                command_type,
+                data,
+            } => {
+                write!(f, "BIOS {} command=0x{:X} type=0x{:X} data=", method, command, command_type)?;
+                if data.is_empty() {
+                    f.write_str("-")
+                } else {
+                    data.iter().try_for_each(|b| write!(f, "{:02x}", b))
+                }
+            }
+            Action::LibraryCall {
+                library,
+                function,
+                argument,
+            } => write!(f, "call {}({}) in {}", function, argument, library.display()),
+            Action::RpcCall { endpoint, method, params } => write!(f, "{} {} {}", endpoint, method, params),
+        }
+    }
+}
+
+/// Everything a profile change would do, in order.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct Explanation {
+    pub profile: ProfileId,
+    pub actions: Vec<Action>,
+    /// Checks that run after the actions, e.g. return codes or the manager's ramp check.
+    pub verification: Vec<String>,
+    /// State read while explaining that decided which actions are listed.
+    pub notes: Vec<String>,
+}
+
+impl Explanation {
+    pub fn new(profile: ProfileId) -> Self {
+        Self {
+            profile,
+            actions: Vec::new(),
+            verification: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn write(self, path: impl Into<PathBuf>, value: impl Into<String>) -> Self {
+        self.action(Action::WriteFile {
+            path: path.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Append `(path, value)` writes in order.
+    pub fn writes(self, writes: impl IntoIterator<Item = (PathBuf, String)>) -> Self {
+        writes.into_iter().fold(self, |explanation, (path, value)| explanation.write(path, value))
+    }
+
+    pub fn verify(mut self, check: impl Into<String>) -> Self {
+        self.verification.push(check.into());
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+}
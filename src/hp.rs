@@ -1,10 +1,12 @@
-use libloading::Library;
-use once_cell::sync::Lazy;
-use std::sync::Mutex;
+use std::path::PathBuf;
 use thiserror::Error;
 
 use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::hp_lighting::Rgb;
+use crate::hp_system::{FeatureFlags, HpBoardInfo, SystemDesignData, ThermalPolicyVersion};
 use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::native_lib::{NativeLibConfig, NativeLibDriver, NativeOp, NativeProfileConfig, SymbolMap, SymbolStatus};
 
 /// HP-specific thermal policy profile IDs based on HP OMEN Command Center behavior.
 /// These map to thermal profiles exposed by HP's native APIs.
@@ -19,10 +21,36 @@ pub enum HpThermalProfile {
 }
 
 impl HpThermalProfile {
+    pub const ALL: [HpThermalProfile; 5] = [Self::Default, Self::Performance, Self::Cool, Self::Quiet, Self::Extreme];
+
     fn to_profile_id(self) -> ProfileId {
         self as ProfileId
     }
 
+    fn from_profile_id(id: ProfileId) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.to_profile_id() == id)
+    }
+
+    /// Why the board described by `design` cannot run this profile, if it can't.
+    pub fn unsupported_reason(self, design: &SystemDesignData) -> Option<String> {
+        match self {
+            Self::Default | Self::Performance | Self::Cool => None,
+            Self::Quiet => match design.thermal_policy {
+                ThermalPolicyVersion::V0 => Some("thermal policy V0 boards have no Quiet mode".into()),
+                _ => None,
+            },
+            Self::Extreme => {
+                if !design.features.has(FeatureFlags::EXTREME_MODE_SUPPORTED) {
+                    Some("board does not support Extreme mode".into())
+                } else if !design.features.has(FeatureFlags::EXTREME_MODE_UNLOCKED) {
+                    Some("Extreme mode is locked in the BIOS".into())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
     fn to_fan_profile(self) -> FanProfile {
         match self {
             Self::Default => FanProfile {
@@ -53,7 +81,7 @@ impl HpThermalProfile {
         }
     }
 
-    fn all_profiles() -> Vec<FanProfile> {
+    pub(crate) fn all_profiles() -> Vec<FanProfile> {
         vec![
             Self::Default.to_fan_profile(),
             Self::Performance.to_fan_profile(),
@@ -62,6 +90,34 @@ impl HpThermalProfile {
             Self::Extreme.to_fan_profile(),
         ]
     }
+
+    /// Profiles the board runs; every profile when the board is unknown.
+    pub(crate) fn supported_profiles(board: Option<&HpBoardInfo>) -> Vec<FanProfile> {
+        Self::ALL
+            .into_iter()
+            .filter(|p| board.is_none_or(|b| p.unsupported_reason(&b.design).is_none()))
+            .map(Self::to_fan_profile)
+            .collect()
+    }
+
+    /// Keyboard tint shown while this profile is active.
+    pub fn color(self) -> Rgb {
+        match self {
+            Self::Default => Rgb::WHITE,
+            Self::Performance => Rgb::ORANGE,
+            Self::Cool => Rgb::CYAN,
+            Self::Quiet => Rgb::GREEN,
+            Self::Extreme => Rgb::RED,
+        }
+    }
+
+    /// Profile colors for `hp_lighting::ProfileColorDriver`.
+    pub fn profile_colors() -> Vec<(ProfileId, Rgb)> {
+        Self::ALL
+            .into_iter()
+            .map(|p| (p.to_profile_id(), p.color()))
+            .collect()
+    }
 }
 
 #[derive(Debug, Error)]
@@ -84,173 +140,156 @@ impl From<HpDriverError> for DriverError {
     }
 }
 
-/// FFI function signatures for NativeRpcClient.dll (inferred from HP OMEN ecosystem).
-/// Actual signatures may vary; these are best-effort based on typical HP RPC patterns.
-type SetThermalProfileFn = unsafe extern "C" fn(profile: u32) -> i32;
-type GetThermalProfileFn = unsafe extern "C" fn() -> i32;
-type GetFanSpeedFn = unsafe extern "C" fn() -> i32;
-type GetSystemTemperatureFn = unsafe extern "C" fn() -> i32;
-
-static HP_LIBRARY: Lazy<Mutex<Option<Library>>> = Lazy::new(|| Mutex::new(None));
-
-fn load_hp_library() -> Result<(), HpDriverError> {
-    let mut guard = HP_LIBRARY.lock().unwrap();
-    if guard.is_some() {
-        return Ok(());
-    }
-
-    // Try loading from system directories, HP OMEN, HP Victus, and HP Support Framework paths
-    let possible_paths = [
-        "NativeRpcClient.dll",
-        // HP Support Framework (HP Victus and other HP systems)
-        "C:\\Program Files (x86)\\HP\\HP Support Framework\\Modules\\NativeRpcClient.dll",
-        "C:\\Program Files\\HP\\HP Support Framework\\Modules\\NativeRpcClient.dll",
-        // HP OMEN Command Center
-        "C:\\Program Files\\HP\\OMEN\\NativeRpcClient.dll",
-        "C:\\Program Files (x86)\\HP\\OMEN\\NativeRpcClient.dll",
-        "C:\\Program Files\\OMEN\\NativeRpcClient.dll",
-        // HP OMEN Gaming Hub
-        "C:\\Program Files\\HP\\OMEN Gaming Hub\\NativeRpcClient.dll",
-        "C:\\Program Files (x86)\\HP\\OMEN Gaming Hub\\NativeRpcClient.dll",
-        "C:\\Program Files\\HP\\HPOmenGamingHub\\NativeRpcClient.dll",
-        "C:\\Program Files (x86)\\HP\\HPOmenGamingHub\\NativeRpcClient.dll",
-    ];
+/// Known install locations of NativeRpcClient.dll: system directories, HP OMEN, HP Victus
+/// and HP Support Framework paths.
+const HP_LIBRARY_PATHS: &[&str] = &[
+    "NativeRpcClient.dll",
+    // HP Support Framework (HP Victus and other HP systems)
+    "C:\\Program Files (x86)\\HP\\HP Support Framework\\Modules\\NativeRpcClient.dll",
+    "C:\\Program Files\\HP\\HP Support Framework\\Modules\\NativeRpcClient.dll",
+    // HP OMEN Command Center
+    "C:\\Program Files\\HP\\OMEN\\NativeRpcClient.dll",
+    "C:\\Program Files (x86)\\HP\\OMEN\\NativeRpcClient.dll",
+    "C:\\Program Files\\OMEN\\NativeRpcClient.dll",
+    // HP OMEN Gaming Hub
+    "C:\\Program Files\\HP\\OMEN Gaming Hub\\NativeRpcClient.dll",
+    "C:\\Program Files (x86)\\HP\\OMEN Gaming Hub\\NativeRpcClient.dll",
+    "C:\\Program Files\\HP\\HPOmenGamingHub\\NativeRpcClient.dll",
+    "C:\\Program Files (x86)\\HP\\HPOmenGamingHub\\NativeRpcClient.dll",
+];
 
-    for path in &possible_paths {
-        match unsafe { Library::new(path) } {
-            Ok(lib) => {
-                *guard = Some(lib);
-                return Ok(());
-            }
-            Err(_) => continue,
-        }
+/// Symbol names inferred from the HP OMEN ecosystem; actual exports may vary by version.
+fn hp_symbol_map() -> SymbolMap {
+    let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+    SymbolMap {
+        set_profile: names(&[
+            "SetThermalProfile",
+            "HP_SetThermalProfile",
+            "SetFanProfile",
+            "OMEN_SetThermalProfile",
+        ]),
+        get_profile: names(&["GetThermalProfile", "HP_GetThermalProfile", "GetFanProfile"]),
+        fan_rpm: names(&["GetFanSpeed"]),
+        temperature: names(&["GetSystemTemperature"]),
     }
-
-    Err(HpDriverError::DllNotFound(
-        "NativeRpcClient.dll not found in known HP paths. Ensure HP Support Framework, HP OMEN Gaming Hub, or HP OMEN Command Center is installed.".into()
-    ))
 }
 
 /// HP-specific driver implementation using NativeRpcClient.dll.
 /// Works with HP OMEN and HP Victus gaming laptops.
-pub struct HpOmenDriver;
+pub struct HpOmenDriver {
+    library: NativeLibDriver,
+    /// Board description from the BIOS; without it every profile is offered.
+    board: Option<HpBoardInfo>,
+}
 
 impl HpOmenDriver {
     pub fn new() -> Result<Self, HpDriverError> {
-        load_hp_library()?;
-        Ok(Self)
-    }
+        let config = NativeLibConfig {
+            search_paths: HP_LIBRARY_PATHS.iter().map(PathBuf::from).collect(),
+            symbols: hp_symbol_map(),
+            profiles: HpThermalProfile::all_profiles()
+                .into_iter()
+                .map(|p| NativeProfileConfig {
+                    id: p.id,
+                    name: p.name,
+                    maximum: p.is_maximum,
+                })
+                .collect(),
+        };
 
-    fn call_set_thermal_profile(&self, profile: u32) -> Result<(), HpDriverError> {
-        let guard = HP_LIBRARY.lock().unwrap();
-        let lib = guard.as_ref().ok_or_else(|| {
-            HpDriverError::DllNotFound("library not loaded".into())
+        // Keep the loader's per-path failures: a DLL that exists but won't load (wrong
+        // architecture, missing dependency) needs a different fix than a missing one.
+        let library = NativeLibDriver::new(config).map_err(|e| match e {
+            DriverError::NotReady(reason) => HpDriverError::DllNotFound(format!(
+                "{}. Ensure HP Support Framework, HP OMEN Gaming Hub, or HP OMEN Command Center is installed.",
+                reason
+            )),
+            other => HpDriverError::DllNotFound(other.to_string()),
         })?;
+        Ok(Self { library, board: None })
+    }
 
-        // Try multiple common function name patterns
-        let function_names: &[&[u8]] = &[
-            b"SetThermalProfile\0",
-            b"HP_SetThermalProfile\0",
-            b"SetFanProfile\0",
-            b"OMEN_SetThermalProfile\0",
-        ];
-
-        for name in function_names {
-            if let Ok(func) = unsafe { lib.get::<SetThermalProfileFn>(name) } {
-                let result = unsafe { func(profile) };
-                if result == 0 {
-                    return Ok(());
-                } else {
-                    return Err(HpDriverError::RpcFailed(format!("return code: {}", result)));
-                }
-            }
-        }
-
-        Err(HpDriverError::FunctionNotFound(
-            "SetThermalProfile or similar not found in NativeRpcClient.dll".into()
-        ))
+    /// Restrict profiles to what the board reports in its system design data.
+    pub fn with_board_info(mut self, board: HpBoardInfo) -> Self {
+        self.board = Some(board);
+        self
     }
 
-    fn call_get_thermal_profile(&self) -> Result<i32, HpDriverError> {
-        let guard = HP_LIBRARY.lock().unwrap();
-        let lib = guard.as_ref().ok_or_else(|| {
-            HpDriverError::DllNotFound("library not loaded".into())
-        })?;
+    pub fn board_info(&self) -> Option<&HpBoardInfo> {
+        self.board.as_ref()
+    }
 
-        let function_names: &[&[u8]] = &[
-            b"GetThermalProfile\0",
-            b"HP_GetThermalProfile\0",
-            b"GetFanProfile\0",
-        ];
+    /// Each profile with the reason it is unavailable on this board, if any.
+    pub fn profile_support(&self) -> Vec<(HpThermalProfile, Option<String>)> {
+        HpThermalProfile::ALL
+            .into_iter()
+            .map(|p| (p, self.board.as_ref().and_then(|b| p.unsupported_reason(&b.design))))
+            .collect()
+    }
 
-        for name in function_names {
-            if let Ok(func) = unsafe { lib.get::<GetThermalProfileFn>(name) } {
-                let result = unsafe { func() };
-                if result >= 0 {
-                    return Ok(result);
-                }
-            }
+    fn is_available(&self, profile: ProfileId) -> Result<(), DriverError> {
+        let reason = HpThermalProfile::from_profile_id(profile)
+            .and_then(|p| self.board.as_ref().and_then(|b| p.unsupported_reason(&b.design)));
+        match reason {
+            Some(reason) => Err(DriverError::Unsupported(reason)),
+            None => Ok(()),
         }
+    }
 
-        Err(HpDriverError::FunctionNotFound(
-            "GetThermalProfile or similar not found".into()
-        ))
+    /// Which thermal-control symbols the loaded DLL actually exports.
+    pub fn symbols(&self) -> &[SymbolStatus] {
+        self.library.symbols()
     }
 }
 
 impl FanDriver for HpOmenDriver {
     fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
-        // Verify we can load the library
-        load_hp_library().map_err(|e: HpDriverError| DriverError::from(e))?;
-        
-        Ok(FanCapabilities {
-            supported: true,
-            profiles: HpThermalProfile::all_profiles(),
-        })
+        let mut caps = self.library.capabilities()?;
+        caps.profiles.retain(|p| self.is_available(p.id).is_ok());
+        Ok(caps)
     }
 
     fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
-        match self.call_get_thermal_profile() {
-            Ok(profile_id) => Ok(Some(profile_id as ProfileId)),
-            Err(_) => Ok(None),
-        }
+        self.library.current_profile()
     }
 
     fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
-        self.call_set_thermal_profile(profile)
-            .map_err(|e| e.into())
+        if !self.library.supports(NativeOp::SetProfile) {
+            return Err(HpDriverError::FunctionNotFound(
+                "SetThermalProfile or similar not found in NativeRpcClient.dll".into(),
+            )
+            .into());
+        }
+        self.is_available(profile)?;
+        self.library.set_profile(profile)
     }
 
-    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
-        // Best-effort telemetry; HP DLL may not expose these
-        let guard = HP_LIBRARY.lock().unwrap();
-        let lib = guard.as_ref().ok_or_else(|| {
-            DriverError::NotReady("library not loaded".into())
-        })?;
-
-        let mut rpm = None;
-        let mut temp = None;
-
-        // Try to get fan RPM
-        if let Ok(func) = unsafe { lib.get::<GetFanSpeedFn>(b"GetFanSpeed\0") } {
-            let speed = unsafe { func() };
-            if speed >= 0 {
-                rpm = Some(speed as u32);
-            }
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        if !self.library.supports(NativeOp::SetProfile) {
+            return Err(HpDriverError::FunctionNotFound(
+                "SetThermalProfile or similar not found in NativeRpcClient.dll".into(),
+            )
+            .into());
         }
+        self.is_available(profile)?;
+        self.library.explain_profile(profile)
+    }
 
-        // Try to get temperature
-        if let Ok(func) = unsafe { lib.get::<GetSystemTemperatureFn>(b"GetSystemTemperature\0") } {
-            let t = unsafe { func() };
-            if t >= 0 {
-                temp = Some(t as f32);
-            }
-        }
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        // Best-effort telemetry; HP DLL may not expose these
+        self.library.telemetry()
+    }
 
-        Ok(FanTelemetry {
-            rpm,
-            temperature_c: temp,
-            timestamp: std::time::Instant::now(),
-        })
+    fn diagnose(&self) -> Vec<String> {
+        let mut lines = match &self.board {
+            Some(board) => board.diagnose(),
+            None => vec!["system design data unavailable: offering every profile".into()],
+        };
+        lines.extend(
+            self.profile_support()
+                .into_iter()
+                .filter_map(|(profile, reason)| Some(format!("{:?} unavailable: {}", profile, reason?))),
+        );
+        lines
     }
 }
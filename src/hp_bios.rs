@@ -0,0 +1,719 @@
+//! Typed model of HP's `hpqBIntM` BIOS WMI interface (`root\wmi`), the mechanism behind HP's
+//! max-fan and thermal controls. Every call passes an `hpqBDataIn` instance (`Sign` "SECU", `Command`,
+//! `CommandType`, `Size`, `hpqBData`) to `hpqBIOSInt<N>`, where `N` is the output buffer size.
+//! Requests and payload codecs live here; how a request reaches the BIOS is up to a
+//! `BiosTransport`.
+
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::{Action, Explanation};
+use crate::model::{FanCapabilities, FanTelemetry, ProfileId};
+use crate::state;
+
+/// `Sign` field of every request.
+pub const SIGN: [u8; 4] = *b"SECU";
+
+/// `Command` field: selects the BIOS command group.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiosCommand {
+    Legacy = 0x0000_0001,
+    GpuMode = 0x0000_0002,
+    Default = 0x0002_0008,
+    Keyboard = 0x0002_0009,
+}
+
+/// Output buffer size, which picks the `hpqBIOSInt<N>` method to call.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSize {
+    None = 0,
+    Small = 4,
+    Medium = 128,
+    Large = 1024,
+    Huge = 4096,
+}
+
+impl OutputSize {
+    pub fn bytes(self) -> usize {
+        self as usize
+    }
+
+    /// WMI method name, e.g. `hpqBIOSInt128`.
+    pub fn method_name(self) -> String {
+        format!("hpqBIOSInt{}", self as u32)
+    }
+}
+
+/// `CommandType` values, used with `BiosCommand::Default` unless noted otherwise.
+pub mod command_type {
+    pub const GET_MAX_FAN: u32 = 0x26;
+    pub const SET_MAX_FAN: u32 = 0x27;
+    pub const GET_GPU_POWER: u32 = 0x21;
+    pub const SET_GPU_POWER: u32 = 0x22;
+    pub const SET_CPU_POWER_LIMITS: u32 = 0x29;
+    /// Graphics mode; read with `BiosCommand::Legacy`, written with `BiosCommand::GpuMode`.
+    pub const GRAPHICS_MODE: u32 = 0x52;
+    pub const GET_FAN_COUNT: u32 = 0x10;
+    pub const GET_SYSTEM_DESIGN_DATA: u32 = 0x28;
+    pub const GET_FAN_TYPE: u32 = 0x2C;
+    pub const GET_FAN_LEVEL: u32 = 0x2D;
+    pub const SET_FAN_LEVEL: u32 = 0x2E;
+    pub const GET_TEMPERATURE: u32 = 0x23;
+    pub const GET_KEYBOARD_TYPE: u32 = 0x2B;
+    /// With `BiosCommand::Keyboard`.
+    pub const GET_COLOR_TABLE: u32 = 0x02;
+    /// With `BiosCommand::Keyboard`.
+    pub const SET_COLOR_TABLE: u32 = 0x03;
+    /// With `BiosCommand::Keyboard`.
+    pub const GET_BACKLIGHT: u32 = 0x04;
+    /// With `BiosCommand::Keyboard`.
+    pub const SET_BACKLIGHT: u32 = 0x05;
+}
+
+/// One BIOS call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BiosRequest {
+    pub command: BiosCommand,
+    pub command_type: u32,
+    /// `hpqBData`; empty requests are sent with `Size = 0` and no data.
+    pub data: Vec<u8>,
+    pub output: OutputSize,
+}
+
+impl BiosRequest {
+    pub fn new(command: BiosCommand, command_type: u32) -> Self {
+        Self {
+            command,
+            command_type,
+            data: Vec::new(),
+            output: OutputSize::None,
+        }
+    }
+
+    pub fn data(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    pub fn output(mut self, output: OutputSize) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Turn max fan on or off (what `HpBios::set_max_fan` sends).
+    pub fn set_max_fan(enabled: bool) -> Self {
+        Self::new(BiosCommand::Default, command_type::SET_MAX_FAN).data([enabled as u8])
+    }
+}
+
+impl From<&BiosRequest> for Action {
+    fn from(request: &BiosRequest) -> Self {
+        Action::BiosCall {
+            method: request.output.method_name(),
+            command: request.command as u32,
+            command_type: request.command_type,
+            data: request.data.clone(),
+        }
+    }
+}
+
+/// `hpqBDataOut`: `rwReturnCode` plus the output buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BiosResponse {
+    pub return_code: u32,
+    pub data: Vec<u8>,
+}
+
+impl BiosResponse {
+    /// Map a non-zero `rwReturnCode` to an error, returning the output data otherwise.
+    /// Codes 3 and 4 mean the board does not implement the command or command type.
+    pub fn check(self) -> Result<Vec<u8>, DriverError> {
+        match self.return_code {
+            0 => Ok(self.data),
+            code @ (3 | 4) => Err(DriverError::Unsupported(format!("BIOS command not available (code {})", code))),
+            code => Err(DriverError::Internal(format!("BIOS returned error {}", code))),
+        }
+    }
+}
+
+/// Delivers requests to the BIOS (PowerShell/CIM, a service, or a fake).
+pub trait BiosTransport {
+    fn call(&self, request: &BiosRequest) -> Result<BiosResponse, DriverError>;
+}
+
+impl<T: BiosTransport + ?Sized> BiosTransport for &T {
+    fn call(&self, request: &BiosRequest) -> Result<BiosResponse, DriverError> {
+        (**self).call(request)
+    }
+}
+
+/// Payloads shorter than `len` are a protocol error, not something to index into.
+pub(crate) fn expect_len(data: &[u8], len: usize, what: &str) -> Result<(), DriverError> {
+    if data.len() < len {
+        return Err(DriverError::Internal(format!(
+            "{}: expected {} bytes, got {}",
+            what,
+            len,
+            data.len()
+        )));
+    }
+    Ok(())
+}
+
+/// GPU device power state reported alongside the TGP settings.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuDState {
+    D1 = 1,
+    D2 = 2,
+    D3 = 3,
+    D4 = 4,
+    D5 = 5,
+}
+
+/// GPU power presets offered by OMEN Gaming Hub.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuPowerLevel {
+    /// Base TGP only.
+    Minimum,
+    /// Custom TGP on, PPAB off.
+    Medium,
+    /// Custom TGP and Processing Power AI Boost on.
+    Maximum,
+}
+
+/// GPU power settings (`GpuPowerData` in `Hardware/BiosData.cs`), 4 bytes on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpuPowerData {
+    /// Custom Total Graphics Power limit.
+    pub custom_tgp: bool,
+    /// Processing Power AI Boost.
+    pub ppab: bool,
+    pub d_state: GpuDState,
+    /// Sensor threshold; 0 leaves the firmware default.
+    pub peak_temperature: u8,
+}
+
+impl GpuPowerData {
+    pub const SIZE: usize = 4;
+
+    pub fn from_level(level: GpuPowerLevel) -> Self {
+        Self {
+            custom_tgp: level != GpuPowerLevel::Minimum,
+            ppab: level == GpuPowerLevel::Maximum,
+            d_state: GpuDState::D1,
+            peak_temperature: 0,
+        }
+    }
+
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        [self.custom_tgp as u8, self.ppab as u8, self.d_state as u8, self.peak_temperature]
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DriverError> {
+        expect_len(data, Self::SIZE, "GPU power data")?;
+        let d_state = match data[2] {
+            1 => GpuDState::D1,
+            2 => GpuDState::D2,
+            3 => GpuDState::D3,
+            4 => GpuDState::D4,
+            5 => GpuDState::D5,
+            other => return Err(DriverError::Internal(format!("unknown GPU D-state {}", other))),
+        };
+        Ok(Self {
+            custom_tgp: data[0] != 0,
+            ppab: data[1] != 0,
+            d_state,
+            peak_temperature: data[3],
+        })
+    }
+
+    pub fn get_request() -> BiosRequest {
+        BiosRequest::new(BiosCommand::Default, command_type::GET_GPU_POWER).output(OutputSize::Small)
+    }
+
+    pub fn set_request(&self) -> BiosRequest {
+        BiosRequest::new(BiosCommand::Default, command_type::SET_GPU_POWER).data(self.encode())
+    }
+}
+
+/// Byte the BIOS reads as "leave this limit unchanged".
+pub const POWER_LIMIT_UNCHANGED: u8 = 0xFF;
+
+/// Range CPU power limits are clamped to before they are sent. The BIOS accepts any byte, so
+/// this is the only thing standing between a typo and an unusable or overheating machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuPowerRange {
+    pub min_w: u8,
+    pub max_w: u8,
+}
+
+impl CpuPowerRange {
+    /// `max_w` is capped below `POWER_LIMIT_UNCHANGED` so a limit is never read as "unchanged".
+    pub fn new(min_w: u8, max_w: u8) -> Self {
+        let max_w = max_w.min(POWER_LIMIT_UNCHANGED - 1);
+        Self {
+            min_w: min_w.min(max_w),
+            max_w,
+        }
+    }
+
+    fn clamp(&self, watts: u8) -> u8 {
+        watts.clamp(self.min_w, self.max_w)
+    }
+}
+
+impl Default for CpuPowerRange {
+    /// Conservative envelope for Victus/OMEN laptop CPUs (H/HX parts).
+    fn default() -> Self {
+        Self::new(15, 140)
+    }
+}
+
+/// CPU package power limits in watts (`CpuPowerData` in `Hardware/BiosData.cs`). `None` leaves
+/// the board's current value in place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuPowerLimits {
+    /// Sustained limit.
+    pub pl1_w: Option<u8>,
+    /// Turbo limit.
+    pub pl2_w: Option<u8>,
+    /// Peak limit.
+    pub pl4_w: Option<u8>,
+}
+
+impl CpuPowerLimits {
+    pub const SIZE: usize = 4;
+
+    /// Clamp each set limit into `range` and keep them ordered PL1 <= PL2 <= PL4.
+    pub fn clamped(&self, range: CpuPowerRange) -> Self {
+        let pl1 = self.pl1_w.map(|w| range.clamp(w));
+        let pl2 = self.pl2_w.map(|w| range.clamp(w).max(pl1.unwrap_or(0)));
+        let pl4 = self.pl4_w.map(|w| range.clamp(w).max(pl2.or(pl1).unwrap_or(0)));
+        Self {
+            pl1_w: pl1,
+            pl2_w: pl2,
+            pl4_w: pl4,
+        }
+    }
+
+    /// Wire layout: PL1, PL2, PL4, reserved; unset limits are `POWER_LIMIT_UNCHANGED`.
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        let byte = |limit: Option<u8>| limit.unwrap_or(POWER_LIMIT_UNCHANGED);
+        [byte(self.pl1_w), byte(self.pl2_w), byte(self.pl4_w), POWER_LIMIT_UNCHANGED]
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DriverError> {
+        expect_len(data, Self::SIZE, "CPU power limits")?;
+        let limit = |b: u8| (b != POWER_LIMIT_UNCHANGED).then_some(b);
+        Ok(Self {
+            pl1_w: limit(data[0]),
+            pl2_w: limit(data[1]),
+            pl4_w: limit(data[2]),
+        })
+    }
+
+    /// Request setting these limits after clamping them into `range`.
+    pub fn set_request(&self, range: CpuPowerRange) -> BiosRequest {
+        BiosRequest::new(BiosCommand::Default, command_type::SET_CPU_POWER_LIMITS).data(self.clamped(range).encode())
+    }
+}
+
+/// Graphics MUX setting. Changes only take effect after a reboot.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsMode {
+    /// Integrated GPU drives the panel, discrete GPU renders on demand.
+    Hybrid = 0x00,
+    /// Discrete GPU drives the panel directly (MUX switched).
+    Discrete = 0x01,
+    /// Advanced Optimus: the driver switches the MUX at runtime.
+    Optimus = 0x02,
+}
+
+impl GraphicsMode {
+    pub fn decode(data: &[u8]) -> Result<Self, DriverError> {
+        expect_len(data, 1, "graphics mode")?;
+        match data[0] {
+            0x00 => Ok(Self::Hybrid),
+            0x01 => Ok(Self::Discrete),
+            0x02 => Ok(Self::Optimus),
+            other => Err(DriverError::Internal(format!("unknown graphics mode {:#04x}", other))),
+        }
+    }
+
+    pub fn get_request() -> BiosRequest {
+        BiosRequest::new(BiosCommand::Legacy, command_type::GRAPHICS_MODE).output(OutputSize::Small)
+    }
+
+    pub fn set_request(self) -> BiosRequest {
+        BiosRequest::new(BiosCommand::GpuMode, command_type::GRAPHICS_MODE).data([self as u8])
+    }
+}
+
+/// The BIOS only reports the configured mode. The mode in effect is the one configured at the
+/// first read during this boot (remembered in the state directory, keyed by `state::boot_id`);
+/// anything different configured since then is pending a reboot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphicsModeState {
+    pub active: GraphicsMode,
+    pub configured: GraphicsMode,
+}
+
+impl GraphicsModeState {
+    /// Mode that will take effect at the next boot, if it differs from the active one.
+    pub fn pending(&self) -> Option<GraphicsMode> {
+        (self.configured != self.active).then_some(self.configured)
+    }
+
+    pub fn reboot_required(&self) -> bool {
+        self.pending().is_some()
+    }
+}
+
+/// State key of the graphics mode this boot runs with.
+const BOOT_GRAPHICS_MODE_KEY: &str = "graphics_mode";
+
+/// Typed operations over a transport.
+pub struct HpBios<T: BiosTransport> {
+    transport: T,
+    cpu_range: CpuPowerRange,
+    /// Where the graphics mode of the running boot is remembered, and the boot's id.
+    boot_state: Option<(PathBuf, String)>,
+}
+
+impl<T: BiosTransport> HpBios<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            cpu_range: CpuPowerRange::default(),
+            boot_state: state::state_dir().zip(state::boot_id()),
+        }
+    }
+
+    /// Remember boot-scoped values in `dir` under boot id `boot` instead of the user's state
+    /// directory and the running boot.
+    pub fn with_boot_state(mut self, dir: &Path, boot: &str) -> Self {
+        self.boot_state = Some((dir.to_path_buf(), boot.to_string()));
+        self
+    }
+
+    /// Override the range CPU power limits are clamped to.
+    pub fn with_cpu_range(mut self, range: CpuPowerRange) -> Self {
+        self.cpu_range = range;
+        self
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Range CPU power limits are clamped to.
+    pub fn cpu_range(&self) -> CpuPowerRange {
+        self.cpu_range
+    }
+
+    /// Send a request and check its return code.
+    pub fn call(&self, request: &BiosRequest) -> Result<Vec<u8>, DriverError> {
+        self.transport.call(request)?.check()
+    }
+
+    pub fn set_max_fan(&self, enabled: bool) -> Result<(), DriverError> {
+        self.call(&BiosRequest::set_max_fan(enabled)).map(drop)
+    }
+
+    pub fn max_fan(&self) -> Result<bool, DriverError> {
+        let data = self.call(&BiosRequest::new(BiosCommand::Default, command_type::GET_MAX_FAN).output(OutputSize::Small))?;
+        expect_len(&data, 1, "max fan state")?;
+        Ok(data[0] != 0)
+    }
+
+    pub fn gpu_power(&self) -> Result<GpuPowerData, DriverError> {
+        GpuPowerData::decode(&self.call(&GpuPowerData::get_request())?)
+    }
+
+    pub fn set_gpu_power(&self, data: GpuPowerData) -> Result<(), DriverError> {
+        self.call(&data.set_request()).map(drop)
+    }
+
+    /// Set CPU power limits, clamped to the configured range. Returns what was actually sent.
+    pub fn set_cpu_power_limits(&self, limits: CpuPowerLimits) -> Result<CpuPowerLimits, DriverError> {
+        let clamped = limits.clamped(self.cpu_range);
+        self.call(&clamped.set_request(self.cpu_range))?;
+        Ok(clamped)
+    }
+
+    /// Configured mode and the one this boot runs with. Without a state directory or boot id
+    /// the configured mode is assumed active, so nothing shows as pending.
+    pub fn graphics_mode(&self) -> Result<GraphicsModeState, DriverError> {
+        let configured = GraphicsMode::decode(&self.call(&GraphicsMode::get_request())?)?;
+        let Some((dir, boot)) = &self.boot_state else {
+            return Ok(GraphicsModeState { active: configured, configured });
+        };
+        let remembered = state::boot_value(dir, BOOT_GRAPHICS_MODE_KEY, boot)
+            .and_then(|value| value.parse::<u8>().ok())
+            .and_then(|byte| GraphicsMode::decode(&[byte]).ok());
+        let active = match remembered {
+            Some(mode) => mode,
+            None => {
+                // First read this boot: take the configured mode as the running one. Best effort;
+                // if it can't be saved, the next read simply assumes the same.
+                let _ = state::set_boot_value(dir, BOOT_GRAPHICS_MODE_KEY, boot, &(configured as u8).to_string());
+                configured
+            }
+        };
+        Ok(GraphicsModeState { active, configured })
+    }
+
+    /// Configure `mode` for the next boot. The returned state's `pending()` tells whether a
+    /// reboot is needed; requesting the mode already configured sends nothing.
+    pub fn set_graphics_mode(&self, mode: GraphicsMode) -> Result<GraphicsModeState, DriverError> {
+        let state = self.graphics_mode()?;
+        if state.configured == mode {
+            return Ok(state);
+        }
+        self.call(&mode.set_request())?;
+        Ok(GraphicsModeState {
+            configured: mode,
+            ..state
+        })
+    }
+}
+
+/// CPU and GPU power a profile applies on HP boards. `None` leaves the setting alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HpPowerSettings {
+    pub cpu: Option<CpuPowerLimits>,
+    pub gpu: Option<GpuPowerLevel>,
+}
+
+/// Wraps a driver and sends the CPU and GPU power settings attached to each profile after
+/// switching it, so one profile change configures fan, CPU and GPU together. Profiles without
+/// a GPU level put back the GPU power found when the wrapper was created; the BIOS cannot
+/// report CPU limits, so profiles without CPU limits keep whatever was last sent.
+pub struct HpPowerDriver<D, T: BiosTransport> {
+    inner: D,
+    bios: HpBios<T>,
+    settings: Vec<(ProfileId, HpPowerSettings)>,
+    original_gpu: Option<GpuPowerData>,
+}
+
+impl<D: FanDriver, T: BiosTransport> HpPowerDriver<D, T> {
+    pub fn new(inner: D, bios: HpBios<T>) -> Self {
+        let original_gpu = bios.gpu_power().ok();
+        Self {
+            inner,
+            bios,
+            settings: Vec::new(),
+            original_gpu,
+        }
+    }
+
+    /// Attach settings to `profile`, replacing any attached before.
+    pub fn with_power(mut self, profile: ProfileId, settings: HpPowerSettings) -> Self {
+        self.settings.retain(|(id, _)| *id != profile);
+        self.settings.push((profile, settings));
+        self
+    }
+
+    pub fn power_for(&self, profile: ProfileId) -> HpPowerSettings {
+        self.settings
+            .iter()
+            .find(|(id, _)| *id == profile)
+            .map(|(_, settings)| *settings)
+            .unwrap_or_default()
+    }
+
+    /// Requests `set_profile(profile)` sends after switching the inner driver.
+    pub fn power_requests(&self, profile: ProfileId) -> Vec<BiosRequest> {
+        let settings = self.power_for(profile);
+        let mut requests = Vec::new();
+        if let Some(cpu) = settings.cpu {
+            requests.push(cpu.set_request(self.bios.cpu_range()));
+        }
+        let gpu = match settings.gpu {
+            Some(level) => Some(GpuPowerData::from_level(level)),
+            // Only undo GPU changes if some profile makes them.
+            None if self.settings.iter().any(|(_, s)| s.gpu.is_some()) => self.original_gpu,
+            None => None,
+        };
+        requests.extend(gpu.map(|data| data.set_request()));
+        requests
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+}
+
+impl<D: FanDriver, T: BiosTransport> FanDriver for HpPowerDriver<D, T> {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        self.inner.capabilities()
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        self.inner.current_profile()
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        self.inner.set_profile(profile)?;
+        for request in self.power_requests(profile) {
+            self.bios.call(&request)?;
+        }
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let mut explanation = self.inner.explain_profile(profile)?;
+        if self.power_for(profile).cpu.is_none() {
+            explanation = explanation.note("no CPU power limits attached to this profile: the last limits sent stay in effect");
+        }
+        Ok(self
+            .power_requests(profile)
+            .iter()
+            .fold(explanation, |e, request| e.action(request.into())))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        self.inner.telemetry()
+    }
+
+    fn diagnose(&self) -> Vec<String> {
+        self.inner.diagnose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{FakeBios, Fixture, StubFan};
+
+    fn bios_in_mode(mode: GraphicsMode) -> FakeBios {
+        FakeBios::new()
+            .respond(BiosCommand::Legacy, command_type::GRAPHICS_MODE, &[mode as u8])
+            .respond(BiosCommand::GpuMode, command_type::GRAPHICS_MODE, &[])
+    }
+
+    #[test]
+    fn graphics_mode_switch_is_pending_until_reboot() {
+        let state = Fixture::new();
+        let bios = HpBios::new(bios_in_mode(GraphicsMode::Hybrid)).with_boot_state(state.root(), "boot-a");
+        let after = bios.set_graphics_mode(GraphicsMode::Discrete).unwrap();
+        assert_eq!(after.active, GraphicsMode::Hybrid);
+        assert_eq!(after.pending(), Some(GraphicsMode::Discrete));
+        assert_eq!(bios.transport().requests.borrow().last(), Some(&GraphicsMode::Discrete.set_request()));
+    }
+
+    #[test]
+    fn pending_graphics_mode_survives_a_new_process() {
+        let state = Fixture::new();
+        HpBios::new(bios_in_mode(GraphicsMode::Hybrid))
+            .with_boot_state(state.root(), "boot-a")
+            .graphics_mode()
+            .unwrap();
+
+        // A later run in the same boot sees the BIOS already configured for Discrete.
+        let later = HpBios::new(bios_in_mode(GraphicsMode::Discrete)).with_boot_state(state.root(), "boot-a");
+        let mode = later.graphics_mode().unwrap();
+        assert_eq!(mode.active, GraphicsMode::Hybrid);
+        assert!(mode.reboot_required());
+
+        // After a reboot the configured mode is the running one.
+        let rebooted = HpBios::new(bios_in_mode(GraphicsMode::Discrete)).with_boot_state(state.root(), "boot-b");
+        assert!(!rebooted.graphics_mode().unwrap().reboot_required());
+    }
+
+    #[test]
+    fn setting_the_configured_mode_sends_nothing() {
+        let state = Fixture::new();
+        let bios = HpBios::new(bios_in_mode(GraphicsMode::Optimus)).with_boot_state(state.root(), "boot-a");
+        bios.set_graphics_mode(GraphicsMode::Optimus).unwrap();
+        assert_eq!(bios.transport().requests.borrow().len(), 1);
+    }
+
+    fn limits(pl1: Option<u8>, pl2: Option<u8>, pl4: Option<u8>) -> CpuPowerLimits {
+        CpuPowerLimits {
+            pl1_w: pl1,
+            pl2_w: pl2,
+            pl4_w: pl4,
+        }
+    }
+
+    #[test]
+    fn cpu_limits_round_trip_with_unchanged_bytes() {
+        let set = limits(Some(45), None, Some(120));
+        assert_eq!(set.encode(), [45, POWER_LIMIT_UNCHANGED, 120, POWER_LIMIT_UNCHANGED]);
+        assert_eq!(CpuPowerLimits::decode(&set.encode()).unwrap(), set);
+        assert_eq!(CpuPowerLimits::default().encode(), [POWER_LIMIT_UNCHANGED; 4]);
+    }
+
+    #[test]
+    fn cpu_limits_are_clamped_and_ordered() {
+        let range = CpuPowerRange::default();
+        assert_eq!(limits(Some(5), Some(250), None).clamped(range), limits(Some(15), Some(140), None));
+        // PL2 and PL4 are raised to the limit below them.
+        assert_eq!(limits(Some(90), Some(60), Some(30)).clamped(range), limits(Some(90), Some(90), Some(90)));
+        assert_eq!(limits(Some(50), None, Some(40)).clamped(range), limits(Some(50), None, Some(50)));
+        assert_eq!(
+            limits(Some(200), None, None).set_request(range).data,
+            vec![140, POWER_LIMIT_UNCHANGED, POWER_LIMIT_UNCHANGED, POWER_LIMIT_UNCHANGED]
+        );
+    }
+
+    #[test]
+    fn cpu_range_never_reaches_the_unchanged_byte() {
+        let range = CpuPowerRange::new(200, 255);
+        assert_eq!(range.max_w, POWER_LIMIT_UNCHANGED - 1);
+        assert_eq!(limits(Some(255), None, None).clamped(range).encode()[0], POWER_LIMIT_UNCHANGED - 1);
+    }
+
+    #[test]
+    fn short_payloads_are_rejected() {
+        assert!(matches!(CpuPowerLimits::decode(&[45, 90, 120]), Err(DriverError::Internal(_))));
+        assert!(matches!(GpuPowerData::decode(&[1, 1]), Err(DriverError::Internal(_))));
+        assert!(matches!(GraphicsMode::decode(&[]), Err(DriverError::Internal(_))));
+    }
+
+    #[test]
+    fn gpu_power_round_trips() {
+        let data = GpuPowerData::from_level(GpuPowerLevel::Maximum);
+        assert_eq!(data.encode(), [1, 1, 1, 0]);
+        assert_eq!(GpuPowerData::decode(&data.encode()).unwrap(), data);
+        assert!(matches!(GpuPowerData::decode(&[1, 0, 9, 0]), Err(DriverError::Internal(_))));
+    }
+
+    #[test]
+    fn power_driver_sends_profile_power_after_the_fan_profile() {
+        let bios = FakeBios::new()
+            .respond(BiosCommand::Default, command_type::GET_GPU_POWER, &[1, 0, 1, 0])
+            .respond(BiosCommand::Default, command_type::SET_GPU_POWER, &[])
+            .respond(BiosCommand::Default, command_type::SET_CPU_POWER_LIMITS, &[]);
+        let fan = StubFan::default();
+        let config = crate::power_config::PowerConfig::from_toml_str(
+            "[profiles.Quiet]\npl1_w = 25\npl2_w = 35\ngpu = \"minimum\"\n",
+        )
+        .unwrap();
+        let settings = config.resolve(&fan.capabilities().unwrap()).unwrap();
+        assert_eq!(settings.len(), 1);
+        let (quiet, power) = settings[0];
+        assert_eq!(quiet, 1);
+        let driver = HpPowerDriver::new(fan, HpBios::new(bios)).with_power(quiet, power.hp_settings());
+
+        driver.set_profile(quiet).unwrap();
+        assert_eq!(driver.inner().0.get(), Some(1));
+        let sent: Vec<BiosRequest> = driver.bios.transport().requests.borrow()[1..].to_vec();
+        assert_eq!(
+            sent,
+            vec![
+                limits(Some(25), Some(35), None).set_request(CpuPowerRange::default()),
+                GpuPowerData::from_level(GpuPowerLevel::Minimum).set_request(),
+            ]
+        );
+
+        // A profile without settings puts back the GPU power read at construction.
+        assert_eq!(driver.power_requests(0), vec![GpuPowerData::decode(&[1, 0, 1, 0]).unwrap().set_request()]);
+        assert_eq!(driver.explain_profile(0).unwrap().actions.len(), 1);
+    }
+}
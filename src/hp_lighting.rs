@@ -0,0 +1,424 @@
+//! HP four-zone keyboard lighting over the `hpqBIntM` BIOS interface: keyboard type detection,
+//! backlight on/off and per-zone RGB colors, plus tinting the keyboard to show the active profile.
+
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::hp_bios::{self, command_type, BiosCommand, BiosRequest, BiosTransport, HpBios, OutputSize};
+use crate::model::{FanCapabilities, FanTelemetry, ProfileId};
+
+/// Size of the color table exchanged with the BIOS.
+pub const COLOR_TABLE_SIZE: usize = 128;
+/// Offset of zone 0's red byte in the color table; zones follow as RGB triplets.
+const COLOR_TABLE_ZONES_OFFSET: usize = 25;
+const ZONE_COUNT: usize = 4;
+
+/// Backlight bytes understood by the BIOS.
+const BACKLIGHT_ON: u8 = 0xE4;
+const BACKLIGHT_OFF: u8 = 0x64;
+
+#[derive(Debug, Error)]
+#[error("invalid color {0:?}: expected #RRGGBB, R,G,B or a color name")]
+pub struct ParseColorError(String);
+
+impl From<ParseColorError> for DriverError {
+    fn from(e: ParseColorError) -> Self {
+        DriverError::Unsupported(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const OFF: Rgb = Rgb::new(0, 0, 0);
+    pub const WHITE: Rgb = Rgb::new(255, 255, 255);
+    pub const RED: Rgb = Rgb::new(255, 0, 0);
+    pub const ORANGE: Rgb = Rgb::new(255, 96, 0);
+    pub const YELLOW: Rgb = Rgb::new(255, 200, 0);
+    pub const GREEN: Rgb = Rgb::new(0, 255, 0);
+    pub const CYAN: Rgb = Rgb::new(0, 200, 255);
+    pub const BLUE: Rgb = Rgb::new(0, 0, 255);
+    pub const PURPLE: Rgb = Rgb::new(160, 0, 255);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    fn named(name: &str) -> Option<Self> {
+        Some(match name {
+            "off" | "black" => Self::OFF,
+            "white" => Self::WHITE,
+            "red" => Self::RED,
+            "orange" => Self::ORANGE,
+            "yellow" => Self::YELLOW,
+            "green" => Self::GREEN,
+            "cyan" => Self::CYAN,
+            "blue" => Self::BLUE,
+            "purple" => Self::PURPLE,
+            _ => return None,
+        })
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = ParseColorError;
+
+    /// Accepts `#RRGGBB`, `RRGGBB`, `R,G,B` (decimal) or a color name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim().to_ascii_lowercase();
+        let error = || ParseColorError(s.to_string());
+
+        if let Some(color) = Self::named(&text) {
+            return Ok(color);
+        }
+
+        if text.contains(',') {
+            let parts: Vec<u8> = text
+                .split(',')
+                .map(|part| part.trim().parse::<u8>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| error())?;
+            return match parts[..] {
+                [r, g, b] => Ok(Self::new(r, g, b)),
+                _ => Err(error()),
+            };
+        }
+
+        let hex = text.strip_prefix('#').unwrap_or(&text);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| error());
+        Ok(Self::new(byte(0)?, byte(2)?, byte(4)?))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+/// Lighting zones in color-table order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardZone {
+    /// Arrow keys and numpad.
+    Right = 0,
+    Middle = 1,
+    Left = 2,
+    Wasd = 3,
+}
+
+impl KeyboardZone {
+    pub const ALL: [KeyboardZone; ZONE_COUNT] = [Self::Right, Self::Middle, Self::Left, Self::Wasd];
+}
+
+/// One color per keyboard zone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RgbZones(pub [Rgb; ZONE_COUNT]);
+
+impl RgbZones {
+    pub fn uniform(color: Rgb) -> Self {
+        Self([color; ZONE_COUNT])
+    }
+
+    pub fn zone(&self, zone: KeyboardZone) -> Rgb {
+        self.0[zone as usize]
+    }
+
+    pub fn with_zone(mut self, zone: KeyboardZone, color: Rgb) -> Self {
+        self.0[zone as usize] = color;
+        self
+    }
+
+    /// Encode as a full color table: zone count, padding, then RGB per zone.
+    pub fn encode(&self) -> [u8; COLOR_TABLE_SIZE] {
+        let mut table = [0u8; COLOR_TABLE_SIZE];
+        table[0] = ZONE_COUNT as u8;
+        for (index, color) in self.0.iter().enumerate() {
+            let offset = COLOR_TABLE_ZONES_OFFSET + index * 3;
+            table[offset..offset + 3].copy_from_slice(&[color.r, color.g, color.b]);
+        }
+        table
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DriverError> {
+        hp_bios::expect_len(data, COLOR_TABLE_ZONES_OFFSET + ZONE_COUNT * 3, "keyboard color table")?;
+        let mut zones = [Rgb::OFF; ZONE_COUNT];
+        for (index, zone) in zones.iter_mut().enumerate() {
+            let offset = COLOR_TABLE_ZONES_OFFSET + index * 3;
+            *zone = Rgb::new(data[offset], data[offset + 1], data[offset + 2]);
+        }
+        Ok(Self(zones))
+    }
+}
+
+impl FromStr for RgbZones {
+    type Err = ParseColorError;
+
+    /// One color for the whole keyboard, or four whitespace-separated colors in zone order
+    /// (right, middle, left, WASD).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colors: Vec<Rgb> = s.split_whitespace().map(str::parse).collect::<Result<_, _>>()?;
+        match colors[..] {
+            [color] => Ok(Self::uniform(color)),
+            [right, middle, left, wasd] => Ok(Self([right, middle, left, wasd])),
+            _ => Err(ParseColorError(s.to_string())),
+        }
+    }
+}
+
+/// Physical keyboard layout reported by the BIOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardType {
+    Standard,
+    WithNumpad,
+    Tenkeyless,
+    PerKeyRgb,
+    Unknown(u8),
+}
+
+impl KeyboardType {
+    fn from_byte(value: u8) -> Self {
+        match value {
+            0 => Self::Standard,
+            1 => Self::WithNumpad,
+            2 => Self::Tenkeyless,
+            3 => Self::PerKeyRgb,
+            other => Self::Unknown(other),
+        }
+    }
+
+    /// Whether the four-zone color table applies (per-key boards use a different interface).
+    pub fn has_four_zones(self) -> bool {
+        matches!(self, Self::Standard | Self::WithNumpad | Self::Tenkeyless)
+    }
+}
+
+/// Keyboard lighting operations.
+pub struct HpLighting<T: BiosTransport> {
+    bios: HpBios<T>,
+}
+
+impl<T: BiosTransport> HpLighting<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            bios: HpBios::new(transport),
+        }
+    }
+
+    pub fn keyboard_type(&self) -> Result<KeyboardType, DriverError> {
+        let data = self.bios.call(
+            &BiosRequest::new(BiosCommand::Default, command_type::GET_KEYBOARD_TYPE).output(OutputSize::Small),
+        )?;
+        hp_bios::expect_len(&data, 1, "keyboard type")?;
+        Ok(KeyboardType::from_byte(data[0]))
+    }
+
+    pub fn backlight(&self) -> Result<bool, DriverError> {
+        let data = self.bios.call(
+            &BiosRequest::new(BiosCommand::Keyboard, command_type::GET_BACKLIGHT).output(OutputSize::Small),
+        )?;
+        hp_bios::expect_len(&data, 1, "backlight state")?;
+        Ok(data[0] == BACKLIGHT_ON)
+    }
+
+    pub fn set_backlight(&self, on: bool) -> Result<(), DriverError> {
+        self.bios.call(&Self::set_backlight_request(on)).map(drop)
+    }
+
+    pub fn set_backlight_request(on: bool) -> BiosRequest {
+        let value = if on { BACKLIGHT_ON } else { BACKLIGHT_OFF };
+        BiosRequest::new(BiosCommand::Keyboard, command_type::SET_BACKLIGHT).data([value])
+    }
+
+    pub fn colors(&self) -> Result<RgbZones, DriverError> {
+        let data = self.bios.call(
+            &BiosRequest::new(BiosCommand::Keyboard, command_type::GET_COLOR_TABLE).output(OutputSize::Medium),
+        )?;
+        RgbZones::decode(&data)
+    }
+
+    pub fn set_colors(&self, zones: RgbZones) -> Result<(), DriverError> {
+        self.bios.call(&Self::set_colors_request(zones)).map(drop)
+    }
+
+    pub fn set_colors_request(zones: RgbZones) -> BiosRequest {
+        BiosRequest::new(BiosCommand::Keyboard, command_type::SET_COLOR_TABLE).data(zones.encode())
+    }
+
+    /// Tint the whole keyboard and make sure the backlight is on.
+    pub fn show_color(&self, color: Rgb) -> Result<(), DriverError> {
+        Self::show_color_requests(color)
+            .iter()
+            .try_for_each(|request| self.bios.call(request).map(drop))
+    }
+
+    /// Requests `show_color(color)` sends, in order.
+    pub fn show_color_requests(color: Rgb) -> [BiosRequest; 2] {
+        [
+            Self::set_colors_request(RgbZones::uniform(color)),
+            Self::set_backlight_request(true),
+        ]
+    }
+}
+
+/// Wraps a driver and tints the keyboard with the color attached to each profile after it is
+/// applied. Lighting failures are ignored: the profile change is what matters.
+pub struct ProfileColorDriver<D, T: BiosTransport> {
+    inner: D,
+    lighting: HpLighting<T>,
+    colors: Vec<(ProfileId, Rgb)>,
+}
+
+impl<D: FanDriver, T: BiosTransport> ProfileColorDriver<D, T> {
+    pub fn new(inner: D, lighting: HpLighting<T>, colors: Vec<(ProfileId, Rgb)>) -> Self {
+        Self { inner, lighting, colors }
+    }
+
+    pub fn color_for(&self, profile: ProfileId) -> Option<Rgb> {
+        self.colors.iter().find(|(id, _)| *id == profile).map(|(_, color)| *color)
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+}
+
+impl<D: FanDriver, T: BiosTransport> FanDriver for ProfileColorDriver<D, T> {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        self.inner.capabilities()
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        self.inner.current_profile()
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        self.inner.set_profile(profile)?;
+        if let Some(color) = self.color_for(profile) {
+            let _ = self.lighting.show_color(color);
+        }
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let explanation = self.inner.explain_profile(profile)?;
+        let Some(color) = self.color_for(profile) else {
+            return Ok(explanation);
+        };
+        Ok(HpLighting::<T>::show_color_requests(color)
+            .iter()
+            .fold(explanation.note(format!("keyboard tint {}; lighting failures are ignored", color)), |e, request| {
+                e.action(request.into())
+            }))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        self.inner.telemetry()
+    }
+
+    fn diagnose(&self) -> Vec<String> {
+        self.inner.diagnose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{FakeBios, StubFan};
+
+    #[test]
+    fn parses_hex_decimal_and_named_colors() {
+        assert_eq!("#FF6000".parse::<Rgb>().unwrap(), Rgb::ORANGE);
+        assert_eq!("00c8ff".parse::<Rgb>().unwrap(), Rgb::CYAN);
+        assert_eq!(" 160, 0 ,255 ".parse::<Rgb>().unwrap(), Rgb::PURPLE);
+        assert_eq!("Red".parse::<Rgb>().unwrap(), Rgb::RED);
+        assert_eq!("black".parse::<Rgb>().unwrap(), Rgb::OFF);
+        assert_eq!(Rgb::new(1, 171, 255).to_string(), "#01ABFF");
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for bad in ["", "#FFF", "#GG0000", "#FF00001", "256,0,0", "1,2", "1,2,3,4", "magenta", "#ＦＦ0000"] {
+            assert!(bad.parse::<Rgb>().is_err(), "{:?} should not parse", bad);
+        }
+        assert!(matches!(DriverError::from("nope".parse::<Rgb>().unwrap_err()), DriverError::Unsupported(_)));
+    }
+
+    #[test]
+    fn parses_one_or_four_zone_colors() {
+        assert_eq!("blue".parse::<RgbZones>().unwrap(), RgbZones::uniform(Rgb::BLUE));
+        let zones: RgbZones = "red green #0000FF 1,2,3".parse().unwrap();
+        assert_eq!(zones.zone(KeyboardZone::Right), Rgb::RED);
+        assert_eq!(zones.zone(KeyboardZone::Wasd), Rgb::new(1, 2, 3));
+        assert!("red green".parse::<RgbZones>().is_err());
+        assert!("".parse::<RgbZones>().is_err());
+    }
+
+    #[test]
+    fn color_table_layout() {
+        let zones = RgbZones::uniform(Rgb::OFF)
+            .with_zone(KeyboardZone::Right, Rgb::new(1, 2, 3))
+            .with_zone(KeyboardZone::Wasd, Rgb::new(10, 20, 30));
+        let table = zones.encode();
+        assert_eq!(table[0], 4);
+        assert!(table[1..COLOR_TABLE_ZONES_OFFSET].iter().all(|b| *b == 0));
+        assert_eq!(table[25..28], [1, 2, 3]);
+        assert_eq!(table[34..37], [10, 20, 30]);
+        assert!(table[37..].iter().all(|b| *b == 0));
+
+        assert_eq!(RgbZones::decode(&table).unwrap(), zones);
+        assert!(matches!(RgbZones::decode(&table[..36]), Err(DriverError::Internal(_))));
+    }
+
+    #[test]
+    fn keyboard_type_and_backlight() {
+        let bios = FakeBios::new()
+            .respond(BiosCommand::Default, command_type::GET_KEYBOARD_TYPE, &[3])
+            .respond(BiosCommand::Keyboard, command_type::GET_BACKLIGHT, &[BACKLIGHT_ON]);
+        let lighting = HpLighting::new(bios);
+        assert_eq!(lighting.keyboard_type().unwrap(), KeyboardType::PerKeyRgb);
+        assert!(!KeyboardType::PerKeyRgb.has_four_zones());
+        assert!(KeyboardType::Tenkeyless.has_four_zones());
+        assert!(lighting.backlight().unwrap());
+    }
+
+    #[test]
+    fn profile_color_follows_the_profile_change() {
+        let bios = FakeBios::new()
+            .respond(BiosCommand::Keyboard, command_type::SET_COLOR_TABLE, &[])
+            .respond(BiosCommand::Keyboard, command_type::SET_BACKLIGHT, &[]);
+        let driver = ProfileColorDriver::new(StubFan::default(), HpLighting::new(bios), vec![(2, Rgb::RED)]);
+
+        driver.set_profile(2).unwrap();
+        assert_eq!(driver.inner().0.get(), Some(2));
+        assert_eq!(
+            *driver.lighting.bios.transport().requests.borrow(),
+            HpLighting::<FakeBios>::show_color_requests(Rgb::RED).to_vec()
+        );
+        assert_eq!(driver.explain_profile(2).unwrap().actions.len(), 2);
+
+        // No color attached: nothing sent, nothing explained.
+        driver.set_profile(0).unwrap();
+        assert_eq!(driver.lighting.bios.transport().requests.borrow().len(), 2);
+        assert!(driver.explain_profile(0).unwrap().actions.is_empty());
+    }
+
+    #[test]
+    fn lighting_failures_do_not_fail_the_profile_change() {
+        // Every lighting call is refused with return code 4.
+        let driver = ProfileColorDriver::new(StubFan::default(), HpLighting::new(FakeBios::new()), vec![(1, Rgb::GREEN)]);
+        driver.set_profile(1).unwrap();
+        assert_eq!(driver.inner().0.get(), Some(1));
+    }
+}
@@ -0,0 +1,656 @@
+//! JSON-RPC client for the HP Omen HSA service, the mechanism NativeRpcClient.dll and
+//! `Hp.Bridge.Client` (`ExecuteCommandAsync`) use instead of exported C functions
+//! (see RESEARCH_NOTES.md).
+//!
+//! The client is transport-agnostic: anything `Read + Write` works (a named pipe opened as a file
+//! on Windows, a Unix socket for the stand-in server in the tests). Messages are JSON-RPC 2.0:
+//!
+//! ```text
+//! -> {"jsonrpc":"2.0","id":1,"method":"ExecuteCommand","params":{"command":"SetThermalPolicy","arguments":{"policy":4}}}
+//! <- {"jsonrpc":"2.0","id":1,"result":{}}
+//! <- {"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"unknown command"}}
+//! ```
+//!
+//! Method and command names are not yet confirmed against a capture of the real service; they
+//! live in `RpcDialect` so they can be corrected without touching the client.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::{Action, Explanation};
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+
+/// Frames larger than this are treated as a corrupt stream rather than allocated.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// JSON-RPC "method not found".
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// How long `RpcClient::call` waits for the response to a request.
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How messages are delimited on the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// 4-byte little-endian length, then that many bytes of UTF-8 JSON (pipe message mode).
+    #[default]
+    LengthPrefixed,
+    /// One JSON document per line.
+    Newline,
+}
+
+impl Framing {
+    pub fn write_frame<W: Write>(self, writer: &mut W, payload: &[u8]) -> Result<(), DriverError> {
+        let io = |e: std::io::Error| DriverError::Io(format!("rpc write: {}", e));
+        match self {
+            Self::LengthPrefixed => {
+                let len = u32::try_from(payload.len())
+                    .ok()
+                    .filter(|len| *len as usize <= MAX_FRAME_LEN)
+                    .ok_or_else(|| DriverError::Internal(format!("rpc frame too large: {} bytes", payload.len())))?;
+                writer.write_all(&len.to_le_bytes()).map_err(io)?;
+                writer.write_all(payload).map_err(io)?;
+            }
+            Self::Newline => {
+                writer.write_all(payload).map_err(io)?;
+                writer.write_all(b"\n").map_err(io)?;
+            }
+        }
+        writer.flush().map_err(io)
+    }
+
+    /// Read one frame. End of stream before a complete frame is an `Io` error.
+    pub fn read_frame<R: BufRead>(self, reader: &mut R) -> Result<Vec<u8>, DriverError> {
+        let io = |e: std::io::Error| DriverError::Io(format!("rpc read: {}", e));
+        match self {
+            Self::LengthPrefixed => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len).map_err(io)?;
+                let len = u32::from_le_bytes(len) as usize;
+                if len > MAX_FRAME_LEN {
+                    return Err(DriverError::Internal(format!("rpc frame too large: {} bytes", len)));
+                }
+                let mut payload = vec![0u8; len];
+                reader.read_exact(&mut payload).map_err(io)?;
+                Ok(payload)
+            }
+            Self::Newline => {
+                let mut line = Vec::new();
+                reader.read_until(b'\n', &mut line).map_err(io)?;
+                if line.last() != Some(&b'\n') {
+                    return Err(DriverError::Io("rpc read: connection closed".into()));
+                }
+                line.pop();
+                Ok(line)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    pub id: u64,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl From<RpcError> for DriverError {
+    fn from(e: RpcError) -> Self {
+        match e.code {
+            METHOD_NOT_FOUND => DriverError::Unsupported(e.message),
+            _ => DriverError::Internal(format!("rpc error {}: {}", e.code, e.message)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    /// `None` (JSON `null`) when the server could not tell which request it is answering.
+    pub id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+/// JSON-RPC 2.0 client over a byte stream.
+pub struct RpcClient<S: Read + Write> {
+    stream: Arc<Mutex<BufReader<S>>>,
+    framing: Framing,
+    next_id: AtomicU64,
+    timeout: Duration,
+}
+
+impl<S: Read + Write + Send + 'static> RpcClient<S> {
+    pub fn new(stream: S, framing: Framing) -> Self {
+        Self {
+            stream: Arc::new(Mutex::new(BufReader::new(stream))),
+            framing,
+            next_id: AtomicU64::new(1),
+            timeout: DEFAULT_CALL_TIMEOUT,
+        }
+    }
+
+    /// Override how long a call waits for its response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send a request and wait up to the timeout for the response carrying its id.
+    ///
+    /// The exchange runs on a worker thread because a pipe opened as a file has no read
+    /// timeout. A worker that times out keeps the stream until its read completes; later calls
+    /// queue behind it and skip its answer by id.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, DriverError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = RpcRequest {
+            jsonrpc: "2.0".into(),
+            id,
+            method: method.into(),
+            params,
+        };
+        let payload = serde_json::to_vec(&request).map_err(|e| DriverError::Internal(e.to_string()))?;
+
+        let (tx, rx) = mpsc::channel();
+        let stream = Arc::clone(&self.stream);
+        let framing = self.framing;
+        thread::spawn(move || {
+            let mut stream = stream.lock().unwrap();
+            let _ = tx.send(exchange(&mut stream, framing, id, &payload));
+        });
+        match rx.recv_timeout(self.timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(DriverError::Io(format!(
+                "no rpc response to {} within {} ms",
+                method,
+                self.timeout.as_millis()
+            ))),
+            Err(RecvTimeoutError::Disconnected) => Err(DriverError::Internal("rpc worker exited".into())),
+        }
+    }
+
+    pub fn execute(&self, command: &HpRpcCommand) -> Result<Value, DriverError> {
+        self.call(&command.method, command.params.clone())
+    }
+}
+
+/// Write `payload` and read frames until the response to `id`.
+fn exchange<S: Read + Write>(
+    stream: &mut BufReader<S>,
+    framing: Framing,
+    id: u64,
+    payload: &[u8],
+) -> Result<Value, DriverError> {
+    framing.write_frame(stream.get_mut(), payload)?;
+    loop {
+        let frame = framing.read_frame(stream)?;
+        let response: RpcResponse = serde_json::from_slice(&frame).map_err(|e| {
+            DriverError::Internal(format!("malformed rpc response {:?}: {}", String::from_utf8_lossy(&frame), e))
+        })?;
+        match (response.id, response.result, response.error) {
+            // Request ids start at 1: an error without one means the server couldn't parse
+            // a request, and no answer carrying our id is coming.
+            (None | Some(0), _, Some(error)) => return Err(error.into()),
+            (Some(response_id), ..) if response_id != id => {
+                // Answer to a request abandoned earlier; skip it.
+                continue;
+            }
+            (_, _, Some(error)) => return Err(error.into()),
+            (_, result, None) => return Ok(result.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+/// Method and command names spoken by the service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcDialect {
+    /// JSON-RPC method wrapping every command (`ExecuteCommandAsync` on the .NET side).
+    pub execute_method: String,
+    pub set_thermal_policy: String,
+    pub get_thermal_policy: String,
+    pub set_max_fan: String,
+    pub get_fan_speed: String,
+}
+
+impl Default for RpcDialect {
+    fn default() -> Self {
+        Self {
+            execute_method: "ExecuteCommand".into(),
+            set_thermal_policy: "SetThermalPolicy".into(),
+            get_thermal_policy: "GetThermalPolicy".into(),
+            set_max_fan: "SetMaxFan".into(),
+            get_fan_speed: "GetFanSpeed".into(),
+        }
+    }
+}
+
+/// A ready-to-send command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HpRpcCommand {
+    pub method: String,
+    pub params: Value,
+}
+
+impl RpcDialect {
+    fn command(&self, name: &str, arguments: Value) -> HpRpcCommand {
+        HpRpcCommand {
+            method: self.execute_method.clone(),
+            params: json!({ "command": name, "arguments": arguments }),
+        }
+    }
+
+    /// Switch the thermal policy (`HpThermalProfile` numbering).
+    pub fn set_thermal_profile(&self, profile: ProfileId) -> HpRpcCommand {
+        self.command(&self.set_thermal_policy, json!({ "policy": profile }))
+    }
+
+    /// Result: `{"policy": <id>}`.
+    pub fn get_thermal_profile(&self) -> HpRpcCommand {
+        self.command(&self.get_thermal_policy, json!({}))
+    }
+
+    pub fn set_max_fan(&self, enabled: bool) -> HpRpcCommand {
+        self.command(&self.set_max_fan, json!({ "enabled": enabled }))
+    }
+
+    /// Result: `{"rpm": [<fan 0>, <fan 1>, ...]}`.
+    pub fn get_fan_speed(&self) -> HpRpcCommand {
+        self.command(&self.get_fan_speed, json!({}))
+    }
+}
+
+#[derive(Deserialize)]
+struct PolicyResult {
+    policy: Option<ProfileId>,
+}
+
+#[derive(Deserialize)]
+struct FanSpeedResult {
+    #[serde(default)]
+    rpm: Vec<u32>,
+}
+
+/// Thermal control through the HSA service.
+pub struct HpRpcDriver<S: Read + Write> {
+    client: RpcClient<S>,
+    dialect: RpcDialect,
+    profiles: Vec<FanProfile>,
+}
+
+impl<S: Read + Write + Send + 'static> HpRpcDriver<S> {
+    /// `profiles` are the thermal policies to offer, numbered as the service expects.
+    pub fn new(client: RpcClient<S>, profiles: Vec<FanProfile>) -> Self {
+        Self {
+            client,
+            dialect: RpcDialect::default(),
+            profiles,
+        }
+    }
+
+    pub fn with_dialect(mut self, dialect: RpcDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn client(&self) -> &RpcClient<S> {
+        &self.client
+    }
+}
+
+impl<S: Read + Write + Send + 'static> FanDriver for HpRpcDriver<S> {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        Ok(FanCapabilities {
+            supported: true,
+            profiles: self.profiles.clone(),
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        let result = self.client.execute(&self.dialect.get_thermal_profile())?;
+        let policy: PolicyResult =
+            serde_json::from_value(result).map_err(|e| DriverError::Internal(format!("unexpected rpc result: {}", e)))?;
+        Ok(policy.policy)
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        if !self.profiles.iter().any(|p| p.id == profile) {
+            return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile)));
+        }
+        self.client.execute(&self.dialect.set_thermal_profile(profile)).map(drop)
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        if !self.profiles.iter().any(|p| p.id == profile) {
+            return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile)));
+        }
+        let command = self.dialect.set_thermal_profile(profile);
+        Ok(Explanation::new(profile)
+            .action(Action::RpcCall {
+                endpoint: "HP HSA service".into(),
+                method: command.method,
+                params: command.params,
+            })
+            .verify("service answers without a JSON-RPC error"))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        let result = self.client.execute(&self.dialect.get_fan_speed())?;
+        let speed: FanSpeedResult =
+            serde_json::from_value(result).map_err(|e| DriverError::Internal(format!("unexpected rpc result: {}", e)))?;
+        Ok(FanTelemetry {
+            rpm: speed.rpm.into_iter().max(),
+            temperature_c: None,
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+/// Connect to the service's named pipe (opened as a plain file handle).
+#[cfg(windows)]
+pub fn connect_pipe(path: &str) -> Result<RpcClient<std::fs::File>, DriverError> {
+    let pipe = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| DriverError::NotReady(format!("cannot open {}: {}", path, e)))?;
+    Ok(RpcClient::new(pipe, Framing::LengthPrefixed))
+}
+
+/// Unix-socket stand-in for the HSA service, speaking the same framing and `RpcDialect`,
+/// so the client and driver can be exercised without HP software.
+#[cfg(all(test, unix))]
+mod standin {
+    use std::io::BufReader;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use serde_json::{json, Value};
+
+    use super::{Framing, RpcDialect, RpcError, RpcRequest, RpcResponse, METHOD_NOT_FOUND};
+
+    /// Simulated board state.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct StandInState {
+        pub policy: u32,
+        pub max_fan: bool,
+        pub fan_rpm: Vec<u32>,
+    }
+
+    impl Default for StandInState {
+        fn default() -> Self {
+            Self {
+                policy: 0,
+                max_fan: false,
+                fan_rpm: vec![2400, 2200],
+            }
+        }
+    }
+
+    pub struct StandInServer {
+        path: PathBuf,
+        state: Arc<Mutex<StandInState>>,
+    }
+
+    impl StandInServer {
+        /// Bind `path` and serve connections on a background thread.
+        pub fn spawn(path: &Path, framing: Framing) -> std::io::Result<Self> {
+            let _ = std::fs::remove_file(path);
+            let listener = UnixListener::bind(path)?;
+            let state = Arc::new(Mutex::new(StandInState::default()));
+            let shared = Arc::clone(&state);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { break };
+                    let state = Arc::clone(&shared);
+                    thread::spawn(move || serve(stream, framing, &state));
+                }
+            });
+            Ok(Self {
+                path: path.to_path_buf(),
+                state,
+            })
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        pub fn state(&self) -> StandInState {
+            self.state.lock().unwrap().clone()
+        }
+    }
+
+    impl Drop for StandInServer {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn serve(stream: UnixStream, framing: Framing, state: &Mutex<StandInState>) {
+        let Ok(mut writer) = stream.try_clone() else { return };
+        let mut reader = BufReader::new(stream);
+        while let Ok(frame) = framing.read_frame(&mut reader) {
+            let response = match serde_json::from_slice::<RpcRequest>(&frame) {
+                Ok(request) => {
+                    let outcome = handle(&request, state);
+                    RpcResponse {
+                        jsonrpc: "2.0".into(),
+                        id: Some(request.id),
+                        result: outcome.as_ref().ok().cloned(),
+                        error: outcome.err(),
+                    }
+                }
+                Err(e) => RpcResponse {
+                    jsonrpc: "2.0".into(),
+                    id: None,
+                    result: None,
+                    error: Some(RpcError {
+                        code: -32700,
+                        message: e.to_string(),
+                    }),
+                },
+            };
+            let Ok(payload) = serde_json::to_vec(&response) else { break };
+            if framing.write_frame(&mut writer, &payload).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn handle(request: &RpcRequest, state: &Mutex<StandInState>) -> Result<Value, RpcError> {
+        let dialect = RpcDialect::default();
+        let not_found = |what: &str| RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("unknown {}", what),
+        };
+        if request.method != dialect.execute_method {
+            return Err(not_found(&request.method));
+        }
+        let command = request.params["command"].as_str().unwrap_or_default();
+        let arguments = &request.params["arguments"];
+        let mut state = state.lock().unwrap();
+
+        if command == dialect.set_thermal_policy {
+            let policy = arguments["policy"].as_u64().ok_or_else(|| RpcError {
+                code: -32602,
+                message: "missing policy".into(),
+            })?;
+            state.policy = policy as u32;
+            Ok(json!({}))
+        } else if command == dialect.get_thermal_policy {
+            Ok(json!({ "policy": state.policy }))
+        } else if command == dialect.set_max_fan {
+            state.max_fan = arguments["enabled"].as_bool().unwrap_or(false);
+            Ok(json!({}))
+        } else if command == dialect.get_fan_speed {
+            let rpm: Vec<u32> = if state.max_fan {
+                state.fan_rpm.iter().map(|_| 5800).collect()
+            } else {
+                state.fan_rpm.clone()
+            };
+            Ok(json!({ "rpm": rpm }))
+        } else {
+            Err(not_found(command))
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::standin::StandInServer;
+    use super::*;
+    use crate::test_support::Fixture;
+    use std::io::Cursor;
+    use std::os::unix::net::UnixStream;
+    use std::time::Instant;
+
+    fn round_trip(framing: Framing, payloads: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut wire = Vec::new();
+        for payload in payloads {
+            framing.write_frame(&mut wire, payload).unwrap();
+        }
+        let mut reader = Cursor::new(wire);
+        payloads.iter().map(|_| framing.read_frame(&mut reader).unwrap()).collect()
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let payloads: [&[u8]; 3] = [b"{\"id\":1}", b"", b"{\"text\":\"caf\xc3\xa9\"}"];
+        assert_eq!(round_trip(Framing::LengthPrefixed, &payloads), payloads);
+        assert_eq!(round_trip(Framing::Newline, &payloads[..1]), payloads[..1]);
+
+        let mut wire = Vec::new();
+        Framing::LengthPrefixed.write_frame(&mut wire, b"abc").unwrap();
+        assert_eq!(wire, [3, 0, 0, 0, b'a', b'b', b'c']);
+    }
+
+    #[test]
+    fn truncated_and_oversized_frames_are_errors() {
+        let truncated = [5u8, 0, 0, 0, b'a'];
+        assert!(matches!(
+            Framing::LengthPrefixed.read_frame(&mut Cursor::new(&truncated[..])),
+            Err(DriverError::Io(_))
+        ));
+        let oversized = ((MAX_FRAME_LEN + 1) as u32).to_le_bytes();
+        assert!(matches!(
+            Framing::LengthPrefixed.read_frame(&mut Cursor::new(&oversized[..])),
+            Err(DriverError::Internal(_))
+        ));
+        assert!(matches!(
+            Framing::Newline.read_frame(&mut Cursor::new(&b"{\"id\":1}"[..])),
+            Err(DriverError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn command_builders_wrap_commands_in_the_execute_method() {
+        let dialect = RpcDialect::default();
+        let set = dialect.set_thermal_profile(4);
+        assert_eq!(set.method, "ExecuteCommand");
+        assert_eq!(set.params, json!({ "command": "SetThermalPolicy", "arguments": { "policy": 4 } }));
+        assert_eq!(
+            dialect.set_max_fan(true).params,
+            json!({ "command": "SetMaxFan", "arguments": { "enabled": true } })
+        );
+        assert_eq!(dialect.get_thermal_profile().params["command"], "GetThermalPolicy");
+        assert_eq!(dialect.get_fan_speed().params["command"], "GetFanSpeed");
+
+        let renamed = RpcDialect {
+            execute_method: "Execute".into(),
+            ..RpcDialect::default()
+        };
+        assert_eq!(renamed.get_fan_speed().method, "Execute");
+    }
+
+    fn profiles() -> Vec<FanProfile> {
+        [(0, "Default"), (4, "Extreme (Max)")]
+            .into_iter()
+            .map(|(id, name)| FanProfile {
+                id,
+                name: name.into(),
+                is_maximum: id == 4,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn driver_talks_to_the_stand_in() {
+        for framing in [Framing::LengthPrefixed, Framing::Newline] {
+            let fixture = Fixture::new();
+            let server = StandInServer::spawn(&fixture.path("hsa.sock"), framing).unwrap();
+            let client = RpcClient::new(UnixStream::connect(server.path()).unwrap(), framing);
+            let driver = HpRpcDriver::new(client, profiles());
+
+            driver.set_profile(4).unwrap();
+            assert_eq!(server.state().policy, 4);
+            assert_eq!(driver.current_profile().unwrap(), Some(4));
+            assert_eq!(driver.telemetry().unwrap().rpm, Some(2400));
+            assert!(matches!(driver.set_profile(2), Err(DriverError::Unsupported(_))));
+
+            driver.client().execute(&RpcDialect::default().set_max_fan(true)).unwrap();
+            assert!(server.state().max_fan);
+            assert_eq!(driver.telemetry().unwrap().rpm, Some(5800));
+
+            let unknown = driver.client().call("Shutdown", json!({}));
+            assert!(matches!(unknown, Err(DriverError::Unsupported(_))));
+        }
+    }
+
+    #[test]
+    fn call_gives_up_after_the_timeout() {
+        let (ours, _silent) = UnixStream::pair().unwrap();
+        let client = RpcClient::new(ours, Framing::Newline).with_timeout(Duration::from_millis(200));
+        let started = Instant::now();
+        assert!(matches!(client.call("Ping", json!({})), Err(DriverError::Io(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn errors_without_a_request_id_are_fatal() {
+        for id in ["null", "0"] {
+            let (ours, mut theirs) = UnixStream::pair().unwrap();
+            let error = format!(
+                "{{\"jsonrpc\":\"2.0\",\"id\":{},\"error\":{{\"code\":-32700,\"message\":\"parse error\"}}}}\n",
+                id
+            );
+            theirs.write_all(error.as_bytes()).unwrap();
+            let client = RpcClient::new(ours, Framing::Newline).with_timeout(Duration::from_secs(5));
+            match client.call("Ping", json!({})) {
+                Err(DriverError::Internal(message)) => assert!(message.contains("parse error")),
+                other => panic!("expected Internal, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn stale_responses_are_skipped() {
+        let (ours, mut theirs) = UnixStream::pair().unwrap();
+        theirs
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{\"stale\":true}}\n{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"ok\":true}}\n")
+            .unwrap();
+        let client = RpcClient::new(ours, Framing::Newline);
+        assert_eq!(client.call("Ping", json!({})).unwrap(), json!({ "ok": true }));
+    }
+}
@@ -0,0 +1,224 @@
+//! PowerShell rendering of HP BIOS requests. Scripts are generated from the typed `hp_bios`
+//! model, so any command can be sent without shipping a `.ps1` file next to the binary, and are
+//! piped to `powershell -Command -` on stdin.
+//!
+//! Every statement is rendered on a single line: PowerShell reading a script from stdin runs it
+//! line by line, and a block spanning lines would need a trailing blank line to execute.
+//!
+//! For each request the script prints one parseable line:
+//!
+//! ```text
+//! BIOS[0] rwReturnCode=0 Data=2a260000
+//! ```
+
+use crate::command::CommandSpec;
+use crate::driver::DriverError;
+use crate::hp_bios::{BiosRequest, BiosResponse, SIGN};
+
+fn byte_list(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("0x{:02X}", b)).collect::<Vec<_>>().join(",")
+}
+
+/// Statement sending `request` and printing its result tagged with `index`.
+fn render_request(index: usize, request: &BiosRequest) -> String {
+    let data = if request.data.is_empty() {
+        String::new()
+    } else {
+        format!(" hpqBData = [Byte[]] @({});", byte_list(&request.data))
+    };
+    format!(
+        "$In = New-CimInstance -ClassName 'hpqBDataIn' -ClientOnly -Namespace 'root\\wmi' -Property @{{ \
+         Command = [UInt32] 0x{command:X}; CommandType = [UInt32] 0x{command_type:X}; Size = [UInt32] {size}; \
+         Sign = [Byte[]] @({sign});{data} }}; \
+         $Result = Invoke-CimMethod -InputObject $Methods -MethodName '{method}' -Arguments @{{ InData = [CimInstance] $In }}; \
+         $Hex = ($Result.OutData.Data | ForEach-Object {{ $_.ToString('x2') }}) -join ''; \
+         Write-Output ('BIOS[{index}] rwReturnCode=' + $Result.OutData.rwReturnCode + ' Data=' + $Hex)\n",
+        command = request.command as u32,
+        command_type = request.command_type,
+        size = request.data.len(),
+        sign = byte_list(&SIGN),
+        data = data,
+        method = request.output.method_name(),
+        index = index,
+    )
+}
+
+/// Full script sending `requests` in order over one CIM session.
+pub fn render(requests: &[BiosRequest]) -> String {
+    let mut script = String::from(
+        "$ErrorActionPreference = 'Stop'\n\
+         $Session = New-CimSession -Name 'hpq' -SkipTestConnection\n\
+         $Methods = Get-CimInstance -ClassName 'hpqBIntM' -CimSession $Session -Namespace 'root\\wmi'\n",
+    );
+    for (index, request) in requests.iter().enumerate() {
+        script.push_str(&render_request(index, request));
+    }
+    script.push_str("Remove-CimSession -CimSession $Session\n");
+    script
+}
+
+/// PowerShell invocation that reads `script` from stdin.
+pub fn command(script: String) -> CommandSpec {
+    CommandSpec::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-ExecutionPolicy", "Bypass", "-Command", "-"])
+        .stdin(script)
+}
+
+/// Parse the `BIOS[<i>]` lines printed by a script rendered for `count` requests.
+pub fn parse_output(stdout: &str, count: usize) -> Result<Vec<BiosResponse>, DriverError> {
+    let mut responses: Vec<Option<BiosResponse>> = vec![None; count];
+    for line in stdout.lines() {
+        let Some(rest) = line.trim().strip_prefix("BIOS[") else {
+            continue;
+        };
+        let malformed = || DriverError::Internal(format!("malformed BIOS result line: {:?}", line));
+        let (index, fields) = rest.split_once(']').ok_or_else(malformed)?;
+        let index: usize = index.parse().map_err(|_| malformed())?;
+
+        let mut return_code = None;
+        let mut data = Vec::new();
+        for field in fields.split_whitespace() {
+            if let Some(code) = field.strip_prefix("rwReturnCode=") {
+                return_code = Some(code.parse::<u32>().map_err(|_| malformed())?);
+            } else if let Some(hex) = field.strip_prefix("Data=") {
+                data = decode_hex(hex).ok_or_else(malformed)?;
+            }
+        }
+        let slot = responses.get_mut(index).ok_or_else(malformed)?;
+        *slot = Some(BiosResponse {
+            return_code: return_code.ok_or_else(malformed)?,
+            data,
+        });
+    }
+
+    responses
+        .into_iter()
+        .enumerate()
+        .map(|(index, response)| {
+            response.ok_or_else(|| {
+                DriverError::Internal(format!("no result for BIOS request {} in output: {:?}", index, stdout.trim()))
+            })
+        })
+        .collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    // Checked first: slicing at odd byte offsets of non-ASCII input would panic.
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hp_bios::{command_type, BiosCommand, GraphicsMode, OutputSize};
+
+    const PREAMBLE: &str = "$ErrorActionPreference = 'Stop'\n\
+        $Session = New-CimSession -Name 'hpq' -SkipTestConnection\n\
+        $Methods = Get-CimInstance -ClassName 'hpqBIntM' -CimSession $Session -Namespace 'root\\wmi'\n";
+
+    const SIGN_BYTES: &str = "0x53,0x45,0x43,0x55";
+
+    fn statement(index: usize, head: &str, method: &str) -> String {
+        format!(
+            "$In = New-CimInstance -ClassName 'hpqBDataIn' -ClientOnly -Namespace 'root\\wmi' -Property @{{ {head} }}; \
+             $Result = Invoke-CimMethod -InputObject $Methods -MethodName '{method}' -Arguments @{{ InData = [CimInstance] $In }}; \
+             $Hex = ($Result.OutData.Data | ForEach-Object {{ $_.ToString('x2') }}) -join ''; \
+             Write-Output ('BIOS[{index}] rwReturnCode=' + $Result.OutData.rwReturnCode + ' Data=' + $Hex)\n"
+        )
+    }
+
+    #[test]
+    fn renders_max_fan_request() {
+        let script = render(&[BiosRequest::set_max_fan(true)]);
+        let expected = format!(
+            "{}{}Remove-CimSession -CimSession $Session\n",
+            PREAMBLE,
+            statement(
+                0,
+                &format!(
+                    "Command = [UInt32] 0x20008; CommandType = [UInt32] 0x27; Size = [UInt32] 1; \
+                     Sign = [Byte[]] @({}); hpqBData = [Byte[]] @(0x01);",
+                    SIGN_BYTES
+                ),
+                "hpqBIOSInt0",
+            )
+        );
+        assert_eq!(script, expected);
+    }
+
+    #[test]
+    fn renders_batches_one_statement_per_line() {
+        let requests = [
+            BiosRequest::new(BiosCommand::Default, command_type::GET_FAN_COUNT).output(OutputSize::Small),
+            GraphicsMode::Discrete.set_request(),
+        ];
+        let expected = format!(
+            "{}{}{}Remove-CimSession -CimSession $Session\n",
+            PREAMBLE,
+            statement(
+                0,
+                &format!(
+                    "Command = [UInt32] 0x20008; CommandType = [UInt32] 0x10; Size = [UInt32] 0; Sign = [Byte[]] @({});",
+                    SIGN_BYTES
+                ),
+                "hpqBIOSInt4",
+            ),
+            statement(
+                1,
+                &format!(
+                    "Command = [UInt32] 0x2; CommandType = [UInt32] 0x52; Size = [UInt32] 1; \
+                     Sign = [Byte[]] @({}); hpqBData = [Byte[]] @(0x01);",
+                    SIGN_BYTES
+                ),
+                "hpqBIOSInt0",
+            )
+        );
+        let script = render(&requests);
+        assert_eq!(script, expected);
+        assert_eq!(script.lines().count(), 6);
+    }
+
+    #[test]
+    fn command_pipes_the_script_on_stdin() {
+        let spec = command("Write-Output 1\n".into());
+        assert_eq!(spec.program, "powershell");
+        assert_eq!(spec.args.last().map(String::as_str), Some("-"));
+        assert_eq!(spec.stdin.as_deref(), Some("Write-Output 1\n"));
+    }
+
+    #[test]
+    fn parses_results_in_index_order() {
+        let stdout = "noise\nBIOS[1] rwReturnCode=4 Data=\nBIOS[0] rwReturnCode=0 Data=2a260000\n";
+        let responses = parse_output(stdout, 2).unwrap();
+        assert_eq!(
+            responses,
+            vec![
+                BiosResponse {
+                    return_code: 0,
+                    data: vec![0x2a, 0x26, 0, 0]
+                },
+                BiosResponse {
+                    return_code: 4,
+                    data: Vec::new()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_malformed_results() {
+        assert!(parse_output("BIOS[0] rwReturnCode=0 Data=00\n", 2).is_err());
+        assert!(parse_output("BIOS[0] rwReturnCode=0 Data=0g\n", 1).is_err());
+        assert!(parse_output("BIOS[0] rwReturnCode=0 Data=abc\n", 1).is_err());
+        assert!(parse_output("BIOS[5] rwReturnCode=0 Data=\n", 1).is_err());
+        // Multi-byte characters must not reach the byte-offset slicing.
+        assert!(parse_output("BIOS[0] rwReturnCode=0 Data=é0\n", 1).is_err());
+        assert!(parse_output("BIOS[0] rwReturnCode=0 Data=0é0\n", 1).is_err());
+    }
+}
@@ -0,0 +1,416 @@
+//! HP board introspection over the `hpqBIntM` BIOS interface: the "system design data" block
+//! (thermal policy version, feature and power-cap flags), fan count/type and current fan levels.
+//! Byte and bit assignments follow the layout documented by the OMEN community tools (OmenMon).
+
+use crate::driver::DriverError;
+use crate::hp_bios::{self, command_type, BiosCommand, BiosRequest, BiosResponse, BiosTransport, HpBios, OutputSize};
+
+/// Thermal policy generation; decides which performance modes the board implements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermalPolicyVersion {
+    /// Older boards: Default, Performance and Cool only.
+    V0,
+    /// Adds Quiet (and Extreme where the feature flags allow it).
+    V1,
+    Unknown(u8),
+}
+
+impl ThermalPolicyVersion {
+    fn from_byte(value: u8) -> Self {
+        match value {
+            0 => Self::V0,
+            1 => Self::V1,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Feature bits (design data byte 3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureFlags(pub u8);
+
+impl FeatureFlags {
+    pub const SOFTWARE_FAN_CONTROL: u8 = 0x01;
+    pub const EXTREME_MODE_SUPPORTED: u8 = 0x02;
+    pub const EXTREME_MODE_UNLOCKED: u8 = 0x04;
+    pub const MANUAL_FAN_LEVELS: u8 = 0x08;
+
+    pub fn has(self, flag: u8) -> bool {
+        self.0 & flag == flag
+    }
+}
+
+/// Power-cap bits (design data bytes 0–1, little-endian).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerCapFlags(pub u16);
+
+impl PowerCapFlags {
+    pub const CPU_PL1_PL2: u16 = 0x0001;
+    pub const CPU_PL4: u16 = 0x0002;
+    pub const CONCURRENT_TDP: u16 = 0x0004;
+    pub const GPU_CUSTOM_TGP: u16 = 0x0008;
+    pub const GPU_PPAB: u16 = 0x0010;
+
+    pub fn has(self, flag: u16) -> bool {
+        self.0 & flag == flag
+    }
+}
+
+/// Decoded system design data (command type 0x28, 128-byte output).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemDesignData {
+    pub power_caps: PowerCapFlags,
+    pub thermal_policy: ThermalPolicyVersion,
+    pub features: FeatureFlags,
+    /// Factory PL4 in watts; 0 if not reported.
+    pub default_pl4_w: u8,
+    /// Non-zero if the BIOS allows CPU overclocking.
+    pub bios_overclocking: u8,
+    /// Graphics MUX switching is available.
+    pub graphics_switch: bool,
+    /// Factory combined CPU+GPU power in watts; 0 if not reported.
+    pub default_concurrent_tdp_w: u8,
+    /// Undecoded bytes, kept for diagnostics.
+    pub raw: Vec<u8>,
+}
+
+impl SystemDesignData {
+    const MIN_SIZE: usize = 8;
+
+    pub fn decode(data: &[u8]) -> Result<Self, DriverError> {
+        hp_bios::expect_len(data, Self::MIN_SIZE, "system design data")?;
+        Ok(Self {
+            power_caps: PowerCapFlags(u16::from_le_bytes([data[0], data[1]])),
+            thermal_policy: ThermalPolicyVersion::from_byte(data[2]),
+            features: FeatureFlags(data[3]),
+            default_pl4_w: data[4],
+            bios_overclocking: data[5],
+            graphics_switch: data[6] & 0x01 != 0,
+            default_concurrent_tdp_w: data[7],
+            raw: data.to_vec(),
+        })
+    }
+
+    pub fn request() -> BiosRequest {
+        BiosRequest::new(BiosCommand::Default, command_type::GET_SYSTEM_DESIGN_DATA).output(OutputSize::Medium)
+    }
+}
+
+/// Highest level accepted for manual fan control (6000 RPM).
+pub const MAX_FAN_LEVEL: u8 = 60;
+
+/// What a fan cools, as reported by the fan type query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanKind {
+    Cpu,
+    Gpu,
+    Other(u8),
+}
+
+impl FanKind {
+    fn from_byte(value: u8) -> Self {
+        match value {
+            1 => Self::Cpu,
+            2 => Self::Gpu,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// One fan and its current speed level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FanInfo {
+    pub index: usize,
+    pub kind: FanKind,
+    /// Speed level in units of 100 RPM, if the board reports it.
+    pub level: Option<u8>,
+}
+
+impl FanInfo {
+    pub fn rpm(&self) -> Option<u32> {
+        self.level.map(|level| u32::from(level) * 100)
+    }
+}
+
+/// Everything known about the board, for capabilities and `--diagnose` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HpBoardInfo {
+    pub design: SystemDesignData,
+    pub fans: Vec<FanInfo>,
+}
+
+impl HpBoardInfo {
+    /// Human-readable summary, one fact per line.
+    pub fn diagnose(&self) -> Vec<String> {
+        let design = &self.design;
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        let mut lines = vec![
+            format!("thermal policy: {:?}", design.thermal_policy),
+            format!(
+                "software fan control: {}",
+                yes_no(design.features.has(FeatureFlags::SOFTWARE_FAN_CONTROL))
+            ),
+            format!(
+                "extreme mode: {}",
+                match (
+                    design.features.has(FeatureFlags::EXTREME_MODE_SUPPORTED),
+                    design.features.has(FeatureFlags::EXTREME_MODE_UNLOCKED)
+                ) {
+                    (false, _) => "not supported",
+                    (true, false) => "supported, locked",
+                    (true, true) => "supported, unlocked",
+                }
+            ),
+            format!(
+                "manual fan levels: {}",
+                yes_no(design.features.has(FeatureFlags::MANUAL_FAN_LEVELS))
+            ),
+            format!(
+                "CPU PL1/PL2 control: {}",
+                yes_no(design.power_caps.has(PowerCapFlags::CPU_PL1_PL2))
+            ),
+            format!("CPU PL4 control: {}", yes_no(design.power_caps.has(PowerCapFlags::CPU_PL4))),
+            format!(
+                "GPU custom TGP: {}",
+                yes_no(design.power_caps.has(PowerCapFlags::GPU_CUSTOM_TGP))
+            ),
+            format!("GPU PPAB: {}", yes_no(design.power_caps.has(PowerCapFlags::GPU_PPAB))),
+            format!("graphics switch: {}", yes_no(design.graphics_switch)),
+        ];
+        if design.default_pl4_w > 0 {
+            lines.push(format!("default PL4: {} W", design.default_pl4_w));
+        }
+        if design.default_concurrent_tdp_w > 0 {
+            lines.push(format!("default concurrent TDP: {} W", design.default_concurrent_tdp_w));
+        }
+        for fan in &self.fans {
+            let speed = fan.rpm().map(|rpm| format!("{} RPM", rpm)).unwrap_or_else(|| "unknown".into());
+            lines.push(format!("fan {}: {:?}, {}", fan.index, fan.kind, speed));
+        }
+        lines
+    }
+}
+
+fn fan_count_request() -> BiosRequest {
+    BiosRequest::new(BiosCommand::Default, command_type::GET_FAN_COUNT).output(OutputSize::Small)
+}
+
+fn fan_levels_request() -> BiosRequest {
+    BiosRequest::new(BiosCommand::Default, command_type::GET_FAN_LEVEL).output(OutputSize::Medium)
+}
+
+fn temperature_request() -> BiosRequest {
+    BiosRequest::new(BiosCommand::Default, command_type::GET_TEMPERATURE).output(OutputSize::Small)
+}
+
+fn decode_fan_count(data: &[u8]) -> Result<usize, DriverError> {
+    hp_bios::expect_len(data, 1, "fan count")?;
+    Ok(usize::from(data[0]))
+}
+
+fn decode_fan_levels(data: &[u8], count: usize) -> Result<Vec<u8>, DriverError> {
+    hp_bios::expect_len(data, count, "fan levels")?;
+    Ok(data[..count].to_vec())
+}
+
+fn decode_temperature(data: &[u8]) -> Result<u8, DriverError> {
+    hp_bios::expect_len(data, 1, "temperature")?;
+    Ok(data[0])
+}
+
+/// Fan levels and temperature, each read independently so a board without fan level readout
+/// still reports its temperature.
+#[derive(Debug)]
+pub struct FanReadings {
+    /// Each fan's level in units of 100 RPM.
+    pub levels: Result<Vec<u8>, DriverError>,
+    pub temperature_c: Result<u8, DriverError>,
+}
+
+impl FanReadings {
+    /// Requests whose responses `decode` takes, in order, for transports that send several
+    /// requests in one round trip.
+    pub fn requests() -> [BiosRequest; 3] {
+        [fan_count_request(), fan_levels_request(), temperature_request()]
+    }
+
+    pub fn decode(responses: Vec<BiosResponse>) -> Self {
+        let mut data = responses.into_iter().map(BiosResponse::check);
+        let missing = || Err(DriverError::Internal("missing BIOS response".into()));
+        let count = data.next().unwrap_or_else(missing).and_then(|d| decode_fan_count(&d));
+        let levels = data.next().unwrap_or_else(missing);
+        let temperature = data.next().unwrap_or_else(missing);
+        Self {
+            levels: count.and_then(|count| decode_fan_levels(&levels?, count)),
+            temperature_c: temperature.and_then(|d| decode_temperature(&d)),
+        }
+    }
+
+    /// Speed of the fastest fan, which is what ramp verification cares about.
+    pub fn max_rpm(&self) -> Option<u32> {
+        let levels = self.levels.as_ref().ok()?;
+        levels.iter().max().map(|level| u32::from(*level) * 100)
+    }
+}
+
+impl<T: BiosTransport> HpBios<T> {
+    pub fn system_design_data(&self) -> Result<SystemDesignData, DriverError> {
+        SystemDesignData::decode(&self.call(&SystemDesignData::request())?)
+    }
+
+    pub fn fan_count(&self) -> Result<usize, DriverError> {
+        decode_fan_count(&self.call(&fan_count_request())?)
+    }
+
+    /// Current speed level of each fan, in units of 100 RPM.
+    pub fn fan_levels(&self) -> Result<Vec<u8>, DriverError> {
+        let count = self.fan_count()?;
+        decode_fan_levels(&self.call(&fan_levels_request())?, count)
+    }
+
+    /// Set each fan's speed level (units of 100 RPM, capped at `MAX_FAN_LEVEL`), in fan order.
+    /// Only boards that advertise manual fan levels in their design data accept this.
+    pub fn set_fan_levels(&self, levels: &[u8]) -> Result<(), DriverError> {
+        if !self.system_design_data()?.features.has(FeatureFlags::MANUAL_FAN_LEVELS) {
+            return Err(DriverError::Unsupported("board does not allow manual fan levels".into()));
+        }
+        let count = self.fan_count()?;
+        if levels.len() != count {
+            return Err(DriverError::Unsupported(format!(
+                "board has {} fans, got {} levels",
+                count,
+                levels.len()
+            )));
+        }
+        let data: Vec<u8> = levels.iter().map(|level| (*level).min(MAX_FAN_LEVEL)).collect();
+        self.call(&BiosRequest::new(BiosCommand::Default, command_type::SET_FAN_LEVEL).data(data))
+            .map(drop)
+    }
+
+    /// Board temperature sensor in °C.
+    pub fn temperature_c(&self) -> Result<u8, DriverError> {
+        decode_temperature(&self.call(&temperature_request())?)
+    }
+
+    /// Fans with their type and, where readable, current level.
+    pub fn fans(&self) -> Result<Vec<FanInfo>, DriverError> {
+        let count = self.fan_count()?;
+        let types = self.call(
+            &BiosRequest::new(BiosCommand::Default, command_type::GET_FAN_TYPE).output(OutputSize::Medium),
+        )?;
+        hp_bios::expect_len(&types, count, "fan types")?;
+        let levels = self.fan_levels().ok();
+        Ok((0..count)
+            .map(|index| FanInfo {
+                index,
+                kind: FanKind::from_byte(types[index]),
+                level: levels.as_ref().map(|levels| levels[index]),
+            })
+            .collect())
+    }
+
+    /// Design data plus fans. Fan queries are optional on older boards, so their failure
+    /// leaves the fan list empty instead of failing the whole query.
+    pub fn board_info(&self) -> Result<HpBoardInfo, DriverError> {
+        Ok(HpBoardInfo {
+            design: self.system_design_data()?,
+            fans: self.fans().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::FakeBios;
+
+    /// V1 policy, Extreme supported but locked, PL1/PL2 and custom TGP, 2 fans.
+    fn victus() -> FakeBios {
+        FakeBios::new()
+            .respond(
+                BiosCommand::Default,
+                command_type::GET_SYSTEM_DESIGN_DATA,
+                &[0x09, 0x00, 0x01, 0x03, 0x00, 0x00, 0x01, 0x00],
+            )
+            .respond(BiosCommand::Default, command_type::GET_FAN_COUNT, &[2])
+            .respond(BiosCommand::Default, command_type::GET_FAN_TYPE, &[1, 2])
+            .respond(BiosCommand::Default, command_type::GET_FAN_LEVEL, &[31, 28])
+    }
+
+    #[test]
+    fn decodes_design_data() {
+        let design = SystemDesignData::decode(&[0x09, 0x00, 0x01, 0x03, 95, 0, 0x01, 0]).unwrap();
+        assert_eq!(design.thermal_policy, ThermalPolicyVersion::V1);
+        assert!(design.power_caps.has(PowerCapFlags::CPU_PL1_PL2));
+        assert!(design.power_caps.has(PowerCapFlags::GPU_CUSTOM_TGP));
+        assert!(!design.power_caps.has(PowerCapFlags::CPU_PL4));
+        assert!(design.features.has(FeatureFlags::EXTREME_MODE_SUPPORTED));
+        assert!(!design.features.has(FeatureFlags::EXTREME_MODE_UNLOCKED));
+        assert_eq!(design.default_pl4_w, 95);
+        assert!(design.graphics_switch);
+    }
+
+    #[test]
+    fn short_design_data_is_an_error() {
+        assert!(matches!(SystemDesignData::decode(&[0; 7]), Err(DriverError::Internal(_))));
+    }
+
+    #[test]
+    fn board_info_lists_fans() {
+        let bios = HpBios::new(victus());
+        let board = bios.board_info().unwrap();
+        assert_eq!(
+            board.fans,
+            [
+                FanInfo { index: 0, kind: FanKind::Cpu, level: Some(31) },
+                FanInfo { index: 1, kind: FanKind::Gpu, level: Some(28) },
+            ]
+        );
+        let lines = board.diagnose();
+        assert!(lines.contains(&"extreme mode: supported, locked".to_string()));
+        assert!(lines.contains(&"fan 0: Cpu, 3100 RPM".to_string()));
+    }
+
+    #[test]
+    fn fan_queries_are_optional() {
+        let bios = HpBios::new(FakeBios::new().respond(
+            BiosCommand::Default,
+            command_type::GET_SYSTEM_DESIGN_DATA,
+            &[0; 8],
+        ));
+        let board = bios.board_info().unwrap();
+        assert_eq!(board.design.thermal_policy, ThermalPolicyVersion::V0);
+        assert!(board.fans.is_empty());
+    }
+
+    #[test]
+    fn manual_fan_levels_need_the_feature_flag() {
+        let bios = HpBios::new(victus());
+        assert!(matches!(bios.set_fan_levels(&[40, 40]), Err(DriverError::Unsupported(_))));
+    }
+
+    fn response(return_code: u32, data: &[u8]) -> BiosResponse {
+        BiosResponse {
+            return_code,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn fan_readings_fail_independently() {
+        let readings = FanReadings::decode(vec![response(0, &[2]), response(0, &[31, 28, 0, 0]), response(0, &[61])]);
+        assert_eq!(readings.levels.as_deref().unwrap(), [31, 28]);
+        assert_eq!(readings.max_rpm(), Some(3100));
+        assert_eq!(readings.temperature_c.unwrap(), 61);
+
+        let no_levels = FanReadings::decode(vec![response(0, &[2]), response(4, &[]), response(0, &[58])]);
+        assert!(matches!(no_levels.levels, Err(DriverError::Unsupported(_))));
+        assert_eq!(no_levels.max_rpm(), None);
+        assert_eq!(no_levels.temperature_c.unwrap(), 58);
+
+        let short = FanReadings::decode(vec![response(0, &[3]), response(0, &[31]), response(0, &[])]);
+        assert!(matches!(short.levels, Err(DriverError::Internal(_))));
+        assert!(matches!(short.temperature_c, Err(DriverError::Internal(_))));
+        assert!(FanReadings::decode(Vec::new()).temperature_c.is_err());
+    }
+}
@@ -1,56 +1,70 @@
 //! HP WMI BIOS-based thermal control for HP Victus and OMEN systems.
-//! Calls PowerShell script that uses the hpqBIntM WMI class.
+//! Sends `hpqBIntM` requests through PowerShell scripts rendered by `hp_script`.
 
-use std::process::Command;
+use crate::command::{CommandRunner, CommandSpec, SystemRunner};
 use crate::driver::{DriverError, FanDriver};
+use crate::explain::{Action, Explanation};
+use crate::hp_bios::{BiosRequest, BiosResponse, BiosTransport, HpBios};
+use crate::hp_script;
+use crate::hp_system::FanReadings;
 use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
 
-pub struct HpWmiDriver {
-    script_path: String,
+pub struct HpWmiDriver<R: CommandRunner = SystemRunner> {
+    runner: R,
 }
 
 impl HpWmiDriver {
     pub fn new() -> Result<Self, DriverError> {
+        Self::with_runner(SystemRunner)
+    }
+}
+
+impl<R: CommandRunner> HpWmiDriver<R> {
+    /// Build the driver on top of a specific command runner (e.g. `ScriptedRunner` in tests).
+    pub fn with_runner(runner: R) -> Result<Self, DriverError> {
         // Check if we're on an HP system by looking for the WMI class
-        let check = Command::new("powershell")
-            .args(&["-NoProfile", "-Command", 
-                    "Get-CimInstance -ClassName 'hpqBIntM' -Namespace 'root\\wmi' -ErrorAction SilentlyContinue | Select-Object -First 1"])
-            .output();
+        let check = runner.run(&CommandSpec::new("powershell").args([
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance -ClassName 'hpqBIntM' -Namespace 'root\\wmi' -ErrorAction SilentlyContinue | Select-Object -First 1",
+        ]));
 
         match check {
-            Ok(output) if output.status.success() && !output.stdout.is_empty() => {
-                Ok(Self {
-                    script_path: ".\\SetMaxFan.ps1".to_string(),
-                })
-            }
+            Ok(output) if output.success() && !output.stdout.trim().is_empty() => Ok(Self { runner }),
             _ => Err(DriverError::NotReady(
                 "hpqBIntM WMI class not found - not an HP OMEN/Victus system?".into()
             ))
         }
     }
 
-    fn run_script(&self, script_name: &str) -> Result<(), DriverError> {
-        let output = Command::new("powershell")
-            .args(&[
-                "-NoProfile",
-                "-ExecutionPolicy", "Bypass",
-                "-File", script_name
-            ])
-            .output()
-            .map_err(|e| DriverError::Internal(format!("Failed to run PowerShell: {}", e)))?;
-
-        if output.status.success() {
-            Ok(())
-        } else {
-            Err(DriverError::Internal(format!(
-                "Script failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )))
+    /// Send `requests` in one PowerShell run, returning a response per request.
+    pub fn call_batch(&self, requests: &[BiosRequest]) -> Result<Vec<BiosResponse>, DriverError> {
+        let output = self.runner.run(&hp_script::command(hp_script::render(requests)))?;
+        if !output.success() {
+            return Err(DriverError::Internal(format!("BIOS script failed: {}", output.stderr.trim())));
         }
+        hp_script::parse_output(&output.stdout, requests.len())
+    }
+
+    /// Typed BIOS access through this driver's PowerShell runner.
+    pub fn bios(&self) -> HpBios<&Self> {
+        HpBios::new(self)
+    }
+
+    /// Set each fan's level in units of 100 RPM, where the board allows manual fan control.
+    pub fn set_fan_levels(&self, levels: &[u8]) -> Result<(), DriverError> {
+        self.bios().set_fan_levels(levels)
+    }
+}
+
+impl<R: CommandRunner> BiosTransport for HpWmiDriver<R> {
+    fn call(&self, request: &BiosRequest) -> Result<BiosResponse, DriverError> {
+        let mut responses = self.call_batch(std::slice::from_ref(request))?;
+        Ok(responses.remove(0))
     }
 }
 
-impl FanDriver for HpWmiDriver {
+impl<R: CommandRunner> FanDriver for HpWmiDriver<R> {
     fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
         Ok(FanCapabilities {
             supported: true,
@@ -74,16 +88,136 @@ impl FanDriver for HpWmiDriver {
     }
 
     fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
-        let script = if profile == 1 {
-            "SetMaxFan.ps1"
-        } else {
-            "SetNormalFan.ps1"
-        };
-        
-        self.run_script(script)
+        self.bios().set_max_fan(max_fan(profile)?)
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let request = BiosRequest::set_max_fan(max_fan(profile)?);
+        let script = hp_script::command(hp_script::render(std::slice::from_ref(&request)));
+        Ok(Explanation::new(profile)
+            .action(Action::from(&request))
+            .action(Action::from(&script))
+            .verify("script prints BIOS[0] with rwReturnCode=0"))
     }
 
     fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
-        Err(DriverError::Unsupported("telemetry not available via WMI BIOS".into()))
+        // One PowerShell run for all queries; each reading fails on its own.
+        let readings = FanReadings::decode(self.call_batch(&FanReadings::requests())?);
+        Ok(FanTelemetry {
+            rpm: readings.max_rpm(),
+            temperature_c: readings.temperature_c.as_ref().ok().copied().map(f32::from),
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+
+    fn diagnose(&self) -> Vec<String> {
+        match self.bios().board_info() {
+            Ok(board) => board.diagnose(),
+            Err(e) => vec![format!("system design data unavailable: {}", e)],
+        }
+    }
+}
+
+/// Whether `profile` turns max fan on: profile 1 does, profile 0 turns it off.
+fn max_fan(profile: ProfileId) -> Result<bool, DriverError> {
+    match profile {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(DriverError::Unsupported(format!("Unknown profile: {}", profile))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{CommandOutput, ScriptedRunner};
+    use crate::hp_bios::command_type;
+
+    fn probed() -> Result<CommandOutput, DriverError> {
+        Ok(CommandOutput::ok("InstanceName : ACPI\\PNP0C14\\0_0\n"))
+    }
+
+    #[test]
+    fn probe_needs_the_bios_class() {
+        let missing = ScriptedRunner::new().expect("powershell", Ok(CommandOutput::ok("")));
+        assert!(matches!(HpWmiDriver::with_runner(missing), Err(DriverError::NotReady(_))));
+        let failed = ScriptedRunner::new().expect("powershell", Ok(CommandOutput::failed(1, "Invalid class")));
+        assert!(matches!(HpWmiDriver::with_runner(failed), Err(DriverError::NotReady(_))));
+        assert!(HpWmiDriver::with_runner(ScriptedRunner::new().expect("powershell", probed())).is_ok());
+    }
+
+    #[test]
+    fn max_profile_pipes_the_max_fan_request() {
+        let runner = ScriptedRunner::new()
+            .expect("powershell", probed())
+            .expect("powershell", Ok(CommandOutput::ok("BIOS[0] rwReturnCode=0 Data=\n")));
+        let driver = HpWmiDriver::with_runner(&runner).unwrap();
+        driver.set_profile(1).unwrap();
+
+        let script = runner.calls()[1].stdin.clone().unwrap();
+        assert_eq!(script, hp_script::render(&[BiosRequest::set_max_fan(true)]));
+        assert!(script.contains(&format!("CommandType = [UInt32] 0x{:X}", command_type::SET_MAX_FAN)));
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn bios_and_script_failures_are_errors() {
+        let runner = ScriptedRunner::new()
+            .expect("powershell", probed())
+            .expect("powershell", Ok(CommandOutput::ok("BIOS[0] rwReturnCode=4 Data=\n")))
+            .expect("powershell", Ok(CommandOutput::failed(1, "Access denied")))
+            .expect("powershell", Ok(CommandOutput::ok("WARNING: nothing printed\n")));
+        let driver = HpWmiDriver::with_runner(&runner).unwrap();
+        assert!(matches!(driver.set_profile(1), Err(DriverError::Unsupported(_))));
+        assert!(matches!(driver.set_profile(0), Err(DriverError::Internal(_))));
+        assert!(matches!(driver.set_profile(0), Err(DriverError::Internal(_))));
+    }
+
+    #[test]
+    fn unknown_profiles_are_rejected_without_a_bios_call() {
+        let runner = ScriptedRunner::new().expect("powershell", probed());
+        let driver = HpWmiDriver::with_runner(&runner).unwrap();
+        assert!(matches!(driver.set_profile(2), Err(DriverError::Unsupported(_))));
+        assert!(matches!(driver.explain_profile(2), Err(DriverError::Unsupported(_))));
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[test]
+    fn explain_lists_the_request_and_script() {
+        let driver = HpWmiDriver::with_runner(ScriptedRunner::new().expect("powershell", probed())).unwrap();
+        let explanation = driver.explain_profile(0).unwrap();
+        assert_eq!(explanation.actions[0], Action::from(&BiosRequest::set_max_fan(false)));
+        assert_eq!(explanation.actions.len(), 2);
+    }
+
+    #[test]
+    fn telemetry_reads_everything_in_one_script() {
+        let runner = ScriptedRunner::new().expect("powershell", probed()).expect(
+            "powershell",
+            Ok(CommandOutput::ok(
+                "BIOS[0] rwReturnCode=0 Data=02\nBIOS[1] rwReturnCode=0 Data=1f1c0000\nBIOS[2] rwReturnCode=0 Data=3d\n",
+            )),
+        );
+        let driver = HpWmiDriver::with_runner(&runner).unwrap();
+        let telemetry = driver.telemetry().unwrap();
+        assert_eq!(telemetry.rpm, Some(3100));
+        assert_eq!(telemetry.temperature_c, Some(61.0));
+        assert_eq!(runner.calls().len(), 2);
+        assert_eq!(runner.calls()[1].stdin.as_deref(), Some(hp_script::render(&FanReadings::requests()).as_str()));
+    }
+
+    #[test]
+    fn missing_fan_levels_keep_the_temperature() {
+        let runner = ScriptedRunner::new().expect("powershell", probed()).expect(
+            "powershell",
+            Ok(CommandOutput::ok(
+                "BIOS[0] rwReturnCode=0 Data=02\nBIOS[1] rwReturnCode=4 Data=\nBIOS[2] rwReturnCode=0 Data=3a\n",
+            )),
+        );
+        let telemetry = HpWmiDriver::with_runner(&runner).unwrap().telemetry().unwrap();
+        assert_eq!(telemetry.rpm, None);
+        assert_eq!(telemetry.temperature_c, Some(58.0));
     }
 }
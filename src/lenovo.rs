@@ -0,0 +1,330 @@
+//! Lenovo IdeaPad/Legion fan control.
+//! Profiles come from ACPI `platform_profile`; maximum cooling uses `legion-laptop`'s
+//! `fan_fullspeed` when available, else `ideapad_acpi`'s `fan_mode` thermal-dissipation mode.
+
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::sysfs;
+use crate::thermal;
+
+/// `ideapad_acpi` `fan_mode` values.
+const FAN_MODE_STANDARD: &str = "1";
+const FAN_MODE_DISSIPATION: &str = "4";
+
+/// How the maximum-cooling profile is applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MaxFanControl {
+    /// `legion-laptop` `fan_fullspeed` (0/1).
+    FullSpeed(PathBuf),
+    /// `ideapad_acpi` `fan_mode` set to efficient thermal dissipation.
+    FanMode(PathBuf),
+}
+
+impl MaxFanControl {
+    fn path(&self) -> &Path {
+        match self {
+            Self::FullSpeed(path) | Self::FanMode(path) => path,
+        }
+    }
+
+    fn on_value(&self) -> &'static str {
+        match self {
+            Self::FullSpeed(_) => "1",
+            Self::FanMode(_) => FAN_MODE_DISSIPATION,
+        }
+    }
+
+    fn off_value(&self) -> &'static str {
+        match self {
+            Self::FullSpeed(_) => "0",
+            Self::FanMode(_) => FAN_MODE_STANDARD,
+        }
+    }
+}
+
+pub struct LenovoDriver {
+    sys_root: PathBuf,
+    /// `/sys/firmware/acpi/platform_profile`, when present.
+    platform_profile: Option<PathBuf>,
+    /// Entries of `platform_profile_choices`; profile ids are indices into this list.
+    choices: Vec<String>,
+    max_fan: Option<MaxFanControl>,
+    hwmon: Option<PathBuf>,
+}
+
+impl LenovoDriver {
+    /// Probe the live `/sys` tree.
+    pub fn new() -> Result<Self, DriverError> {
+        Self::probe(Path::new("/sys"))
+    }
+
+    /// Probe a sysfs tree rooted at `root` for ideapad_acpi, legion-laptop and platform_profile.
+    pub fn probe(root: &Path) -> Result<Self, DriverError> {
+        let legion = first_device_with(&root.join("bus/platform/drivers/legion"), "fan_fullspeed");
+        let ideapad = first_device_with(&root.join("bus/platform/drivers/ideapad_acpi"), "fan_mode");
+        let is_lenovo = sysfs::read_optional(&root.join("class/dmi/id/sys_vendor")).as_deref() == Some("LENOVO");
+
+        if legion.is_none() && ideapad.is_none() && !is_lenovo {
+            return Err(DriverError::NotReady(
+                "neither legion-laptop nor ideapad_acpi found - not a Lenovo IdeaPad/Legion?".into(),
+            ));
+        }
+
+        let max_fan = legion
+            .as_ref()
+            .map(|dev| MaxFanControl::FullSpeed(dev.join("fan_fullspeed")))
+            .or_else(|| ideapad.as_ref().map(|dev| MaxFanControl::FanMode(dev.join("fan_mode"))));
+
+        let acpi = root.join("firmware/acpi");
+        let platform_profile = Some(acpi.join("platform_profile")).filter(|p| p.exists());
+        let choices: Vec<String> = sysfs::read_optional(&acpi.join("platform_profile_choices"))
+            .map(|raw| raw.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        if max_fan.is_none() && (platform_profile.is_none() || choices.is_empty()) {
+            return Err(DriverError::NotReady(
+                "Lenovo system exposes neither platform_profile nor a fan mode attribute".into(),
+            ));
+        }
+
+        let hwmon = legion.as_ref().and_then(|dev| {
+            sysfs::numbered_entries(&dev.join("hwmon"), "hwmon")
+                .into_iter()
+                .map(|(_, path)| path)
+                .next()
+        });
+
+        Ok(Self {
+            sys_root: root.to_path_buf(),
+            platform_profile,
+            choices,
+            max_fan,
+            hwmon,
+        })
+    }
+
+    fn max_profile_id(&self) -> ProfileId {
+        self.choices.len() as ProfileId
+    }
+
+    fn max_fan_active(&self) -> Result<bool, DriverError> {
+        match &self.max_fan {
+            Some(control) => Ok(sysfs::read_trimmed(control.path())? == control.on_value()),
+            None => Ok(false),
+        }
+    }
+
+    /// Attribute writes that select `profile`, in order.
+    fn profile_writes(&self, profile: ProfileId) -> Result<Vec<(PathBuf, String)>, DriverError> {
+        if let Some(control) = &self.max_fan {
+            if profile == self.max_profile_id() {
+                return Ok(vec![(control.path().to_path_buf(), control.on_value().into())]);
+            }
+        }
+
+        let (Some(path), Some(choice)) = (&self.platform_profile, self.choices.get(profile as usize)) else {
+            return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile)));
+        };
+
+        let mut writes = Vec::new();
+        if let Some(control) = &self.max_fan {
+            if self.max_fan_active()? {
+                writes.push((control.path().to_path_buf(), control.off_value().into()));
+            }
+        }
+        writes.push((path.clone(), choice.clone()));
+        Ok(writes)
+    }
+}
+
+impl FanDriver for LenovoDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        let mut profiles: Vec<FanProfile> = self
+            .choices
+            .iter()
+            .enumerate()
+            .map(|(index, choice)| FanProfile {
+                id: index as ProfileId,
+                name: display_name(choice),
+                // Without a fan attribute, the performance profile is the best we can do.
+                is_maximum: self.max_fan.is_none() && choice == "performance",
+            })
+            .collect();
+
+        if self.max_fan.is_some() {
+            profiles.push(FanProfile {
+                id: self.max_profile_id(),
+                name: "Full Speed (Max)".into(),
+                is_maximum: true,
+            });
+        }
+
+        Ok(FanCapabilities {
+            supported: true,
+            profiles,
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        if self.max_fan_active()? {
+            return Ok(Some(self.max_profile_id()));
+        }
+        let Some(path) = &self.platform_profile else {
+            return Ok(None);
+        };
+        let current = sysfs::read_trimmed(path)?;
+        Ok(self
+            .choices
+            .iter()
+            .position(|choice| *choice == current)
+            .map(|index| index as ProfileId))
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        for (path, value) in self.profile_writes(profile)? {
+            sysfs::write_value(&path, &value)?;
+        }
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        Ok(Explanation::new(profile).writes(self.profile_writes(profile)?))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        let rpm = self
+            .hwmon
+            .as_ref()
+            .and_then(|hwmon| sysfs::read_i64(&hwmon.join("fan1_input")).ok())
+            .and_then(|rpm| u32::try_from(rpm).ok());
+
+        Ok(FanTelemetry {
+            rpm,
+            temperature_c: thermal::package_temperature_c(&self.sys_root),
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+/// First bound device under a platform driver directory that exposes `attribute`.
+fn first_device_with(driver_dir: &Path, attribute: &str) -> Option<PathBuf> {
+    std::fs::read_dir(driver_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.join(attribute).exists())
+}
+
+/// "balanced-performance" -> "Balanced performance".
+fn display_name(choice: &str) -> String {
+    let spaced = choice.replace('-', " ");
+    let mut chars = spaced.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => spaced,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    const CHOICES: &str = "low-power balanced performance\n";
+    const LEGION: &str = "bus/platform/drivers/legion/PNP0C09:00";
+    const IDEAPAD: &str = "bus/platform/drivers/ideapad_acpi/VPC2004:00";
+
+    fn platform_profile(sys: &Fixture, current: &str) {
+        sys.write("firmware/acpi/platform_profile", current)
+            .write("firmware/acpi/platform_profile_choices", CHOICES);
+    }
+
+    fn names(driver: &LenovoDriver) -> Vec<(ProfileId, String, bool)> {
+        let caps = driver.capabilities().unwrap();
+        caps.profiles.into_iter().map(|p| (p.id, p.name, p.is_maximum)).collect()
+    }
+
+    #[test]
+    fn probe_needs_a_lenovo_attribute() {
+        let sys = Fixture::new();
+        assert!(matches!(LenovoDriver::probe(sys.root()), Err(DriverError::NotReady(_))));
+
+        // A Lenovo without platform_profile or a fan attribute has nothing to control.
+        sys.write("class/dmi/id/sys_vendor", "LENOVO\n");
+        assert!(matches!(LenovoDriver::probe(sys.root()), Err(DriverError::NotReady(_))));
+    }
+
+    #[test]
+    fn platform_profile_only_uses_performance_as_max() {
+        let sys = Fixture::new();
+        sys.write("class/dmi/id/sys_vendor", "LENOVO\n");
+        platform_profile(&sys, "balanced\n");
+        let driver = LenovoDriver::probe(sys.root()).unwrap();
+        assert_eq!(
+            names(&driver),
+            [
+                (0, "Low power".to_string(), false),
+                (1, "Balanced".to_string(), false),
+                (2, "Performance".to_string(), true),
+            ]
+        );
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+
+        driver.set_profile(0).unwrap();
+        assert_eq!(sys.read("firmware/acpi/platform_profile"), "low-power");
+        assert_eq!(driver.current_profile().unwrap(), Some(0));
+    }
+
+    #[test]
+    fn legion_full_speed_is_the_max_profile() {
+        let sys = Fixture::new();
+        platform_profile(&sys, "performance\n");
+        sys.write(&format!("{}/fan_fullspeed", LEGION), "0\n")
+            .write(&format!("{}/hwmon/hwmon7/fan1_input", LEGION), "3100\n");
+        let driver = LenovoDriver::probe(sys.root()).unwrap();
+        assert_eq!(names(&driver)[3], (3, "Full Speed (Max)".to_string(), true));
+        assert!(!names(&driver)[2].2);
+
+        driver.set_profile(3).unwrap();
+        assert_eq!(sys.read(&format!("{}/fan_fullspeed", LEGION)), "1");
+        assert_eq!(driver.current_profile().unwrap(), Some(3));
+        assert_eq!(driver.telemetry().unwrap().rpm, Some(3100));
+
+        // Leaving max turns full speed off before selecting the platform profile.
+        let writes = driver.profile_writes(1).unwrap();
+        assert_eq!(writes[0], (sys.path(&format!("{}/fan_fullspeed", LEGION)), "0".to_string()));
+        driver.set_profile(1).unwrap();
+        assert_eq!(sys.read(&format!("{}/fan_fullspeed", LEGION)), "0");
+        assert_eq!(sys.read("firmware/acpi/platform_profile"), "balanced");
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn ideapad_fan_mode_is_used_without_legion() {
+        let sys = Fixture::new();
+        sys.write(&format!("{}/fan_mode", IDEAPAD), "1\n");
+        let driver = LenovoDriver::probe(sys.root()).unwrap();
+        // No platform_profile: the only profile is the fan mode.
+        assert_eq!(names(&driver), [(0, "Full Speed (Max)".to_string(), true)]);
+        assert_eq!(driver.current_profile().unwrap(), None);
+
+        driver.set_profile(0).unwrap();
+        assert_eq!(sys.read(&format!("{}/fan_mode", IDEAPAD)), FAN_MODE_DISSIPATION);
+        assert_eq!(driver.current_profile().unwrap(), Some(0));
+        assert!(matches!(driver.set_profile(1), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn legion_is_preferred_over_ideapad() {
+        let sys = Fixture::new();
+        sys.write(&format!("{}/fan_mode", IDEAPAD), "1\n")
+            .write(&format!("{}/fan_fullspeed", LEGION), "0\n");
+        let driver = LenovoDriver::probe(sys.root()).unwrap();
+        assert_eq!(driver.max_fan, Some(MaxFanControl::FullSpeed(sys.path(&format!("{}/fan_fullspeed", LEGION)))));
+    }
+}
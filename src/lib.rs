@@ -1,14 +1,57 @@
 //! Core fan control logic (no UI). Relies on a driver that safely exposes ACPI fan profile switches.
 
 pub mod model;
+pub mod command;
+pub mod powercfg;
+pub mod power_config;
+pub mod config_driver;
+pub mod exec_driver;
+pub mod native_lib;
 pub mod driver;
+pub mod explain;
 pub mod manager;
+pub mod select;
+pub mod state;
+pub mod doctor;
+#[cfg(feature = "serialize")]
+pub mod report;
+pub mod sysfs;
+pub mod thermal;
 
 #[cfg(windows)]
 pub mod hp;
 
-#[cfg(windows)]
+pub mod hp_bios;
+pub mod hp_lighting;
+pub mod hp_rpc;
+pub mod hp_script;
+pub mod hp_system;
 pub mod hp_wmi;
-
-#[cfg(windows)]
 pub mod windows_power;
+
+#[cfg(target_os = "linux")]
+pub mod cooling_device;
+
+#[cfg(target_os = "linux")]
+pub mod thinkpad;
+
+#[cfg(target_os = "linux")]
+pub mod asus;
+
+#[cfg(target_os = "linux")]
+pub mod dell;
+
+#[cfg(target_os = "linux")]
+pub mod lenovo;
+
+#[cfg(target_os = "linux")]
+pub mod msi;
+
+#[cfg(target_os = "linux")]
+pub mod cpufreq;
+
+#[cfg(target_os = "linux")]
+pub mod rapl;
+
+#[cfg(test)]
+mod test_support;
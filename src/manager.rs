@@ -1,5 +1,6 @@
 use std::thread;
 use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
 use crate::model::{FanCapabilities, FanTelemetry, MaxFanPolicy, ProfileId};
 
 /// Orchestrates safe fan profile changes with optional verification.
@@ -17,13 +18,7 @@ impl<D: FanDriver> FanManager<D> {
     /// Returns the profile id that was applied.
     pub fn set_max_profile(&self) -> Result<ProfileId, DriverError> {
         let caps = self.driver.capabilities()?;
-        if !caps.supported {
-            return Err(DriverError::Unsupported("fan profiles not supported on this platform".into()));
-        }
-
-        let Some(profile) = Self::select_max_profile(&caps) else {
-            return Err(DriverError::Unsupported("no maximum profile declared".into()));
-        };
+        let profile = Self::max_target(&caps)?;
 
         let before = self.driver.telemetry().ok();
         self.driver.set_profile(profile.id)?;
@@ -50,6 +45,66 @@ impl<D: FanDriver> FanManager<D> {
         Ok(profile.id)
     }
 
+    /// Dry run of `set_max_profile`: the driver's actions plus the ramp and temperature checks
+    /// this policy would run afterwards.
+    pub fn explain_max_profile(&self) -> Result<Explanation, DriverError> {
+        let caps = self.driver.capabilities()?;
+        let profile = Self::max_target(&caps)?;
+        let mut explanation = self.driver.explain_profile(profile.id)?;
+
+        let settle = self.policy.settle_timeout.as_millis();
+        if let Some(min_delta) = self.policy.min_rpm_delta {
+            explanation = explanation.verify(format!(
+                "after {} ms, fan RPM must have risen by at least {} (skipped without RPM telemetry)",
+                settle, min_delta
+            ));
+        }
+        if let Some(max_temp) = self.policy.max_safe_temp_c {
+            explanation = explanation.verify(format!(
+                "after {} ms, temperature must be at most {:.1}°C (skipped without temperature telemetry)",
+                settle, max_temp
+            ));
+        }
+        Ok(explanation)
+    }
+
+    /// Apply a profile after checking it is one the driver advertises.
+    pub fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        self.check_advertised(profile)?;
+        self.driver.set_profile(profile)
+    }
+
+    /// Dry run of `set_profile`.
+    pub fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        self.check_advertised(profile)?;
+        self.driver.explain_profile(profile)
+    }
+
+    /// Undo a boost: go back to `previous` if the driver still offers it, else to the default
+    /// profile. Returns the profile id that was applied.
+    pub fn restore_profile(&self, previous: Option<ProfileId>) -> Result<ProfileId, DriverError> {
+        let target = Self::restore_target(&self.driver.capabilities()?, previous)?;
+        self.driver.set_profile(target)?;
+        Ok(target)
+    }
+
+    /// Dry run of `restore_profile`.
+    pub fn explain_restore_profile(&self, previous: Option<ProfileId>) -> Result<Explanation, DriverError> {
+        let caps = self.driver.capabilities()?;
+        let target = Self::restore_target(&caps, previous)?;
+        let explanation = self.driver.explain_profile(target)?;
+        Ok(if previous == Some(target) {
+            explanation.note(format!("restoring the remembered profile {}", target))
+        } else {
+            explanation.note(format!("no usable remembered profile: falling back to the default {}", target))
+        })
+    }
+
+    /// Currently selected profile, if the driver can tell.
+    pub fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        self.driver.current_profile()
+    }
+
     /// List capabilities.
     pub fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
         self.driver.capabilities()
@@ -60,7 +115,44 @@ impl<D: FanDriver> FanManager<D> {
         self.driver.telemetry()
     }
 
+    /// The profile firmware would run by itself: a non-maximum profile named like
+    /// "Default"/"Auto"/"Balanced"/"Normal", else the first non-maximum one.
+    pub fn default_profile(caps: &FanCapabilities) -> Option<&crate::model::FanProfile> {
+        let normal = caps.profiles.iter().filter(|p| !p.is_maximum);
+        let is_default = |p: &&crate::model::FanProfile| {
+            let name = p.name.to_ascii_lowercase();
+            ["default", "auto", "balanced", "normal"].iter().any(|word| name.contains(word))
+        };
+        normal.clone().find(is_default).or_else(|| normal.clone().next())
+    }
+
+    pub fn driver(&self) -> &D {
+        &self.driver
+    }
+
     fn select_max_profile(caps: &FanCapabilities) -> Option<&crate::model::FanProfile> {
         caps.profiles.iter().find(|p| p.is_maximum)
     }
+
+    fn max_target(caps: &FanCapabilities) -> Result<&crate::model::FanProfile, DriverError> {
+        if !caps.supported {
+            return Err(DriverError::Unsupported("fan profiles not supported on this platform".into()));
+        }
+        Self::select_max_profile(caps).ok_or_else(|| DriverError::Unsupported("no maximum profile declared".into()))
+    }
+
+    fn restore_target(caps: &FanCapabilities, previous: Option<ProfileId>) -> Result<ProfileId, DriverError> {
+        previous
+            .filter(|id| caps.profiles.iter().any(|p| p.id == *id))
+            .or_else(|| Self::default_profile(caps).map(|p| p.id))
+            .ok_or_else(|| DriverError::Unsupported("no profile to restore".into()))
+    }
+
+    fn check_advertised(&self, profile: ProfileId) -> Result<(), DriverError> {
+        let caps = self.driver.capabilities()?;
+        if !caps.profiles.iter().any(|p| p.id == profile) {
+            return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile)));
+        }
+        Ok(())
+    }
 }
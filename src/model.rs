@@ -1,9 +1,15 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "serialize")]
+use serde::{Serialize, Serializer};
+
+use crate::thermal::ThermalLimits;
 
 /// Logical fan profile identifier defined by OEM whitelist.
 pub type ProfileId = u32;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
 pub struct FanProfile {
     /// Identifier passed to the driver; must map to a whitelisted ACPI argument.
     pub id: ProfileId,
@@ -14,6 +20,7 @@ pub struct FanProfile {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
 pub struct FanCapabilities {
     pub supported: bool,
     pub profiles: Vec<FanProfile>,
@@ -29,16 +36,32 @@ impl FanCapabilities {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
 pub struct FanTelemetry {
     pub rpm: Option<u32>,
     pub temperature_c: Option<f32>,
+    /// Fan duty cycle (0-100) for controllers that report speed as a percentage instead of RPM.
+    pub fan_duty_percent: Option<u8>,
+    /// Monotonic sample time, for measuring intervals between readings.
+    #[cfg_attr(feature = "serialize", serde(skip))]
     pub timestamp: std::time::Instant,
+    /// Wall-clock sample time; serialized as `captured_at_unix_ms`.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(rename = "captured_at_unix_ms", serialize_with = "serialize_unix_ms")
+    )]
+    pub captured_at: SystemTime,
 }
 
 /// Policy configuration for setting max fan via profile selection.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
 pub struct MaxFanPolicy {
     /// How long to wait after setting the profile before validating ramp.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(rename = "settle_timeout_ms", serialize_with = "serialize_millis")
+    )]
     pub settle_timeout: Duration,
     /// Minimum expected RPM delta to consider the change effective (if RPM exists).
     pub min_rpm_delta: Option<u32>,
@@ -46,12 +69,47 @@ pub struct MaxFanPolicy {
     pub max_safe_temp_c: Option<f32>,
 }
 
-impl Default for MaxFanPolicy {
-    fn default() -> Self {
+/// Degrees kept between the safety threshold and the CPU's critical trip point.
+pub const CRITICAL_TRIP_MARGIN_C: f32 = 5.0;
+
+/// Threshold used when no critical trip point can be discovered.
+pub const FALLBACK_MAX_SAFE_TEMP_C: f32 = 95.0;
+
+impl MaxFanPolicy {
+    /// Build the default policy with its safety threshold derived from discovered trip points:
+    /// `CRITICAL_TRIP_MARGIN_C` below the CPU package's critical trip (the sensor telemetry
+    /// reads), or `FALLBACK_MAX_SAFE_TEMP_C`.
+    pub fn from_thermal_limits(limits: &ThermalLimits) -> Self {
+        let max_safe_temp_c = limits
+            .cpu_critical_c()
+            .map(|critical| critical - CRITICAL_TRIP_MARGIN_C)
+            .unwrap_or(FALLBACK_MAX_SAFE_TEMP_C);
+
         Self {
             settle_timeout: Duration::from_millis(1500),
             min_rpm_delta: Some(500),
-            max_safe_temp_c: Some(95.0),
+            max_safe_temp_c: Some(max_safe_temp_c),
         }
     }
 }
+
+impl Default for MaxFanPolicy {
+    fn default() -> Self {
+        Self::from_thermal_limits(&ThermalLimits::discover())
+    }
+}
+
+/// Milliseconds since the Unix epoch (negative before it).
+#[cfg(feature = "serialize")]
+pub(crate) fn serialize_unix_ms<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let ms = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(after) => after.as_millis() as i64,
+        Err(before) => -(before.duration().as_millis() as i64),
+    };
+    serializer.serialize_i64(ms)
+}
+
+#[cfg(feature = "serialize")]
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
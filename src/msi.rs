@@ -0,0 +1,243 @@
+//! MSI laptop control through the `msi-ec` platform driver (`/sys/devices/platform/msi-ec`).
+//! Shift modes (eco, comfort, sport, turbo) become profiles; Cooler Boost is the maximum profile.
+
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::sysfs;
+
+pub struct MsiEcDriver {
+    platform: PathBuf,
+    /// Entries of `available_shift_modes`; profile ids are indices into this list.
+    shift_modes: Vec<String>,
+    has_cooler_boost: bool,
+}
+
+impl MsiEcDriver {
+    /// Probe the live `/sys` tree.
+    pub fn new() -> Result<Self, DriverError> {
+        Self::probe(Path::new("/sys"))
+    }
+
+    /// Probe a sysfs tree rooted at `root` for the msi-ec platform device.
+    pub fn probe(root: &Path) -> Result<Self, DriverError> {
+        let platform = root.join("devices/platform/msi-ec");
+        if !platform.is_dir() {
+            return Err(DriverError::NotReady(format!(
+                "{} not found - msi-ec not loaded or not an MSI system?",
+                platform.display()
+            )));
+        }
+
+        let shift_modes: Vec<String> = if platform.join("shift_mode").exists() {
+            sysfs::read_optional(&platform.join("available_shift_modes"))
+                .map(|raw| raw.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let has_cooler_boost = platform.join("cooler_boost").exists();
+
+        if shift_modes.is_empty() && !has_cooler_boost {
+            return Err(DriverError::NotReady(
+                "msi-ec exposes neither shift_mode nor cooler_boost for this EC firmware".into(),
+            ));
+        }
+
+        Ok(Self {
+            platform,
+            shift_modes,
+            has_cooler_boost,
+        })
+    }
+
+    /// Cooler Boost follows the shift modes so shift mode ids stay stable.
+    fn cooler_boost_id(&self) -> ProfileId {
+        self.shift_modes.len() as ProfileId
+    }
+
+    fn cooler_boost_on(&self) -> Result<bool, DriverError> {
+        if !self.has_cooler_boost {
+            return Ok(false);
+        }
+        Ok(sysfs::read_trimmed(&self.platform.join("cooler_boost"))? == "on")
+    }
+
+    /// Attribute writes that select `profile`, in order.
+    fn profile_writes(&self, profile: ProfileId) -> Result<Vec<(PathBuf, String)>, DriverError> {
+        let cooler_boost = self.platform.join("cooler_boost");
+        if self.has_cooler_boost && profile == self.cooler_boost_id() {
+            return Ok(vec![(cooler_boost, "on".into())]);
+        }
+
+        let mode = self
+            .shift_modes
+            .get(profile as usize)
+            .ok_or_else(|| DriverError::Unsupported(format!("Unknown profile: {}", profile)))?;
+
+        let mut writes = Vec::new();
+        if self.cooler_boost_on()? {
+            writes.push((cooler_boost, "off".into()));
+        }
+        writes.push((self.platform.join("shift_mode"), mode.clone()));
+        Ok(writes)
+    }
+
+    /// Current `fan_mode` (e.g. "auto", "silent", "advanced"), if exposed.
+    pub fn fan_mode(&self) -> Option<String> {
+        sysfs::read_optional(&self.platform.join("fan_mode"))
+    }
+}
+
+impl FanDriver for MsiEcDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        let mut profiles: Vec<FanProfile> = self
+            .shift_modes
+            .iter()
+            .enumerate()
+            .map(|(index, mode)| FanProfile {
+                id: index as ProfileId,
+                name: display_name(mode),
+                is_maximum: !self.has_cooler_boost && mode == "turbo",
+            })
+            .collect();
+
+        if self.has_cooler_boost {
+            profiles.push(FanProfile {
+                id: self.cooler_boost_id(),
+                name: "Cooler Boost (Max)".into(),
+                is_maximum: true,
+            });
+        }
+
+        Ok(FanCapabilities {
+            supported: true,
+            profiles,
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        if self.cooler_boost_on()? {
+            return Ok(Some(self.cooler_boost_id()));
+        }
+        if self.shift_modes.is_empty() {
+            return Ok(None);
+        }
+        let current = sysfs::read_trimmed(&self.platform.join("shift_mode"))?;
+        Ok(self
+            .shift_modes
+            .iter()
+            .position(|mode| *mode == current)
+            .map(|index| index as ProfileId))
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        for (path, value) in self.profile_writes(profile)? {
+            sysfs::write_value(&path, &value)?;
+        }
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        Ok(Explanation::new(profile).writes(self.profile_writes(profile)?))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        // The EC reports CPU fan speed as a duty percentage, not RPM.
+        let fan_duty_percent = sysfs::read_i64(&self.platform.join("cpu/realtime_fan_speed"))
+            .ok()
+            .and_then(|duty| u8::try_from(duty).ok());
+        let temperature_c = sysfs::read_i64(&self.platform.join("cpu/realtime_temperature"))
+            .ok()
+            .map(|t| t as f32);
+
+        Ok(FanTelemetry {
+            rpm: None,
+            temperature_c,
+            fan_duty_percent,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+fn display_name(mode: &str) -> String {
+    let mut chars = mode.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    const PLATFORM: &str = "devices/platform/msi-ec";
+
+    fn msi(cooler_boost: bool) -> Fixture {
+        let sys = Fixture::new();
+        sys.write(&format!("{}/shift_mode", PLATFORM), "comfort\n")
+            .write(&format!("{}/available_shift_modes", PLATFORM), "eco\ncomfort\nsport\nturbo\n")
+            .write(&format!("{}/fan_mode", PLATFORM), "auto\n")
+            .write(&format!("{}/cpu/realtime_fan_speed", PLATFORM), "45\n")
+            .write(&format!("{}/cpu/realtime_temperature", PLATFORM), "68\n");
+        if cooler_boost {
+            sys.write(&format!("{}/cooler_boost", PLATFORM), "off\n");
+        }
+        sys
+    }
+
+    #[test]
+    fn probe_needs_shift_mode_or_cooler_boost() {
+        assert!(matches!(MsiEcDriver::probe(Fixture::new().root()), Err(DriverError::NotReady(_))));
+        let sys = Fixture::new();
+        sys.write(&format!("{}/fw_version", PLATFORM), "1552EMS1.118\n");
+        assert!(matches!(MsiEcDriver::probe(sys.root()), Err(DriverError::NotReady(_))));
+    }
+
+    #[test]
+    fn shift_modes_then_cooler_boost() {
+        let sys = msi(true);
+        let driver = MsiEcDriver::probe(sys.root()).unwrap();
+        let caps = driver.capabilities().unwrap();
+        let names: Vec<&str> = caps.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Eco", "Comfort", "Sport", "Turbo", "Cooler Boost (Max)"]);
+        assert_eq!(caps.profiles.iter().filter(|p| p.is_maximum).count(), 1);
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+        assert_eq!(driver.fan_mode().as_deref(), Some("auto"));
+
+        driver.set_profile(4).unwrap();
+        assert_eq!(sys.read(&format!("{}/cooler_boost", PLATFORM)), "on");
+        assert_eq!(driver.current_profile().unwrap(), Some(4));
+
+        // Leaving Cooler Boost turns it off before switching the shift mode.
+        assert_eq!(driver.explain_profile(2).unwrap().actions.len(), 2);
+        driver.set_profile(2).unwrap();
+        assert_eq!(sys.read(&format!("{}/cooler_boost", PLATFORM)), "off");
+        assert_eq!(sys.read(&format!("{}/shift_mode", PLATFORM)), "sport");
+        assert_eq!(driver.current_profile().unwrap(), Some(2));
+        assert!(matches!(driver.set_profile(5), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn turbo_is_the_maximum_without_cooler_boost() {
+        let sys = msi(false);
+        let driver = MsiEcDriver::probe(sys.root()).unwrap();
+        let caps = driver.capabilities().unwrap();
+        let maximum: Vec<ProfileId> = caps.profiles.iter().filter(|p| p.is_maximum).map(|p| p.id).collect();
+        assert_eq!(maximum, [3]);
+        assert!(matches!(driver.set_profile(4), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn telemetry_reports_duty_not_rpm() {
+        let telemetry = MsiEcDriver::probe(msi(true).root()).unwrap().telemetry().unwrap();
+        assert_eq!(telemetry.rpm, None);
+        assert_eq!(telemetry.fan_duty_percent, Some(45));
+        assert_eq!(telemetry.temperature_c, Some(68.0));
+    }
+}
@@ -0,0 +1,401 @@
+//! Generic dynamic-library driver: loads the first library found on a search path and binds
+//! fan operations to exported C symbols named by an explicit symbol map.
+//!
+//! Each operation has a fixed C signature:
+//! - `set_profile`: `int32_t fn(uint32_t profile)`, 0 on success
+//! - `get_profile`: `int32_t fn(void)`, negative on failure
+//! - `fan_rpm`: `int32_t fn(void)`, negative if unavailable
+//! - `temperature`: `int32_t fn(void)` in °C, negative if unavailable
+
+use std::path::{Path, PathBuf};
+
+use libloading::Library;
+use serde::Deserialize;
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::{Action, Explanation};
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+
+type SetProfileFn = unsafe extern "C" fn(profile: u32) -> i32;
+type GetI32Fn = unsafe extern "C" fn() -> i32;
+
+/// Operations a library can provide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NativeOp {
+    SetProfile,
+    GetProfile,
+    FanRpm,
+    Temperature,
+}
+
+impl NativeOp {
+    pub const ALL: [NativeOp; 4] = [Self::SetProfile, Self::GetProfile, Self::FanRpm, Self::Temperature];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SetProfile => "set_profile",
+            Self::GetProfile => "get_profile",
+            Self::FanRpm => "fan_rpm",
+            Self::Temperature => "temperature",
+        }
+    }
+}
+
+/// Candidate symbol names per operation, tried in order.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SymbolMap {
+    #[serde(default)]
+    pub set_profile: Vec<String>,
+    #[serde(default)]
+    pub get_profile: Vec<String>,
+    #[serde(default)]
+    pub fan_rpm: Vec<String>,
+    #[serde(default)]
+    pub temperature: Vec<String>,
+}
+
+impl SymbolMap {
+    pub fn candidates(&self, op: NativeOp) -> &[String] {
+        match op {
+            NativeOp::SetProfile => &self.set_profile,
+            NativeOp::GetProfile => &self.get_profile,
+            NativeOp::FanRpm => &self.fan_rpm,
+            NativeOp::Temperature => &self.temperature,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NativeProfileConfig {
+    pub id: ProfileId,
+    pub name: String,
+    #[serde(default)]
+    pub maximum: bool,
+}
+
+/// Which library to load, how to bind it and which profiles it accepts. Loadable from a
+/// `drivers.d` TOML file with a `[native]` table:
+///
+/// ```toml
+/// [native]
+/// search_paths = ["/opt/vendor/lib/libfan.so", "libfan.so"]
+/// profiles = [{ id = 0, name = "Auto" }, { id = 1, name = "Max", maximum = true }]
+///
+/// [native.symbols]
+/// set_profile = ["fan_set_profile", "SetFanProfile"]
+/// get_profile = ["fan_get_profile"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NativeLibConfig {
+    pub search_paths: Vec<PathBuf>,
+    pub symbols: SymbolMap,
+    pub profiles: Vec<NativeProfileConfig>,
+}
+
+#[derive(Deserialize)]
+struct NativeLibConfigFile {
+    native: NativeLibConfig,
+}
+
+impl NativeLibConfig {
+    pub fn from_toml_str(text: &str) -> Result<Self, DriverError> {
+        toml::from_str::<NativeLibConfigFile>(text)
+            .map(|file| file.native)
+            .map_err(|e| DriverError::Internal(format!("invalid native library config: {}", e)))
+    }
+
+    /// Whether a config file declares a `[native]` library.
+    pub fn is_native_config(text: &str) -> bool {
+        toml::from_str::<toml::Table>(text).is_ok_and(|table| table.contains_key("native"))
+    }
+}
+
+/// Resolution result for one operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolStatus {
+    pub op: NativeOp,
+    /// The symbol that was bound, if any candidate was exported.
+    pub found: Option<String>,
+    /// Every candidate that was tried.
+    pub tried: Vec<String>,
+}
+
+pub struct NativeLibDriver {
+    // Function pointers below are only valid while `_library` is loaded; it lives as long as they do.
+    set_profile: Option<SetProfileFn>,
+    get_profile: Option<GetI32Fn>,
+    fan_rpm: Option<GetI32Fn>,
+    temperature: Option<GetI32Fn>,
+    symbols: Vec<SymbolStatus>,
+    profiles: Vec<FanProfile>,
+    path: PathBuf,
+    _library: Library,
+}
+
+impl NativeLibDriver {
+    /// Load the first library in `config.search_paths` that opens and bind its symbols.
+    pub fn new(config: NativeLibConfig) -> Result<Self, DriverError> {
+        let mut failures = Vec::new();
+        let loaded = config.search_paths.iter().find_map(|path| {
+            // Loading runs the library's initializers; callers choose which paths to trust.
+            match unsafe { Library::new(path) } {
+                Ok(library) => Some((path.clone(), library)),
+                Err(e) => {
+                    failures.push(format!("{}: {}", path.display(), e));
+                    None
+                }
+            }
+        });
+        let Some((path, library)) = loaded else {
+            return Err(DriverError::NotReady(format!(
+                "no library could be loaded ({})",
+                failures.join("; ")
+            )));
+        };
+
+        let symbols: Vec<SymbolStatus> = NativeOp::ALL
+            .iter()
+            .map(|&op| {
+                let tried = config.symbols.candidates(op).to_vec();
+                let found = tried.iter().find(|name| has_symbol(&library, name)).cloned();
+                SymbolStatus { op, found, tried }
+            })
+            .collect();
+        let found = |op: NativeOp| symbols.iter().find(|s| s.op == op).and_then(|s| s.found.clone());
+
+        // SAFETY: the signatures are the documented contract for each operation.
+        let set_profile = found(NativeOp::SetProfile).and_then(|name| unsafe { bind::<SetProfileFn>(&library, &name) });
+        let get_profile = found(NativeOp::GetProfile).and_then(|name| unsafe { bind::<GetI32Fn>(&library, &name) });
+        let fan_rpm = found(NativeOp::FanRpm).and_then(|name| unsafe { bind::<GetI32Fn>(&library, &name) });
+        let temperature = found(NativeOp::Temperature).and_then(|name| unsafe { bind::<GetI32Fn>(&library, &name) });
+
+        let profiles = config
+            .profiles
+            .into_iter()
+            .map(|p| FanProfile {
+                id: p.id,
+                name: p.name,
+                is_maximum: p.maximum,
+            })
+            .collect();
+
+        Ok(Self {
+            set_profile,
+            get_profile,
+            fan_rpm,
+            temperature,
+            symbols,
+            profiles,
+            path,
+            _library: library,
+        })
+    }
+
+    /// Path of the library that was loaded.
+    pub fn library_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Which symbol (if any) was bound for each operation.
+    pub fn symbols(&self) -> &[SymbolStatus] {
+        &self.symbols
+    }
+
+    /// Whether a symbol for `op` was found.
+    pub fn supports(&self, op: NativeOp) -> bool {
+        self.symbols.iter().any(|s| s.op == op && s.found.is_some())
+    }
+
+    fn missing(&self, op: NativeOp) -> DriverError {
+        let tried = self
+            .symbols
+            .iter()
+            .find(|s| s.op == op)
+            .map(|s| s.tried.join(", "))
+            .unwrap_or_default();
+        DriverError::Unsupported(format!(
+            "{} not exported by {} (tried: {})",
+            op.as_str(),
+            self.path.display(),
+            tried
+        ))
+    }
+}
+
+fn has_symbol(library: &Library, name: &str) -> bool {
+    unsafe { library.get::<*const ()>(name.as_bytes()).is_ok() }
+}
+
+/// Copy a function pointer out of `library`. Caller guarantees `T` matches the export.
+unsafe fn bind<T: Copy>(library: &Library, name: &str) -> Option<T> {
+    library.get::<T>(name.as_bytes()).ok().map(|symbol| *symbol)
+}
+
+impl FanDriver for NativeLibDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        if self.set_profile.is_none() {
+            return Ok(FanCapabilities::unsupported());
+        }
+        Ok(FanCapabilities {
+            supported: true,
+            profiles: self.profiles.clone(),
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        let Some(get) = self.get_profile else {
+            return Ok(None);
+        };
+        let result = unsafe { get() };
+        Ok(ProfileId::try_from(result).ok())
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        if !self.profiles.iter().any(|p| p.id == profile) {
+            return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile)));
+        }
+        let set = self.set_profile.ok_or_else(|| self.missing(NativeOp::SetProfile))?;
+        match unsafe { set(profile) } {
+            0 => Ok(()),
+            code => Err(DriverError::Internal(format!("return code: {}", code))),
+        }
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        if !self.profiles.iter().any(|p| p.id == profile) {
+            return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile)));
+        }
+        let function = self
+            .symbols
+            .iter()
+            .find(|s| s.op == NativeOp::SetProfile)
+            .and_then(|s| s.found.clone())
+            .ok_or_else(|| self.missing(NativeOp::SetProfile))?;
+        Ok(Explanation::new(profile)
+            .action(Action::LibraryCall {
+                library: self.path.clone(),
+                function,
+                argument: i64::from(profile),
+            })
+            .verify("function returns 0"))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        let read = |f: Option<GetI32Fn>| f.map(|f| unsafe { f() }).filter(|v| *v >= 0);
+        Ok(FanTelemetry {
+            rpm: read(self.fan_rpm).map(|rpm| rpm as u32),
+            temperature_c: read(self.temperature).map(|t| t as f32),
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+    use std::process::Command;
+
+    /// Build `tests/fixtures/native_fan.rs` into `dir` with the toolchain's rustc.
+    fn build_fixture(fixture: &Fixture) -> PathBuf {
+        let rustc = Path::new(env!("CARGO")).with_file_name(format!("rustc{}", std::env::consts::EXE_SUFFIX));
+        let output = fixture.path(&format!(
+            "{}native_fan{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        ));
+        let status = Command::new(rustc)
+            .args(["--edition", "2021", "--crate-type", "cdylib", "-o"])
+            .arg(&output)
+            .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/native_fan.rs"))
+            .status()
+            .expect("run rustc");
+        assert!(status.success(), "fixture library failed to build");
+        output
+    }
+
+    fn config(library: PathBuf, symbols: SymbolMap) -> NativeLibConfig {
+        NativeLibConfig {
+            search_paths: vec![PathBuf::from("/nonexistent/libmissing.so"), library],
+            symbols,
+            profiles: vec![
+                NativeProfileConfig {
+                    id: 0,
+                    name: "Auto".into(),
+                    maximum: false,
+                },
+                NativeProfileConfig {
+                    id: 1,
+                    name: "Max".into(),
+                    maximum: true,
+                },
+            ],
+        }
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn binds_exported_symbols_and_reports_missing_ones() {
+        let fixture = Fixture::new();
+        let library = build_fixture(&fixture);
+        let symbols = SymbolMap {
+            set_profile: names(&["SetFanProfile", "fan_set_profile"]),
+            get_profile: names(&["fan_get_profile"]),
+            fan_rpm: names(&["fan_rpm"]),
+            temperature: names(&["GetSystemTemperature"]),
+        };
+        let driver = NativeLibDriver::new(config(library.clone(), symbols)).unwrap();
+        assert_eq!(driver.library_path(), library);
+
+        let report: Vec<(NativeOp, Option<&str>)> =
+            driver.symbols().iter().map(|s| (s.op, s.found.as_deref())).collect();
+        assert_eq!(
+            report,
+            vec![
+                (NativeOp::SetProfile, Some("fan_set_profile")),
+                (NativeOp::GetProfile, Some("fan_get_profile")),
+                (NativeOp::FanRpm, Some("fan_rpm")),
+                (NativeOp::Temperature, None),
+            ]
+        );
+        assert_eq!(driver.symbols()[0].tried, names(&["SetFanProfile", "fan_set_profile"]));
+        assert!(!driver.supports(NativeOp::Temperature));
+
+        driver.set_profile(1).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+        assert!(matches!(driver.set_profile(2), Err(DriverError::Unsupported(_))));
+        let telemetry = driver.telemetry().unwrap();
+        assert_eq!(telemetry.rpm, Some(4200));
+        assert_eq!(telemetry.temperature_c, None);
+    }
+
+    #[test]
+    fn library_without_set_profile_is_unsupported() {
+        let fixture = Fixture::new();
+        let symbols = SymbolMap {
+            set_profile: names(&["SetThermalProfile"]),
+            ..SymbolMap::default()
+        };
+        let driver = NativeLibDriver::new(config(build_fixture(&fixture), symbols)).unwrap();
+        assert!(!driver.capabilities().unwrap().supported);
+        assert!(matches!(driver.explain_profile(0), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn no_loadable_library_lists_every_path() {
+        let mut config = config(PathBuf::from("/nonexistent/libother.so"), SymbolMap::default());
+        config.search_paths.truncate(1);
+        match NativeLibDriver::new(config) {
+            Err(DriverError::NotReady(message)) => assert!(message.contains("/nonexistent/libmissing.so")),
+            _ => panic!("expected NotReady"),
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Per-profile power settings from `power.toml`, applied alongside the fan profile by the
+//! wrappers that can enforce them: `hp_bios::HpPowerDriver` on HP boards under Windows,
+//! `rapl::PowerLimitedDriver` on Linux (PL1, PL2 and the PL1 window only):
+//!
+//! ```toml
+//! [profiles.Quiet]
+//! pl1_w = 25
+//! pl2_w = 35
+//! pl1_window_us = 28000000
+//! gpu = "minimum"
+//!
+//! [profiles."Extreme (Max)"]
+//! gpu = "maximum"
+//! ```
+//!
+//! Tables are keyed by profile name (case-insensitive) or numeric profile id. Profiles without
+//! a table get no power changes of their own.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::driver::DriverError;
+use crate::hp_bios::{CpuPowerLimits, GpuPowerLevel, HpPowerSettings, POWER_LIMIT_UNCHANGED};
+use crate::model::{FanCapabilities, ProfileId};
+
+#[derive(Debug, Error)]
+pub enum PowerConfigError {
+    #[error("failed to read power config {0}: {1}")]
+    Read(PathBuf, String),
+    #[error("invalid power config: {0}")]
+    Parse(String),
+    #[error("power config rejected: {0}")]
+    Invalid(String),
+}
+
+impl From<PowerConfigError> for DriverError {
+    fn from(e: PowerConfigError) -> Self {
+        match e {
+            PowerConfigError::Read(path, msg) => DriverError::Io(format!("{}: {}", path.display(), msg)),
+            PowerConfigError::Parse(msg) | PowerConfigError::Invalid(msg) => DriverError::Internal(msg),
+        }
+    }
+}
+
+/// Top-level layout of `power.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PowerConfig {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfilePower>,
+}
+
+/// Power settings attached to one profile. Unset fields leave the current value alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfilePower {
+    /// Sustained CPU package limit.
+    pub pl1_w: Option<f32>,
+    /// Turbo CPU package limit.
+    pub pl2_w: Option<f32>,
+    /// Peak CPU package limit.
+    pub pl4_w: Option<f32>,
+    /// PL1 averaging window (RAPL only).
+    pub pl1_window_us: Option<u64>,
+    pub gpu: Option<GpuSetting>,
+}
+
+/// GPU power preset, spelled as in OMEN Gaming Hub.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpuSetting {
+    Minimum,
+    Medium,
+    Maximum,
+}
+
+impl From<GpuSetting> for GpuPowerLevel {
+    fn from(setting: GpuSetting) -> Self {
+        match setting {
+            GpuSetting::Minimum => Self::Minimum,
+            GpuSetting::Medium => Self::Medium,
+            GpuSetting::Maximum => Self::Maximum,
+        }
+    }
+}
+
+impl ProfilePower {
+    fn cpu_limits(&self) -> [Option<f32>; 3] {
+        [self.pl1_w, self.pl2_w, self.pl4_w]
+    }
+
+    /// The same settings as HP BIOS requests. Watts are rounded; `HpBios` clamps them further.
+    pub fn hp_settings(&self) -> HpPowerSettings {
+        let watts = |limit: Option<f32>| limit.map(|w| w.round().clamp(0.0, f32::from(POWER_LIMIT_UNCHANGED - 1)) as u8);
+        let cpu = CpuPowerLimits {
+            pl1_w: watts(self.pl1_w),
+            pl2_w: watts(self.pl2_w),
+            pl4_w: watts(self.pl4_w),
+        };
+        HpPowerSettings {
+            cpu: (cpu != CpuPowerLimits::default()).then_some(cpu),
+            gpu: self.gpu.map(GpuPowerLevel::from),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ProfilePower {
+    /// The RAPL part of the settings; `None` if they set none of PL1, PL2 or the PL1 window.
+    pub fn rapl_limits(&self) -> Option<crate::rapl::PowerLimits> {
+        let limits = crate::rapl::PowerLimits {
+            pl1_w: self.pl1_w,
+            pl2_w: self.pl2_w,
+            pl1_window_us: self.pl1_window_us,
+        };
+        (limits != crate::rapl::PowerLimits::default()).then_some(limits)
+    }
+}
+
+impl PowerConfig {
+    pub fn from_toml_str(text: &str) -> Result<Self, PowerConfigError> {
+        let config: Self = toml::from_str(text).map_err(|e| PowerConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, PowerConfigError> {
+        let text = fs::read_to_string(path).map_err(|e| PowerConfigError::Read(path.to_path_buf(), e.to_string()))?;
+        Self::from_toml_str(&text)
+    }
+
+    /// `config_path()` if it exists; an empty config otherwise.
+    pub fn load() -> Result<Self, PowerConfigError> {
+        let path = config_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::from_file(&path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    fn validate(&self) -> Result<(), PowerConfigError> {
+        for (key, power) in &self.profiles {
+            if let Some(watts) = power.cpu_limits().into_iter().flatten().find(|w| !w.is_finite() || *w <= 0.0) {
+                return Err(PowerConfigError::Invalid(format!("{}: power limit {} W is not positive", key, watts)));
+            }
+            if power.pl1_window_us == Some(0) {
+                return Err(PowerConfigError::Invalid(format!("{}: PL1 window must be positive", key)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Settings for each profile of `caps`. A key naming no profile is an error, so a typo
+    /// doesn't silently leave a profile without its limits.
+    pub fn resolve(&self, caps: &FanCapabilities) -> Result<Vec<(ProfileId, ProfilePower)>, PowerConfigError> {
+        self.profiles
+            .iter()
+            .map(|(key, power)| {
+                profile_for(caps, key)
+                    .map(|id| (id, *power))
+                    .ok_or_else(|| PowerConfigError::Invalid(format!("no profile named {:?}", key)))
+            })
+            .collect()
+    }
+
+    /// Like `resolve`, for a config shared by every probed driver: tables naming a profile
+    /// `caps` lacks are left out and their keys returned, so one backend's profile names don't
+    /// fail another backend.
+    pub fn resolve_known(&self, caps: &FanCapabilities) -> (Vec<(ProfileId, ProfilePower)>, Vec<String>) {
+        let mut known = Vec::new();
+        let mut skipped = Vec::new();
+        for (key, power) in &self.profiles {
+            match profile_for(caps, key) {
+                Some(id) => known.push((id, *power)),
+                None => skipped.push(key.clone()),
+            }
+        }
+        (known, skipped)
+    }
+}
+
+/// The profile a table key names: by name (case-insensitive) or numeric id.
+fn profile_for(caps: &FanCapabilities, key: &str) -> Option<ProfileId> {
+    caps.profiles
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(key) || key.parse() == Ok(p.id))
+        .map(|p| p.id)
+}
+
+/// Location of `power.toml`: `EDGE_OPTIMIZER_POWER_CONFIG` if set, else
+/// `/etc/edge_optimizer/power.toml` (`%ProgramData%\EdgeOptimizer\power.toml` on Windows).
+pub fn config_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("EDGE_OPTIMIZER_POWER_CONFIG") {
+        return PathBuf::from(path);
+    }
+    #[cfg(windows)]
+    {
+        let base = std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into());
+        PathBuf::from(base).join("EdgeOptimizer").join("power.toml")
+    }
+    #[cfg(not(windows))]
+    {
+        PathBuf::from("/etc/edge_optimizer/power.toml")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::FanProfile;
+
+    fn caps() -> FanCapabilities {
+        let profile = |id, name: &str| FanProfile {
+            id,
+            name: name.into(),
+            is_maximum: id == 4,
+        };
+        FanCapabilities {
+            supported: true,
+            profiles: vec![profile(0, "Default"), profile(3, "Quiet"), profile(4, "Extreme (Max)")],
+        }
+    }
+
+    #[test]
+    fn resolves_names_and_ids() {
+        let config = PowerConfig::from_toml_str(
+            "[profiles.quiet]\npl1_w = 25\npl2_w = 35\ngpu = \"minimum\"\n\n[profiles.4]\ngpu = \"maximum\"\n",
+        )
+        .unwrap();
+        let resolved = config.resolve(&caps()).unwrap();
+        assert_eq!(resolved.len(), 2);
+        let quiet = resolved.iter().find(|(id, _)| *id == 3).unwrap().1.hp_settings();
+        assert_eq!(
+            quiet.cpu,
+            Some(CpuPowerLimits {
+                pl1_w: Some(25),
+                pl2_w: Some(35),
+                pl4_w: None
+            })
+        );
+        assert_eq!(quiet.gpu, Some(GpuPowerLevel::Minimum));
+        let extreme = resolved.iter().find(|(id, _)| *id == 4).unwrap().1.hp_settings();
+        assert_eq!(extreme.cpu, None);
+        assert_eq!(extreme.gpu, Some(GpuPowerLevel::Maximum));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn rapl_limits_take_pl1_pl2_and_window() {
+        let config =
+            PowerConfig::from_toml_str("[profiles.Quiet]\npl1_w = 25\npl1_window_us = 28000000\n\n[profiles.4]\ngpu = \"maximum\"\n")
+                .unwrap();
+        let resolved = config.resolve(&caps()).unwrap();
+        let quiet = resolved.iter().find(|(id, _)| *id == 3).unwrap().1;
+        assert_eq!(
+            quiet.rapl_limits(),
+            Some(crate::rapl::PowerLimits::pl1(25.0).with_pl1_window_us(28_000_000))
+        );
+        let extreme = resolved.iter().find(|(id, _)| *id == 4).unwrap().1;
+        assert_eq!(extreme.rapl_limits(), None);
+    }
+
+    #[test]
+    fn unknown_profile_is_rejected() {
+        let config = PowerConfig::from_toml_str("[profiles.Turbo]\npl1_w = 90\n").unwrap();
+        assert!(matches!(config.resolve(&caps()), Err(PowerConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn resolve_known_skips_unknown_profiles() {
+        let config = PowerConfig::from_toml_str("[profiles.Turbo]\npl1_w = 90\n\n[profiles.Quiet]\npl1_w = 25\n").unwrap();
+        let (known, skipped) = config.resolve_known(&caps());
+        assert_eq!(known.len(), 1);
+        assert_eq!(known[0].0, 3);
+        assert_eq!(skipped, vec!["Turbo".to_string()]);
+    }
+
+    #[test]
+    fn non_positive_limits_are_rejected() {
+        assert!(matches!(
+            PowerConfig::from_toml_str("[profiles.Quiet]\npl1_w = 0\n"),
+            Err(PowerConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            PowerConfig::from_toml_str("[profiles.Quiet]\npl3_w = 10\n"),
+            Err(PowerConfigError::Parse(_))
+        ));
+        assert!(matches!(
+            PowerConfig::from_toml_str("[profiles.Quiet]\npl1_window_us = 0\n"),
+            Err(PowerConfigError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+//! Locale-independent parsing of `powercfg /list` and `powercfg /getactivescheme` output.
+//! Lines are recognised by the scheme GUID they carry, never by the (translated) labels:
+//!
+//! ```text
+//! Power Scheme GUID: 381b4222-f694-41f0-9685-ff5bb260df2e  (Balanced) *
+//! GUID du mode de gestion de l'alimentation : 381b4222-f694-41f0-9685-ff5bb260df2e  (Utilisation normale) *
+//! ```
+
+/// Well-known scheme GUIDs shipped with Windows.
+pub const BALANCED: &str = "381b4222-f694-41f0-9685-ff5bb260df2e";
+pub const HIGH_PERFORMANCE: &str = "8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c";
+pub const POWER_SAVER: &str = "a1841308-3541-4fab-bc81-f71556f20b4a";
+pub const ULTIMATE_PERFORMANCE: &str = "e9a42b02-d5df-448d-aa00-03f14749eb61";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct PowerScheme {
+    /// Lower-case GUID.
+    pub guid: String,
+    /// Display name as printed by powercfg (may be localized).
+    pub name: String,
+    /// Marked with `*` in `/list` output.
+    pub active: bool,
+}
+
+/// Parse every scheme line of `powercfg /list`.
+pub fn parse_list(output: &str) -> Vec<PowerScheme> {
+    output.lines().filter_map(parse_scheme_line).collect()
+}
+
+/// Parse `powercfg /getactivescheme`, which prints a single scheme line.
+pub fn parse_active(output: &str) -> Option<PowerScheme> {
+    output.lines().find_map(parse_scheme_line).map(|scheme| PowerScheme {
+        active: true,
+        ..scheme
+    })
+}
+
+fn parse_scheme_line(line: &str) -> Option<PowerScheme> {
+    let (start, guid) = find_guid(line)?;
+    let rest = &line[start + guid.len()..];
+
+    let name = match (rest.find('('), rest.rfind(')')) {
+        (Some(open), Some(close)) if open < close => rest[open + 1..close].trim().to_string(),
+        _ => String::new(),
+    };
+    let active = rest.trim_end().ends_with('*');
+
+    Some(PowerScheme {
+        guid: guid.to_ascii_lowercase(),
+        name,
+        active,
+    })
+}
+
+/// Locate the first `8-4-4-4-12` hex GUID in `line`, returning its byte offset and text.
+fn find_guid(line: &str) -> Option<(usize, &str)> {
+    const GROUPS: [usize; 5] = [8, 4, 4, 4, 12];
+    const LEN: usize = 36;
+
+    let bytes = line.as_bytes();
+    (0..=bytes.len().checked_sub(LEN)?).find_map(|start| {
+        let candidate = &bytes[start..start + LEN];
+        let mut offset = 0;
+        for (index, group) in GROUPS.iter().enumerate() {
+            if !candidate[offset..offset + group].iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            offset += group;
+            if index < GROUPS.len() - 1 {
+                if candidate[offset] != b'-' {
+                    return None;
+                }
+                offset += 1;
+            }
+        }
+        // Reject matches embedded in a longer hex run.
+        let boundary = |b: Option<&u8>| b.is_none_or(|b| !b.is_ascii_hexdigit());
+        (boundary(start.checked_sub(1).and_then(|i| bytes.get(i))) && boundary(bytes.get(start + LEN)))
+            .then(|| (start, &line[start..start + LEN]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST_EN: &str = "
+Existing Power Schemes (* Active)
+-----------------------------------
+Power Scheme GUID: 381b4222-f694-41f0-9685-ff5bb260df2e  (Balanced)
+Power Scheme GUID: 8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c  (High performance) *
+Power Scheme GUID: a1841308-3541-4fab-bc81-f71556f20b4a  (Power saver)
+";
+
+    const LIST_FR: &str = "
+Modes de gestion de l'alimentation existants (* Actif)
+-----------------------------------
+GUID du mode de gestion de l'alimentation : 381b4222-f694-41f0-9685-ff5bb260df2e  (Utilisation normale) *
+GUID du mode de gestion de l'alimentation : a1841308-3541-4fab-bc81-f71556f20b4a  (Économie d'énergie)
+";
+
+    /// Modern-standby machine: Balanced plus an OEM plan, no High Performance.
+    const LIST_OEM_DE: &str = "
+Vorhandene Energieschemas (* Aktiv)
+-----------------------------------
+GUID des Energieschemas: 381B4222-F694-41F0-9685-FF5BB260DF2E  (Ausbalanciert)
+GUID des Energieschemas: 2a8c7f6b-4e3d-4b1a-9c0f-3e2d1c0b9a87  (HP Optimized (Modern Standby)) *
+";
+
+    #[test]
+    fn parses_english_list() {
+        let schemes = parse_list(LIST_EN);
+        let guids: Vec<&str> = schemes.iter().map(|s| s.guid.as_str()).collect();
+        assert_eq!(guids, [BALANCED, HIGH_PERFORMANCE, POWER_SAVER]);
+        assert_eq!(schemes[1].name, "High performance");
+        assert_eq!(schemes.iter().filter(|s| s.active).count(), 1);
+        assert!(schemes[1].active);
+    }
+
+    #[test]
+    fn parses_translated_list() {
+        let schemes = parse_list(LIST_FR);
+        assert_eq!(schemes.len(), 2);
+        assert_eq!(schemes[0].guid, BALANCED);
+        assert_eq!(schemes[0].name, "Utilisation normale");
+        assert!(schemes[0].active);
+        assert_eq!(schemes[1].name, "Économie d'énergie");
+        assert!(!schemes[1].active);
+    }
+
+    #[test]
+    fn keeps_oem_names_with_parentheses_and_lowercases_guids() {
+        let schemes = parse_list(LIST_OEM_DE);
+        assert_eq!(schemes[0].guid, BALANCED);
+        assert_eq!(schemes[1].guid, "2a8c7f6b-4e3d-4b1a-9c0f-3e2d1c0b9a87");
+        assert_eq!(schemes[1].name, "HP Optimized (Modern Standby)");
+        assert!(schemes[1].active);
+        assert!(!schemes.iter().any(|s| s.guid == HIGH_PERFORMANCE));
+    }
+
+    #[test]
+    fn parses_active_scheme() {
+        let en = parse_active("Power Scheme GUID: 381b4222-f694-41f0-9685-ff5bb260df2e  (Balanced)\n").unwrap();
+        assert_eq!((en.guid.as_str(), en.name.as_str(), en.active), (BALANCED, "Balanced", true));
+
+        let de = parse_active("GUID des Energieschemas: 8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c  (Höchstleistung)").unwrap();
+        assert_eq!(de.guid, HIGH_PERFORMANCE);
+        assert_eq!(de.name, "Höchstleistung");
+        assert!(parse_active("Access is denied.\n").is_none());
+    }
+
+    #[test]
+    fn ignores_lines_without_a_guid() {
+        assert!(parse_list("Existing Power Schemes (* Active)\n-----\n").is_empty());
+        // Too long to be a GUID: part of a longer hex run.
+        assert!(parse_list("0381b4222-f694-41f0-9685-ff5bb260df2e  (x)").is_empty());
+    }
+}
@@ -0,0 +1,455 @@
+//! Intel RAPL package power limits (PL1/PL2) through the powercap sysfs interface:
+//! `/sys/class/powercap/intel-rapl:N/constraint_M_{name,power_limit_uw,time_window_us,max_power_uw}`.
+//! Fan profiles decide how hard the machine is cooled; power limits decide how much heat it makes.
+//! `select` attaches the limits from `power_config` to whichever Linux driver it picks.
+
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanTelemetry, ProfileId};
+use crate::sysfs;
+
+/// File in the state directory holding the limits found before the first change.
+const SNAPSHOT_FILE: &str = "rapl_original";
+
+/// Requests below this are raised to it; a few watts is enough to make a package unusable.
+pub const MIN_POWER_LIMIT_UW: u64 = 5_000_000;
+
+/// Which RAPL constraint a limit applies to, identified by `constraint_M_name`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerLimitKind {
+    /// PL1, sustained (`long_term`).
+    LongTerm,
+    /// PL2, turbo (`short_term`).
+    ShortTerm,
+    /// PL4 (`peak_power`); read for diagnostics but never written.
+    Peak,
+}
+
+impl PowerLimitKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "long_term" => Some(Self::LongTerm),
+            "short_term" => Some(Self::ShortTerm),
+            "peak_power" => Some(Self::Peak),
+            _ => None,
+        }
+    }
+}
+
+/// One `constraint_M_*` group of a zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RaplConstraint {
+    pub index: u32,
+    pub kind: Option<PowerLimitKind>,
+    pub power_limit_uw: u64,
+    pub time_window_us: Option<u64>,
+    /// Upper bound advertised by firmware; `None` if absent or zero.
+    pub max_power_uw: Option<u64>,
+}
+
+/// A package-level `intel-rapl:N` zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RaplZone {
+    pub path: PathBuf,
+    /// Zone `name`, e.g. "package-0".
+    pub name: String,
+    pub constraints: Vec<RaplConstraint>,
+}
+
+impl RaplZone {
+    pub fn constraint(&self, kind: PowerLimitKind) -> Option<&RaplConstraint> {
+        self.constraints.iter().find(|c| c.kind == Some(kind))
+    }
+}
+
+/// Limits a profile can attach. Unset fields leave the current value alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PowerLimits {
+    pub pl1_w: Option<f32>,
+    pub pl2_w: Option<f32>,
+    /// PL1 averaging window.
+    pub pl1_window_us: Option<u64>,
+}
+
+impl PowerLimits {
+    pub fn pl1(watts: f32) -> Self {
+        Self {
+            pl1_w: Some(watts),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_pl2(mut self, watts: f32) -> Self {
+        self.pl2_w = Some(watts);
+        self
+    }
+
+    pub fn with_pl1_window_us(mut self, window_us: u64) -> Self {
+        self.pl1_window_us = Some(window_us);
+        self
+    }
+}
+
+/// All package zones found under `class/powercap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rapl {
+    pub zones: Vec<RaplZone>,
+}
+
+impl Rapl {
+    /// Discover zones in the live `/sys` tree.
+    pub fn new() -> Result<Self, DriverError> {
+        Self::probe(Path::new("/sys"))
+    }
+
+    /// Discover zones in a sysfs tree rooted at `root`.
+    pub fn probe(root: &Path) -> Result<Self, DriverError> {
+        let powercap = root.join("class/powercap");
+        // `intel-rapl:N` only; subzones (`intel-rapl:N:M`) are core/uncore/dram and don't parse.
+        let zones: Vec<RaplZone> = sysfs::numbered_entries(&powercap, "intel-rapl:")
+            .into_iter()
+            .filter_map(|(_, path)| read_zone(path))
+            .collect();
+        if zones.is_empty() {
+            return Err(DriverError::NotReady(format!(
+                "no intel-rapl power limits under {}",
+                powercap.display()
+            )));
+        }
+        Ok(Self { zones })
+    }
+
+    /// Re-read every zone, e.g. to take a snapshot before applying limits.
+    pub fn refresh(&self) -> Self {
+        Self {
+            zones: self.zones.iter().filter_map(|z| read_zone(z.path.clone())).collect(),
+        }
+    }
+
+    /// Write `limits` to every package zone, clamped to each constraint's safe range.
+    pub fn apply(&self, limits: &PowerLimits) -> Result<(), DriverError> {
+        write_all(self.limit_writes(limits))
+    }
+
+    /// Attribute writes `apply(limits)` makes, with each limit already clamped.
+    pub fn limit_writes(&self, limits: &PowerLimits) -> Vec<(PathBuf, String)> {
+        let mut writes = Vec::new();
+        for zone in &self.zones {
+            for (kind, watts) in [
+                (PowerLimitKind::LongTerm, limits.pl1_w),
+                (PowerLimitKind::ShortTerm, limits.pl2_w),
+            ] {
+                let (Some(watts), Some(constraint)) = (watts, zone.constraint(kind)) else {
+                    continue;
+                };
+                let requested = (watts.max(0.0) * 1_000_000.0) as u64;
+                let value = clamp_limit(constraint, requested);
+                writes.push((constraint_path(zone, constraint, "power_limit_uw"), value.to_string()));
+            }
+
+            if let (Some(window), Some(constraint)) = (limits.pl1_window_us, zone.constraint(PowerLimitKind::LongTerm)) {
+                if constraint.time_window_us.is_some() {
+                    writes.push((constraint_path(zone, constraint, "time_window_us"), window.to_string()));
+                }
+            }
+        }
+        writes
+    }
+
+    /// Write back the PL1/PL2 limits and windows recorded in `snapshot`.
+    pub fn restore(&self, snapshot: &Rapl) -> Result<(), DriverError> {
+        write_all(self.restore_writes(snapshot))
+    }
+
+    /// Attribute writes `restore(snapshot)` makes.
+    pub fn restore_writes(&self, snapshot: &Rapl) -> Vec<(PathBuf, String)> {
+        let mut writes = Vec::new();
+        for zone in &snapshot.zones {
+            for constraint in &zone.constraints {
+                if !matches!(constraint.kind, Some(PowerLimitKind::LongTerm | PowerLimitKind::ShortTerm)) {
+                    continue;
+                }
+                writes.push((
+                    constraint_path(zone, constraint, "power_limit_uw"),
+                    constraint.power_limit_uw.to_string(),
+                ));
+                if let Some(window) = constraint.time_window_us {
+                    writes.push((constraint_path(zone, constraint, "time_window_us"), window.to_string()));
+                }
+            }
+        }
+        writes
+    }
+}
+
+fn write_all(writes: Vec<(PathBuf, String)>) -> Result<(), DriverError> {
+    for (path, value) in writes {
+        sysfs::write_value(&path, &value)?;
+    }
+    Ok(())
+}
+
+/// Keep a request within `[MIN_POWER_LIMIT_UW, max_power_uw]`. Without a firmware maximum,
+/// never raise the limit above its current value.
+pub fn clamp_limit(constraint: &RaplConstraint, requested_uw: u64) -> u64 {
+    let upper = constraint.max_power_uw.unwrap_or(constraint.power_limit_uw);
+    requested_uw.min(upper).max(MIN_POWER_LIMIT_UW.min(upper))
+}
+
+fn constraint_path(zone: &RaplZone, constraint: &RaplConstraint, attribute: &str) -> PathBuf {
+    zone.path.join(format!("constraint_{}_{}", constraint.index, attribute))
+}
+
+fn read_zone(path: PathBuf) -> Option<RaplZone> {
+    let name = sysfs::read_optional(&path.join("name")).unwrap_or_default();
+    let constraints: Vec<RaplConstraint> = sysfs::attribute_indices(&path, "constraint_", "_power_limit_uw")
+        .into_iter()
+        .filter_map(|index| {
+            let attr = |suffix: &str| path.join(format!("constraint_{}_{}", index, suffix));
+            let read_u64 = |suffix: &str| sysfs::read_i64(&attr(suffix)).ok().and_then(|v| u64::try_from(v).ok());
+            Some(RaplConstraint {
+                index,
+                kind: sysfs::read_optional(&attr("name")).and_then(|n| PowerLimitKind::from_name(&n)),
+                power_limit_uw: read_u64("power_limit_uw")?,
+                time_window_us: read_u64("time_window_us"),
+                max_power_uw: read_u64("max_power_uw").filter(|max| *max > 0),
+            })
+        })
+        .collect();
+    (!constraints.is_empty()).then_some(RaplZone { path, name, constraints })
+}
+
+/// Wraps a driver and applies the power limits attached to each profile after switching it.
+/// Profiles without limits put back the values found before the first change: those saved in
+/// the state directory by an earlier run, else those found when the wrapper was created.
+pub struct PowerLimitedDriver<D> {
+    inner: D,
+    rapl: Rapl,
+    limits: Vec<(ProfileId, PowerLimits)>,
+    /// Writes that put back the original PL1/PL2 limits and windows.
+    original: Vec<(PathBuf, String)>,
+    state_dir: Option<PathBuf>,
+}
+
+impl<D: FanDriver> PowerLimitedDriver<D> {
+    pub fn new(inner: D, rapl: Rapl) -> Self {
+        Self {
+            original: rapl.restore_writes(&rapl),
+            inner,
+            rapl,
+            limits: Vec::new(),
+            state_dir: None,
+        }
+    }
+
+    /// Keep the original limits in `dir` while profile limits are applied, so a later run can
+    /// still put them back. Limits saved there by an earlier run replace the ones just read.
+    pub fn with_state_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        if let Some(saved) = std::fs::read_to_string(dir.join(SNAPSHOT_FILE)).ok().and_then(|t| parse_writes(&t)) {
+            self.original = saved;
+        }
+        self.state_dir = Some(dir);
+        self
+    }
+
+    /// Attach limits to `profile`, e.g. `.with_limits(QUIET, PowerLimits::pl1(25.0))`.
+    pub fn with_limits(mut self, profile: ProfileId, limits: PowerLimits) -> Self {
+        self.limits.retain(|(id, _)| *id != profile);
+        self.limits.push((profile, limits));
+        self
+    }
+
+    pub fn limits_for(&self, profile: ProfileId) -> Option<&PowerLimits> {
+        self.limits.iter().find(|(id, _)| *id == profile).map(|(_, limits)| limits)
+    }
+
+    /// Writes that put back the limits found before the first change.
+    pub fn original(&self) -> &[(PathBuf, String)] {
+        &self.original
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    fn snapshot_path(&self) -> Option<PathBuf> {
+        self.state_dir.as_ref().map(|dir| dir.join(SNAPSHOT_FILE))
+    }
+
+    /// Save the original limits unless an earlier change already did.
+    fn save_original(&self) -> Result<(), DriverError> {
+        let Some(path) = self.snapshot_path().filter(|path| !path.exists()) else {
+            return Ok(());
+        };
+        let text: String = self
+            .original
+            .iter()
+            .map(|(path, value)| format!("{}\t{}\n", path.display(), value))
+            .collect();
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, text))
+            .map_err(|e| DriverError::Io(format!("saving original RAPL limits to {}: {}", path.display(), e)))
+    }
+
+    /// Put the original limits back; once they are, the saved copy is no longer needed.
+    fn restore_original(&self) -> Result<(), DriverError> {
+        write_all(self.original.clone())?;
+        if let Some(path) = self.snapshot_path().filter(|path| path.exists()) {
+            std::fs::remove_file(&path).map_err(|e| DriverError::Io(format!("{}: {}", path.display(), e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// `path<TAB>value` lines as written by `save_original`; `None` if any line is malformed.
+fn parse_writes(text: &str) -> Option<Vec<(PathBuf, String)>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split_once('\t').map(|(path, value)| (PathBuf::from(path), value.to_string())))
+        .collect()
+}
+
+impl<D: FanDriver> FanDriver for PowerLimitedDriver<D> {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        self.inner.capabilities()
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        self.inner.current_profile()
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        self.inner.set_profile(profile)?;
+        match self.limits_for(profile) {
+            Some(limits) => {
+                self.save_original()?;
+                self.rapl.apply(limits)
+            }
+            None => self.restore_original(),
+        }
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let explanation = self.inner.explain_profile(profile)?;
+        Ok(match self.limits_for(profile) {
+            Some(limits) => explanation.writes(self.rapl.limit_writes(limits)),
+            None => explanation
+                .note("no power limits attached to this profile: restoring the original RAPL limits")
+                .writes(self.original.clone()),
+        })
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        self.inner.telemetry()
+    }
+
+    fn diagnose(&self) -> Vec<String> {
+        self.inner.diagnose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Fixture, StubFan};
+
+    const ZONE: &str = "class/powercap/intel-rapl:0";
+
+    /// One package zone: PL1 45 W over 28 s (firmware maximum 65 W), PL2 65 W without a
+    /// maximum, and PL4; plus a core subzone that must be skipped.
+    fn powercap_tree() -> Fixture {
+        let fixture = Fixture::new();
+        fixture
+            .write(&format!("{}/name", ZONE), "package-0\n")
+            .write(&format!("{}/constraint_0_name", ZONE), "long_term\n")
+            .write(&format!("{}/constraint_0_power_limit_uw", ZONE), "45000000\n")
+            .write(&format!("{}/constraint_0_time_window_us", ZONE), "27983872\n")
+            .write(&format!("{}/constraint_0_max_power_uw", ZONE), "65000000\n")
+            .write(&format!("{}/constraint_1_name", ZONE), "short_term\n")
+            .write(&format!("{}/constraint_1_power_limit_uw", ZONE), "65000000\n")
+            .write(&format!("{}/constraint_1_time_window_us", ZONE), "2440\n")
+            .write(&format!("{}/constraint_1_max_power_uw", ZONE), "0\n")
+            .write(&format!("{}/constraint_2_name", ZONE), "peak_power\n")
+            .write(&format!("{}/constraint_2_power_limit_uw", ZONE), "121000000\n")
+            .write("class/powercap/intel-rapl:0:0/name", "core\n")
+            .write("class/powercap/intel-rapl:0:0/constraint_0_power_limit_uw", "0\n");
+        fixture
+    }
+
+    fn limit(fixture: &Fixture, constraint: u32) -> String {
+        fixture.read(&format!("{}/constraint_{}_power_limit_uw", ZONE, constraint))
+    }
+
+    #[test]
+    fn probe_reads_package_zones_only() {
+        let fixture = powercap_tree();
+        let rapl = Rapl::probe(fixture.root()).unwrap();
+        assert_eq!(rapl.zones.len(), 1);
+        let zone = &rapl.zones[0];
+        assert_eq!(zone.name, "package-0");
+        let pl1 = zone.constraint(PowerLimitKind::LongTerm).unwrap();
+        assert_eq!((pl1.power_limit_uw, pl1.max_power_uw), (45_000_000, Some(65_000_000)));
+        assert_eq!(zone.constraint(PowerLimitKind::ShortTerm).unwrap().max_power_uw, None);
+        assert!(zone.constraint(PowerLimitKind::Peak).is_some());
+
+        assert!(matches!(Rapl::probe(Fixture::new().root()), Err(DriverError::NotReady(_))));
+    }
+
+    #[test]
+    fn limits_are_clamped_to_the_safe_range() {
+        let fixture = powercap_tree();
+        let rapl = Rapl::probe(fixture.root()).unwrap();
+
+        // PL1 stops at the firmware maximum; PL2 has none, so it may not rise at all.
+        rapl.apply(&PowerLimits::pl1(90.0).with_pl2(100.0)).unwrap();
+        assert_eq!(limit(&fixture, 0), "65000000");
+        assert_eq!(limit(&fixture, 1), "65000000");
+
+        rapl.apply(&PowerLimits::pl1(1.0).with_pl2(30.0).with_pl1_window_us(1_000_000)).unwrap();
+        assert_eq!(limit(&fixture, 0), MIN_POWER_LIMIT_UW.to_string());
+        assert_eq!(limit(&fixture, 1), "30000000");
+        assert_eq!(fixture.read(&format!("{}/constraint_0_time_window_us", ZONE)), "1000000");
+        assert_eq!(limit(&fixture, 2), "121000000", "PL4 is never written");
+    }
+
+    #[test]
+    fn profiles_without_limits_restore_the_originals() {
+        let fixture = powercap_tree();
+        let driver = PowerLimitedDriver::new(StubFan::default(), Rapl::probe(fixture.root()).unwrap())
+            .with_limits(1, PowerLimits::pl1(25.0).with_pl2(35.0));
+
+        driver.set_profile(1).unwrap();
+        assert_eq!(driver.inner().0.get(), Some(1));
+        assert_eq!((limit(&fixture, 0), limit(&fixture, 1)), ("25000000".into(), "35000000".into()));
+
+        assert!(!driver.explain_profile(0).unwrap().notes.is_empty());
+        driver.set_profile(0).unwrap();
+        assert_eq!((limit(&fixture, 0), limit(&fixture, 1)), ("45000000".into(), "65000000".into()));
+        assert_eq!(fixture.read(&format!("{}/constraint_0_time_window_us", ZONE)), "27983872");
+    }
+
+    #[test]
+    fn originals_survive_until_restored_in_a_later_run() {
+        let fixture = powercap_tree();
+        let state = fixture.path("state");
+        let quiet = PowerLimits::pl1(25.0);
+        let first = PowerLimitedDriver::new(StubFan::default(), Rapl::probe(fixture.root()).unwrap())
+            .with_state_dir(&state)
+            .with_limits(1, quiet);
+        first.set_profile(1).unwrap();
+        assert!(state.join(SNAPSHOT_FILE).exists());
+
+        // The next run finds 25 W in sysfs, but must still restore 45 W.
+        let later = PowerLimitedDriver::new(StubFan::default(), Rapl::probe(fixture.root()).unwrap())
+            .with_state_dir(&state)
+            .with_limits(1, quiet);
+        assert_eq!(later.original(), first.original());
+        later.set_profile(0).unwrap();
+        assert_eq!(limit(&fixture, 0), "45000000");
+        assert!(!state.join(SNAPSHOT_FILE).exists());
+    }
+}
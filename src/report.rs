@@ -0,0 +1,70 @@
+//! Versioned JSON documents for scripts and status-bar widgets.
+//!
+//! Every document has the same envelope; `data` holds the command-specific payload and `error`
+//! is present only when the command failed:
+//!
+//! ```text
+//! {"schema_version": 1, "command": "get", "generated_at_unix_ms": 1760000000000, "data": {...}}
+//! ```
+
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::driver::DriverError;
+
+/// Bumped whenever a field is removed or changes meaning; adding fields keeps the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct JsonDocument<T> {
+    pub schema_version: u32,
+    /// Command (or report kind) that produced the document, e.g. "list" or "monitor".
+    pub command: String,
+    #[serde(rename = "generated_at_unix_ms", serialize_with = "crate::model::serialize_unix_ms")]
+    pub generated_at: SystemTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<DriverError>,
+}
+
+impl<T: Serialize> JsonDocument<T> {
+    /// Successful result of `command`.
+    pub fn new(command: impl Into<String>, data: T) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            command: command.into(),
+            generated_at: SystemTime::now(),
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    /// Failed `command`, without a payload.
+    pub fn failed(command: impl Into<String>, error: DriverError) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            command: command.into(),
+            generated_at: SystemTime::now(),
+            data: None,
+            error: Some(error),
+        }
+    }
+
+    /// Attach the error that ended the command while keeping partial data (e.g. probe results).
+    pub fn with_error(mut self, error: DriverError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Indented JSON, for one-shot commands.
+    pub fn to_pretty_string(&self) -> String {
+        serde_json::to_string_pretty(self).expect("documents contain only JSON-representable values")
+    }
+
+    /// Single-line JSON, for streams such as `monitor` (one document per line).
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).expect("documents contain only JSON-representable values")
+    }
+}
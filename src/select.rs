@@ -0,0 +1,531 @@
+//! Runtime driver selection: probes the platform's candidate drivers in priority order.
+
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::config_driver::{self, ConfigDriver, DriverConfig};
+use crate::driver::{DriverError, FanDriver};
+use crate::exec_driver::{ExecConfig, ExecDriver};
+use crate::native_lib::{NativeLibConfig, NativeLibDriver};
+use crate::power_config::PowerConfig;
+
+/// Constructor that probes the hardware and returns a ready driver.
+pub type ProbeFn = Box<dyn Fn() -> Result<Box<dyn FanDriver>, DriverError>>;
+
+/// A driver known to this build (or declared by a config file), with its probe.
+pub struct DriverCandidate {
+    /// Stable identifier, e.g. "thinkpad", "hp-wmi" or "config:rpi5".
+    pub name: String,
+    /// Human-friendly description used in status output.
+    pub description: String,
+    pub probe: ProbeFn,
+}
+
+impl DriverCandidate {
+    fn builtin(
+        name: &str,
+        description: &str,
+        probe: impl Fn() -> Result<Box<dyn FanDriver>, DriverError> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            probe: Box::new(probe),
+        }
+    }
+}
+
+/// Outcome of probing one candidate.
+#[derive(Debug)]
+pub struct ProbeAttempt {
+    pub name: String,
+    pub description: String,
+    /// `Ok` if the driver initialized; otherwise why it was skipped.
+    pub result: Result<(), DriverError>,
+    /// `FanDriver::diagnose` of a driver that initialized in `probe_all`.
+    pub details: Vec<String>,
+}
+
+/// Serialized as `{"name", "description", "ok", "error", "details"}`, with `error` null on success.
+#[cfg(feature = "serialize")]
+impl serde::Serialize for ProbeAttempt {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("ProbeAttempt", 5)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("description", &self.description)?;
+        state.serialize_field("ok", &self.result.is_ok())?;
+        state.serialize_field("error", &self.result.as_ref().err())?;
+        state.serialize_field("details", &self.details)?;
+        state.end()
+    }
+}
+
+/// Probe outcomes in the order candidates were tried.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct ProbeReport {
+    pub attempts: Vec<ProbeAttempt>,
+    /// Why `power.toml` could not be loaded. Reported once here; the drivers were probed
+    /// without power settings.
+    pub power_config_error: Option<DriverError>,
+}
+
+impl ProbeReport {
+    /// Dry-run notes naming every probe that ran: picking a driver initializes the candidates
+    /// tried before it (reading sysfs, loading libraries, connecting to services or starting
+    /// helpers), so a dry run is only free of profile writes, not of probing.
+    pub fn notes(&self) -> Vec<String> {
+        let power = self.power_config_error.iter().map(|e| format!("power.toml ignored: {}", e));
+        self.attempts
+            .iter()
+            .map(|attempt| match &attempt.result {
+                Ok(()) => format!("Probed {}: initialized and selected", attempt.name),
+                Err(e) => format!("Probed {}: skipped ({})", attempt.name, e),
+            })
+            .chain(power)
+            .collect()
+    }
+}
+
+/// The driver picked by `select_driver`.
+pub struct SelectedDriver {
+    pub name: String,
+    pub description: String,
+    pub driver: Box<dyn FanDriver>,
+}
+
+/// Build the driver a `drivers.d` file declares: an `[exec]` helper, a `[native]` library or a
+/// sysfs mapping.
+fn config_file_driver(path: &Path) -> Result<Box<dyn FanDriver>, DriverError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| DriverError::Io(format!("read {}: {}", path.display(), e)))?;
+    if ExecConfig::is_exec_config(&text) {
+        Ok(Box::new(ExecDriver::new(ExecConfig::from_toml_str(&text)?)?))
+    } else if NativeLibConfig::is_native_config(&text) {
+        Ok(Box::new(NativeLibDriver::new(NativeLibConfig::from_toml_str(&text)?)?))
+    } else {
+        Ok(Box::new(ConfigDriver::new(DriverConfig::from_toml_str(&text)?)?))
+    }
+}
+
+/// Named pipe of the HP Omen HSA service; the endpoint is not discovered automatically yet.
+#[cfg(windows)]
+const HP_RPC_PIPE_ENV: &str = "EDGE_OPTIMIZER_HP_RPC_PIPE";
+
+/// HP system design data and fans from the BIOS, used to offer only the thermal profiles the
+/// board runs. `None` where the `hpqBIntM` interface is missing or the query fails.
+#[cfg(windows)]
+fn hp_board_info() -> Option<crate::hp_system::HpBoardInfo> {
+    let wmi = crate::hp_wmi::HpWmiDriver::new().ok()?;
+    let bios = wmi.bios();
+    bios.board_info().ok()
+}
+
+/// Attach the CPU and GPU power settings from `power.toml` to an HP driver's profiles. Without
+/// settings for its profiles the driver is returned as is.
+#[cfg(windows)]
+fn with_hp_power(driver: Box<dyn FanDriver>, config: &PowerConfig) -> Result<Box<dyn FanDriver>, DriverError> {
+    if config.is_empty() {
+        return Ok(driver);
+    }
+    let (settings, skipped) = config.resolve_known(&driver.capabilities()?);
+    let driver = with_notes(driver, skipped_profile_notes(&skipped));
+    if settings.is_empty() {
+        return Ok(driver);
+    }
+    let bios = crate::hp_bios::HpBios::new(crate::hp_wmi::HpWmiDriver::new()?);
+    Ok(Box::new(settings.into_iter().fold(
+        crate::hp_bios::HpPowerDriver::new(driver, bios),
+        |driver, (profile, power)| driver.with_power(profile, power.hp_settings()),
+    )))
+}
+
+/// Attach the RAPL limits from `power.toml` to a Linux driver's profiles. Without limits for its
+/// profiles, or on machines without `intel-rapl` zones, the driver is returned as is.
+#[cfg(target_os = "linux")]
+fn with_rapl(driver: Box<dyn FanDriver>, config: &PowerConfig) -> Result<Box<dyn FanDriver>, DriverError> {
+    if config.is_empty() {
+        return Ok(driver);
+    }
+    let (settings, skipped) = config.resolve_known(&driver.capabilities()?);
+    let driver = with_notes(driver, skipped_profile_notes(&skipped));
+    let limits: Vec<_> = settings
+        .into_iter()
+        .filter_map(|(profile, power)| Some((profile, power.rapl_limits()?)))
+        .collect();
+    if limits.is_empty() {
+        return Ok(driver);
+    }
+    let Ok(rapl) = crate::rapl::Rapl::new() else {
+        return Ok(driver);
+    };
+    let mut limited = limits
+        .into_iter()
+        .fold(crate::rapl::PowerLimitedDriver::new(driver, rapl), |driver, (profile, limits)| {
+            driver.with_limits(profile, limits)
+        });
+    if let Some(dir) = crate::state::state_dir() {
+        limited = limited.with_state_dir(dir);
+    }
+    Ok(Box::new(limited))
+}
+
+/// `power.toml` tables a driver has no profile for. They belong to another backend, so they are
+/// listed rather than failing the probe.
+#[cfg(any(windows, target_os = "linux"))]
+fn skipped_profile_notes(keys: &[String]) -> Vec<String> {
+    keys.iter()
+        .map(|key| format!("power.toml: no profile {:?} here, its settings are skipped", key))
+        .collect()
+}
+
+/// `driver` with `notes` added to its `diagnose` lines.
+#[cfg(any(windows, target_os = "linux"))]
+fn with_notes(driver: Box<dyn FanDriver>, notes: Vec<String>) -> Box<dyn FanDriver> {
+    if notes.is_empty() {
+        return driver;
+    }
+    Box::new(NotedDriver { inner: driver, notes })
+}
+
+/// A probed driver plus facts selection learned about it, reported by `diagnose`.
+#[cfg(any(windows, target_os = "linux"))]
+struct NotedDriver {
+    inner: Box<dyn FanDriver>,
+    notes: Vec<String>,
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+impl FanDriver for NotedDriver {
+    fn capabilities(&self) -> Result<crate::model::FanCapabilities, DriverError> {
+        self.inner.capabilities()
+    }
+
+    fn current_profile(&self) -> Result<Option<crate::model::ProfileId>, DriverError> {
+        self.inner.current_profile()
+    }
+
+    fn set_profile(&self, profile: crate::model::ProfileId) -> Result<(), DriverError> {
+        self.inner.set_profile(profile)
+    }
+
+    fn telemetry(&self) -> Result<crate::model::FanTelemetry, DriverError> {
+        self.inner.telemetry()
+    }
+
+    fn explain_profile(&self, profile: crate::model::ProfileId) -> Result<crate::explain::Explanation, DriverError> {
+        self.inner.explain_profile(profile)
+    }
+
+    fn diagnose(&self) -> Vec<String> {
+        let mut lines = self.inner.diagnose();
+        lines.extend(self.notes.iter().cloned());
+        lines
+    }
+}
+
+/// `power.toml`, loaded once per selection and shared by every probe. A config that can't be
+/// read or parsed is reported once, next to an empty config the probes can go on with.
+fn load_power_config() -> (Rc<PowerConfig>, Option<DriverError>) {
+    match PowerConfig::load() {
+        Ok(config) => (Rc::new(config), None),
+        Err(e) => (Rc::default(), Some(e.into())),
+    }
+}
+
+/// Tint a four-zone HP keyboard with the color attached to each profile. Boards without such a
+/// keyboard, or whose BIOS won't say, keep the driver as is.
+#[cfg(windows)]
+fn with_profile_colors(
+    driver: Box<dyn FanDriver>,
+    colors: Vec<(crate::model::ProfileId, crate::hp_lighting::Rgb)>,
+) -> Box<dyn FanDriver> {
+    let Ok(wmi) = crate::hp_wmi::HpWmiDriver::new() else {
+        return driver;
+    };
+    let lighting = crate::hp_lighting::HpLighting::new(wmi);
+    if !lighting.keyboard_type().is_ok_and(|keyboard| keyboard.has_four_zones()) {
+        return driver;
+    }
+    Box::new(crate::hp_lighting::ProfileColorDriver::new(driver, lighting, colors))
+}
+
+/// Candidate drivers for this platform, most specific first.
+/// Config-file, helper and native-library drivers from `config_driver::config_dir()` come before the built-in ones.
+/// An unreadable `power.toml` is ignored here; `select_driver` and `probe_all` report it.
+pub fn candidates() -> Vec<DriverCandidate> {
+    candidates_with(load_power_config().0)
+}
+
+/// `candidates`, with the built-in drivers' probes sharing `power`.
+#[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
+fn candidates_with(power: Rc<PowerConfig>) -> Vec<DriverCandidate> {
+    let mut list: Vec<DriverCandidate> = config_driver::config_files(&config_driver::config_dir())
+        .into_iter()
+        .map(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            DriverCandidate {
+                name: format!("config:{}", stem),
+                description: format!("config driver {}", path.display()),
+                probe: Box::new(move || config_file_driver(&path)),
+            }
+        })
+        .collect();
+
+    #[cfg(windows)]
+    {
+        list.push(DriverCandidate::builtin(
+            "hp-wmi",
+            "HP WMI BIOS thermal control",
+            {
+                let power = Rc::clone(&power);
+                move || {
+                    // Profile 1 is the maximum fan profile, tinted like Extreme.
+                    let colors = vec![
+                        (0, crate::hp::HpThermalProfile::Default.color()),
+                        (1, crate::hp::HpThermalProfile::Extreme.color()),
+                    ];
+                    let driver = with_hp_power(Box::new(crate::hp_wmi::HpWmiDriver::new()?), &power)?;
+                    Ok(with_profile_colors(driver, colors))
+                }
+            },
+        ));
+        list.push(DriverCandidate::builtin(
+            "hp-rpc",
+            "HP Omen HSA service (JSON-RPC)",
+            {
+                let power = Rc::clone(&power);
+                move || {
+                    let pipe = std::env::var(HP_RPC_PIPE_ENV).map_err(|_| {
+                        DriverError::NotReady(format!("set {} to the HSA service pipe path", HP_RPC_PIPE_ENV))
+                    })?;
+                    let client = crate::hp_rpc::connect_pipe(&pipe)?;
+                    let board = hp_board_info();
+                    let driver = crate::hp_rpc::HpRpcDriver::new(
+                        client,
+                        crate::hp::HpThermalProfile::supported_profiles(board.as_ref()),
+                    );
+                    let driver = with_hp_power(Box::new(driver), &power)?;
+                    Ok(with_profile_colors(driver, crate::hp::HpThermalProfile::profile_colors()))
+                }
+            },
+        ));
+        list.push(DriverCandidate::builtin(
+            "hp-omen",
+            "HP thermal control (NativeRpcClient.dll)",
+            {
+                let power = Rc::clone(&power);
+                move || {
+                    let driver = crate::hp::HpOmenDriver::new().map_err(DriverError::from)?;
+                    let driver = match hp_board_info() {
+                        Some(board) => driver.with_board_info(board),
+                        None => driver,
+                    };
+                    let driver = with_hp_power(Box::new(driver), &power)?;
+                    Ok(with_profile_colors(driver, crate::hp::HpThermalProfile::profile_colors()))
+                }
+            },
+        ));
+        list.push(DriverCandidate::builtin(
+            "windows-power",
+            "Windows power plans",
+            || Ok(Box::new(crate::windows_power::WindowsPowerPlanDriver::new()?)),
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let with_rapl_limits = |probe: fn() -> Result<Box<dyn FanDriver>, DriverError>| {
+            let power = Rc::clone(&power);
+            move || with_rapl(probe()?, &power)
+        };
+        list.push(DriverCandidate::builtin(
+            "thinkpad",
+            "ThinkPad fan control (thinkpad_acpi)",
+            with_rapl_limits(|| Ok(Box::new(crate::thinkpad::ThinkpadFanDriver::new()?))),
+        ));
+        list.push(DriverCandidate::builtin(
+            "asus-wmi",
+            "ASUS fan control (asus-wmi)",
+            with_rapl_limits(|| Ok(Box::new(crate::asus::AsusWmiDriver::new()?))),
+        ));
+        list.push(DriverCandidate::builtin(
+            "dell-smm",
+            "Dell fan control (dell-smm-hwmon)",
+            with_rapl_limits(|| Ok(Box::new(crate::dell::DellSmmDriver::new()?))),
+        ));
+        list.push(DriverCandidate::builtin(
+            "lenovo",
+            "Lenovo IdeaPad/Legion fan control",
+            with_rapl_limits(|| Ok(Box::new(crate::lenovo::LenovoDriver::new()?))),
+        ));
+        list.push(DriverCandidate::builtin(
+            "msi-ec",
+            "MSI fan control (msi-ec)",
+            with_rapl_limits(|| Ok(Box::new(crate::msi::MsiEcDriver::new()?))),
+        ));
+        // Generic fallback: any board with a fan cooling device.
+        list.push(DriverCandidate::builtin(
+            "cooling-device",
+            "Linux thermal cooling device",
+            with_rapl_limits(|| Ok(Box::new(crate::cooling_device::CoolingDeviceDriver::new()?))),
+        ));
+        // Last resort, like Windows power plans: no fan control, only CPU power policy.
+        list.push(DriverCandidate::builtin(
+            "cpufreq",
+            "Linux cpufreq governor/EPP",
+            with_rapl_limits(|| Ok(Box::new(crate::cpufreq::CpufreqDriver::new()?))),
+        ));
+    }
+
+    list
+}
+
+/// Probe candidates in order and return the first that initializes, plus the report of every
+/// attempt made. `only` restricts probing to the candidate with that name.
+pub fn select_driver(only: Option<&str>) -> (Option<SelectedDriver>, ProbeReport) {
+    let (power, power_config_error) = load_power_config();
+    let mut report = ProbeReport {
+        attempts: Vec::new(),
+        power_config_error,
+    };
+
+    for candidate in candidates_with(power) {
+        if only.is_some_and(|name| name != candidate.name.as_str()) {
+            continue;
+        }
+        match (candidate.probe)() {
+            Ok(driver) => {
+                report.attempts.push(ProbeAttempt {
+                    name: candidate.name.clone(),
+                    description: candidate.description.clone(),
+                    result: Ok(()),
+                    details: Vec::new(),
+                });
+                let selected = SelectedDriver {
+                    name: candidate.name,
+                    description: candidate.description,
+                    driver,
+                };
+                return (Some(selected), report);
+            }
+            Err(e) => report.attempts.push(ProbeAttempt {
+                name: candidate.name,
+                description: candidate.description,
+                result: Err(e),
+                details: Vec::new(),
+            }),
+        }
+    }
+
+    (None, report)
+}
+
+/// Probe every candidate (or only `only`) without stopping at the first success, for listing
+/// which drivers this machine supports. Drivers that initialize report their `diagnose` lines
+/// and are dropped again.
+pub fn probe_all(only: Option<&str>) -> ProbeReport {
+    let (power, power_config_error) = load_power_config();
+    let attempts = candidates_with(power)
+        .into_iter()
+        .filter(|candidate| only.is_none_or(|name| name == candidate.name.as_str()))
+        .map(|candidate| {
+            let (result, details) = match (candidate.probe)() {
+                Ok(driver) => (Ok(()), driver.diagnose()),
+                Err(e) => (Err(e), Vec::new()),
+            };
+            ProbeAttempt {
+                name: candidate.name,
+                description: candidate.description,
+                result,
+                details,
+            }
+        })
+        .collect();
+    ProbeReport {
+        attempts,
+        power_config_error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(name: &str, result: Result<(), DriverError>) -> ProbeAttempt {
+        ProbeAttempt {
+            name: name.into(),
+            description: String::new(),
+            result,
+            details: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn notes_name_every_probe_in_order() {
+        let report = ProbeReport {
+            attempts: vec![
+                attempt("thinkpad", Err(DriverError::NotReady("no thinkpad_acpi".into()))),
+                attempt("cpufreq", Ok(())),
+            ],
+            power_config_error: None,
+        };
+
+        assert_eq!(
+            report.notes(),
+            vec![
+                "Probed thinkpad: skipped (driver not ready: no thinkpad_acpi)".to_string(),
+                "Probed cpufreq: initialized and selected".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn notes_are_empty_without_probes() {
+        assert!(ProbeReport::default().notes().is_empty());
+    }
+
+    #[test]
+    fn notes_report_an_ignored_power_config() {
+        let report = ProbeReport {
+            attempts: Vec::new(),
+            power_config_error: Some(DriverError::Internal("bad".into())),
+        };
+        assert_eq!(report.notes(), vec!["power.toml ignored: internal error: bad".to_string()]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn rapl_skips_tables_for_profiles_the_driver_lacks() {
+        use crate::test_support::StubFan;
+
+        let config = PowerConfig::from_toml_str("[profiles.Turbo]\npl1_w = 90\n").unwrap();
+        let driver = with_rapl(Box::new(StubFan(Default::default())), &config).unwrap();
+
+        assert_eq!(driver.capabilities().unwrap().profiles.len(), 3);
+        assert_eq!(
+            driver.diagnose(),
+            vec!["power.toml: no profile \"Turbo\" here, its settings are skipped".to_string()]
+        );
+        driver.set_profile(1).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn malformed_power_config_is_reported_once_and_probes_go_on() {
+        let fixture = crate::test_support::Fixture::new();
+        fixture.write("power.toml", "[profiles.Quiet\n");
+        std::env::set_var("EDGE_OPTIMIZER_POWER_CONFIG", fixture.path("power.toml"));
+        let report = probe_all(None);
+        std::env::remove_var("EDGE_OPTIMIZER_POWER_CONFIG");
+
+        assert!(matches!(report.power_config_error, Some(DriverError::Internal(_))));
+        for attempt in &report.attempts {
+            if let Err(e) = &attempt.result {
+                assert!(!e.to_string().contains("power config"), "{}: {}", attempt.name, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Per-user state kept between runs: the directory `edgeopt` remembers things in, a short log
+//! of recent failures that `doctor` includes in its report, and values that only hold until the
+//! next reboot.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::driver::DriverError;
+
+/// Lines kept in `errors.log`; older entries are dropped on the next write.
+pub const ERROR_LOG_LIMIT: usize = 50;
+
+/// Boot times (Windows) this close together belong to the same boot; deriving them from the
+/// tick count jitters by a second or so.
+const BOOT_TIME_TOLERANCE_SECS: u64 = 5;
+
+/// `$EDGE_OPTIMIZER_STATE_DIR`, else `$XDG_STATE_HOME/edge_optimizer` (default
+/// `~/.local/state/edge_optimizer`), or `%LOCALAPPDATA%\EdgeOptimizer` on Windows.
+pub fn state_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("EDGE_OPTIMIZER_STATE_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        return Some(PathBuf::from(std::env::var_os("LOCALAPPDATA")?).join("EdgeOptimizer"));
+    }
+    match std::env::var_os("XDG_STATE_HOME") {
+        Some(state) => Some(PathBuf::from(state).join("edge_optimizer")),
+        None => Some(PathBuf::from(std::env::var_os("HOME")?).join(".local/state/edge_optimizer")),
+    }
+}
+
+fn error_log() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("errors.log"))
+}
+
+/// Append `<unix seconds> <context>: <error>` to the error log. Best effort: a log that cannot
+/// be written is not worth failing the command over.
+pub fn record_error(context: &str, error: &DriverError) {
+    let Some(path) = error_log() else {
+        return;
+    };
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    append_error(&path, &format!("{} {}: {}", secs, context, error));
+}
+
+/// Logged errors, oldest first.
+pub fn recent_errors() -> Vec<String> {
+    error_log().map(|path| read_errors(&path)).unwrap_or_default()
+}
+
+/// Add `entry` to the log at `path`, keeping the last `ERROR_LOG_LIMIT` lines.
+fn append_error(path: &Path, entry: &str) {
+    let mut lines = read_errors(path);
+    // One entry per line, whatever the message contains.
+    lines.push(entry.replace('\n', " "));
+    let keep = lines.len().saturating_sub(ERROR_LOG_LIMIT);
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, lines[keep..].join("\n") + "\n");
+}
+
+fn read_errors(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .map(|text| text.lines().filter(|l| !l.trim().is_empty()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Identifies the running boot: the kernel's random boot id on Linux, the boot time in Unix
+/// seconds (now minus uptime) on Windows.
+pub fn boot_id() -> Option<String> {
+    #[cfg(windows)]
+    {
+        // SAFETY: GetTickCount64 has no preconditions.
+        let uptime_ms = unsafe { windows::Win32::System::SystemInformation::GetTickCount64() };
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs();
+        Some(now.saturating_sub(uptime_ms / 1000).to_string())
+    }
+    #[cfg(not(windows))]
+    {
+        fs::read_to_string("/proc/sys/kernel/random/boot_id")
+            .ok()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+    }
+}
+
+fn same_boot(a: &str, b: &str) -> bool {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.abs_diff(b) <= BOOT_TIME_TOLERANCE_SECS,
+        _ => a == b,
+    }
+}
+
+/// Value stored under `key` in `dir` during the boot `boot`; `None` if it was stored in an
+/// earlier boot or never.
+pub fn boot_value(dir: &Path, key: &str, boot: &str) -> Option<String> {
+    let text = fs::read_to_string(dir.join(format!("boot_{}", key))).ok()?;
+    let mut lines = text.lines();
+    same_boot(lines.next()?, boot).then_some(())?;
+    lines.next().map(String::from)
+}
+
+/// Remember `value` under `key` until the boot `boot` ends.
+pub fn set_boot_value(dir: &Path, key: &str, boot: &str, value: &str) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(format!("boot_{}", key)), format!("{}\n{}\n", boot, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn boot_times_within_tolerance_are_one_boot() {
+        let state = Fixture::new();
+        set_boot_value(state.root(), "k", "1700000000", "v").unwrap();
+        assert_eq!(boot_value(state.root(), "k", "1700000003").as_deref(), Some("v"));
+        assert_eq!(boot_value(state.root(), "k", "1700000060"), None);
+    }
+
+    #[test]
+    fn same_boot_tolerance_is_inclusive() {
+        assert!(same_boot("1700000000", "1700000005"));
+        assert!(same_boot("1700000005", "1700000000"));
+        assert!(!same_boot("1700000000", "1700000006"));
+        // Linux boot ids are compared exactly.
+        assert!(same_boot("0b1e1f0e-8c3a", "0b1e1f0e-8c3a"));
+        assert!(!same_boot("0b1e1f0e-8c3a", "0b1e1f0e-8c3b"));
+    }
+
+    #[test]
+    fn error_log_keeps_the_last_entries() {
+        let state = Fixture::new();
+        let log = state.path("errors.log");
+        for n in 0..ERROR_LOG_LIMIT + 5 {
+            append_error(&log, &format!("{} max: failed", n));
+        }
+
+        let lines = read_errors(&log);
+        assert_eq!(lines.len(), ERROR_LOG_LIMIT);
+        assert_eq!(lines.first().map(String::as_str), Some("5 max: failed"));
+        assert_eq!(lines.last().map(String::as_str), Some("54 max: failed"));
+    }
+
+    #[test]
+    fn error_log_entries_stay_on_one_line() {
+        let state = Fixture::new();
+        let log = state.path("state/errors.log");
+        append_error(&log, "1 set: first\nsecond");
+        assert_eq!(read_errors(&log), vec!["1 set: first second"]);
+    }
+}
@@ -0,0 +1,71 @@
+//! Small helpers for reading and writing sysfs/procfs attribute files.
+//! All paths are resolved by the caller so drivers can be pointed at a fake tree.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::driver::DriverError;
+
+/// Read an attribute and strip surrounding whitespace.
+pub fn read_trimmed(path: &Path) -> Result<String, DriverError> {
+    fs::read_to_string(path)
+        .map(|s| s.trim().to_string())
+        .map_err(|e| DriverError::Io(format!("read {}: {}", path.display(), e)))
+}
+
+/// Read an attribute if it exists; missing or unreadable files yield `None`.
+pub fn read_optional(path: &Path) -> Option<String> {
+    read_trimmed(path).ok()
+}
+
+/// Read an attribute as a signed integer.
+pub fn read_i64(path: &Path) -> Result<i64, DriverError> {
+    let raw = read_trimmed(path)?;
+    raw.parse::<i64>()
+        .map_err(|_| DriverError::Io(format!("{}: not an integer: {:?}", path.display(), raw)))
+}
+
+/// Write a value to an attribute. sysfs expects the whole value in a single write.
+pub fn write_value(path: &Path, value: &str) -> Result<(), DriverError> {
+    fs::write(path, value).map_err(|e| DriverError::Io(format!("write {}: {}", path.display(), e)))
+}
+
+/// List entries of `dir` whose file name starts with `prefix`, sorted by their numeric suffix.
+/// Returns an empty list when the directory does not exist.
+pub fn numbered_entries(dir: &Path, prefix: &str) -> Vec<(u32, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut found: Vec<(u32, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let index = name.to_str()?.strip_prefix(prefix)?.parse::<u32>().ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    found.sort_by_key(|(index, _)| *index);
+    found
+}
+
+/// Indices `N` of attributes named `{prefix}N{suffix}` in `dir` (e.g. `temp1_input`), sorted.
+pub fn attribute_indices(dir: &Path, prefix: &str, suffix: &str) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut found: Vec<u32> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            name.to_str()?
+                .strip_prefix(prefix)?
+                .strip_suffix(suffix)?
+                .parse::<u32>()
+                .ok()
+        })
+        .collect();
+    found.sort_unstable();
+    found
+}
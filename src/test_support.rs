@@ -0,0 +1,146 @@
+//! Helpers shared by unit tests: throwaway sysfs/procfs trees under the system temp directory
+//! a scripted HP BIOS, and a fan driver for the wrappers to wrap.
+
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::hp_bios::{BiosCommand, BiosRequest, BiosResponse, BiosTransport};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary directory tree, removed on drop.
+pub struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "edge_optimizer-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("create fixture root");
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.root.join(relative)
+    }
+
+    /// Write `contents` to `relative`, creating parent directories.
+    pub fn write(&self, relative: &str, contents: &str) -> &Self {
+        let path = self.path(relative);
+        fs::create_dir_all(path.parent().expect("fixture file has a parent")).expect("create fixture dir");
+        fs::write(&path, contents).expect("write fixture file");
+        self
+    }
+
+    /// Trimmed contents of `relative`.
+    pub fn read(&self, relative: &str) -> String {
+        fs::read_to_string(self.path(relative))
+            .expect("read fixture file")
+            .trim()
+            .to_string()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// In-memory `BiosTransport`: answers each command type with a canned response and records
+/// every request it receives.
+#[derive(Default)]
+pub struct FakeBios {
+    responses: Vec<((BiosCommand, u32), BiosResponse)>,
+    pub requests: RefCell<Vec<BiosRequest>>,
+}
+
+impl FakeBios {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `(command, command_type)` with return code 0 and `data`.
+    pub fn respond(self, command: BiosCommand, command_type: u32, data: &[u8]) -> Self {
+        self.respond_code(command, command_type, 0, data)
+    }
+
+    pub fn respond_code(mut self, command: BiosCommand, command_type: u32, return_code: u32, data: &[u8]) -> Self {
+        let response = BiosResponse {
+            return_code,
+            data: data.to_vec(),
+        };
+        self.responses.push(((command, command_type), response));
+        self
+    }
+}
+
+impl BiosTransport for FakeBios {
+    /// Unknown commands get return code 4, like a board that doesn't implement them.
+    fn call(&self, request: &BiosRequest) -> Result<BiosResponse, DriverError> {
+        self.requests.borrow_mut().push(request.clone());
+        let response = self
+            .responses
+            .iter()
+            .find(|(key, _)| *key == (request.command, request.command_type))
+            .map(|(_, response)| response.clone());
+        Ok(response.unwrap_or(BiosResponse {
+            return_code: 4,
+            data: Vec::new(),
+        }))
+    }
+}
+
+/// Fan driver with profiles 0 ("Default"), 1 ("Quiet") and 2 ("Max") that records the profile
+/// it was switched to and rejects any other id.
+#[derive(Default)]
+pub struct StubFan(pub Cell<Option<ProfileId>>);
+
+impl FanDriver for StubFan {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        let profile = |id, name: &str| FanProfile {
+            id,
+            name: name.into(),
+            is_maximum: id == 2,
+        };
+        Ok(FanCapabilities {
+            supported: true,
+            profiles: vec![profile(0, "Default"), profile(1, "Quiet"), profile(2, "Max")],
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        Ok(self.0.get())
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        self.explain_profile(profile)?;
+        self.0.set(Some(profile));
+        Ok(())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        if profile > 2 {
+            return Err(DriverError::Unsupported(format!("no profile {}", profile)));
+        }
+        Ok(Explanation::new(profile))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        Err(DriverError::Unsupported("no telemetry".into()))
+    }
+}
@@ -0,0 +1,260 @@
+//! Thermal trip-point discovery used to derive safety limits from the platform.
+//! Reads `/sys/class/thermal/thermal_zone*/trip_point_*` and hwmon `tempN_crit`/`tempN_max`.
+
+use std::path::{Path, PathBuf};
+
+use crate::sysfs;
+
+/// Sensor names (zone `type`, or hwmon `chip/label` prefixes) for the CPU package temperature
+/// that driver telemetry reports.
+const CPU_PACKAGE_SENSORS: [&str; 7] = [
+    "x86_pkg_temp",
+    "cpu_thermal",
+    "cpu-thermal",
+    "coretemp/Package",
+    "k10temp/Tctl",
+    "k10temp/Tdie",
+    "zenpower/T",
+];
+
+/// Trip points outside this window are treated as firmware placeholders and ignored.
+const PLAUSIBLE_TRIP_C: std::ops::RangeInclusive<f32> = 40.0..=150.0;
+
+/// Per-sensor thresholds in degrees Celsius, as reported by the kernel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorLimits {
+    /// Zone `type` or hwmon `name`/`tempN_label`, e.g. "x86_pkg_temp" or "coretemp/Package id 0".
+    pub name: String,
+    /// Directory the limits were read from.
+    pub source: PathBuf,
+    /// Firmware starts throttling passively (thermal zone `passive` trip).
+    pub passive_c: Option<f32>,
+    /// Hot trip, or hwmon `tempN_max`.
+    pub hot_c: Option<f32>,
+    /// Critical trip, or hwmon `tempN_crit`; the platform shuts down here.
+    pub critical_c: Option<f32>,
+}
+
+impl SensorLimits {
+    fn new(name: String, source: PathBuf) -> Self {
+        Self {
+            name,
+            source,
+            passive_c: None,
+            hot_c: None,
+            critical_c: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.passive_c.is_none() && self.hot_c.is_none() && self.critical_c.is_none()
+    }
+}
+
+/// Thresholds discovered across all thermal zones and hwmon sensors.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThermalLimits {
+    pub sensors: Vec<SensorLimits>,
+}
+
+impl ThermalLimits {
+    /// Discover limits from the live `/sys` tree. Yields no sensors on platforms without sysfs.
+    pub fn discover() -> Self {
+        Self::discover_in(Path::new("/sys"))
+    }
+
+    /// Discover limits from a sysfs tree rooted at `root` (e.g. a fixture directory).
+    pub fn discover_in(root: &Path) -> Self {
+        let mut sensors = Vec::new();
+
+        for (_, zone) in sysfs::numbered_entries(&root.join("class/thermal"), "thermal_zone") {
+            if let Some(limits) = read_thermal_zone(&zone) {
+                sensors.push(limits);
+            }
+        }
+
+        for (_, hwmon) in sysfs::numbered_entries(&root.join("class/hwmon"), "hwmon") {
+            sensors.extend(read_hwmon(&hwmon));
+        }
+
+        Self { sensors }
+    }
+
+    /// Lowest critical threshold across all sensors; the first sensor to trip wins.
+    pub fn lowest_critical_c(&self) -> Option<f32> {
+        self.sensors
+            .iter()
+            .filter_map(|s| s.critical_c)
+            .min_by(f32::total_cmp)
+    }
+
+    /// Lowest critical threshold of the CPU package sensors, the temperature the drivers'
+    /// telemetry reports. Drives, the PCH and wifi trip lower and are left out.
+    pub fn cpu_critical_c(&self) -> Option<f32> {
+        self.sensors
+            .iter()
+            .filter(|s| is_cpu_package(&s.name))
+            .filter_map(|s| s.critical_c)
+            .min_by(f32::total_cmp)
+    }
+}
+
+fn is_cpu_package(name: &str) -> bool {
+    CPU_PACKAGE_SENSORS.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Current CPU package temperature from the thermal zones under `root` (normally `/sys`).
+/// Prefers the `x86_pkg_temp` zone and falls back to the first zone with a reading.
+pub fn package_temperature_c(root: &Path) -> Option<f32> {
+    let zones = sysfs::numbered_entries(&root.join("class/thermal"), "thermal_zone");
+    let preferred = zones
+        .iter()
+        .find(|(_, zone)| sysfs::read_optional(&zone.join("type")).as_deref() == Some("x86_pkg_temp"));
+
+    preferred
+        .into_iter()
+        .chain(zones.iter())
+        .find_map(|(_, zone)| sysfs::read_i64(&zone.join("temp")).ok())
+        .map(|milli| milli as f32 / 1000.0)
+}
+
+fn read_thermal_zone(zone: &Path) -> Option<SensorLimits> {
+    let name = sysfs::read_optional(&zone.join("type")).unwrap_or_else(|| file_name(zone));
+    let mut limits = SensorLimits::new(name, zone.to_path_buf());
+
+    let mut index = 0;
+    while let Some(kind) = sysfs::read_optional(&zone.join(format!("trip_point_{}_type", index))) {
+        let temp = read_millidegrees(&zone.join(format!("trip_point_{}_temp", index)));
+        // Zones may declare several trips of one kind; keep the lowest.
+        let slot = match kind.as_str() {
+            "passive" => Some(&mut limits.passive_c),
+            "hot" => Some(&mut limits.hot_c),
+            "critical" => Some(&mut limits.critical_c),
+            _ => None,
+        };
+        if let (Some(slot), Some(temp)) = (slot, temp) {
+            *slot = Some(slot.map_or(temp, |current| current.min(temp)));
+        }
+        index += 1;
+    }
+
+    (!limits.is_empty()).then_some(limits)
+}
+
+fn read_hwmon(hwmon: &Path) -> Vec<SensorLimits> {
+    let chip = sysfs::read_optional(&hwmon.join("name")).unwrap_or_else(|| file_name(hwmon));
+    let mut sensors = Vec::new();
+
+    for index in sysfs::attribute_indices(hwmon, "temp", "_input") {
+        let label = sysfs::read_optional(&hwmon.join(format!("temp{}_label", index)))
+            .unwrap_or_else(|| format!("temp{}", index));
+        let mut limits = SensorLimits::new(format!("{}/{}", chip, label), hwmon.to_path_buf());
+        limits.hot_c = read_millidegrees(&hwmon.join(format!("temp{}_max", index)));
+        limits.critical_c = read_millidegrees(&hwmon.join(format!("temp{}_crit", index)));
+        if !limits.is_empty() {
+            sensors.push(limits);
+        }
+    }
+
+    sensors
+}
+
+fn read_millidegrees(path: &Path) -> Option<f32> {
+    let celsius = sysfs::read_i64(path).ok()? as f32 / 1000.0;
+    PLAUSIBLE_TRIP_C.contains(&celsius).then_some(celsius)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{MaxFanPolicy, CRITICAL_TRIP_MARGIN_C, FALLBACK_MAX_SAFE_TEMP_C};
+    use crate::test_support::Fixture;
+
+    /// A laptop whose NVMe drive and wifi trip well below the CPU.
+    fn laptop() -> Fixture {
+        let sys = Fixture::new();
+        sys.write("class/thermal/thermal_zone0/type", "acpitz\n")
+            .write("class/thermal/thermal_zone0/temp", "45000\n")
+            .write("class/thermal/thermal_zone0/trip_point_0_type", "critical\n")
+            .write("class/thermal/thermal_zone0/trip_point_0_temp", "120000\n")
+            .write("class/thermal/thermal_zone1/type", "x86_pkg_temp\n")
+            .write("class/thermal/thermal_zone1/temp", "88000\n")
+            .write("class/thermal/thermal_zone1/trip_point_0_type", "passive\n")
+            .write("class/thermal/thermal_zone1/trip_point_0_temp", "95000\n")
+            .write("class/thermal/thermal_zone2/type", "iwlwifi_1\n")
+            .write("class/thermal/thermal_zone2/trip_point_0_type", "critical\n")
+            .write("class/thermal/thermal_zone2/trip_point_0_temp", "75000\n")
+            .write("class/hwmon/hwmon0/name", "nvme\n")
+            .write("class/hwmon/hwmon0/temp1_input", "41850\n")
+            .write("class/hwmon/hwmon0/temp1_label", "Composite\n")
+            .write("class/hwmon/hwmon0/temp1_crit", "84850\n")
+            .write("class/hwmon/hwmon1/name", "coretemp\n")
+            .write("class/hwmon/hwmon1/temp1_input", "88000\n")
+            .write("class/hwmon/hwmon1/temp1_label", "Package id 0\n")
+            .write("class/hwmon/hwmon1/temp1_max", "100000\n")
+            .write("class/hwmon/hwmon1/temp1_crit", "100000\n");
+        sys
+    }
+
+    #[test]
+    fn discovers_zones_and_hwmon_sensors() {
+        let sys = laptop();
+        let limits = ThermalLimits::discover_in(sys.root());
+        let names: Vec<&str> = limits.sensors.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["acpitz", "x86_pkg_temp", "iwlwifi_1", "nvme/Composite", "coretemp/Package id 0"]);
+        assert_eq!(limits.sensors[1].passive_c, Some(95.0));
+        assert_eq!(limits.sensors[4].hot_c, Some(100.0));
+    }
+
+    #[test]
+    fn cpu_critical_ignores_drives_and_wifi() {
+        let sys = laptop();
+        let limits = ThermalLimits::discover_in(sys.root());
+        assert_eq!(limits.lowest_critical_c(), Some(75.0));
+        assert_eq!(limits.cpu_critical_c(), Some(100.0));
+
+        let policy = MaxFanPolicy::from_thermal_limits(&limits);
+        assert_eq!(policy.max_safe_temp_c, Some(100.0 - CRITICAL_TRIP_MARGIN_C));
+    }
+
+    #[test]
+    fn amd_tctl_is_a_cpu_sensor() {
+        let sys = Fixture::new();
+        sys.write("class/hwmon/hwmon2/name", "k10temp\n")
+            .write("class/hwmon/hwmon2/temp1_input", "70000\n")
+            .write("class/hwmon/hwmon2/temp1_label", "Tctl\n")
+            .write("class/hwmon/hwmon2/temp1_crit", "105000\n");
+        assert_eq!(ThermalLimits::discover_in(sys.root()).cpu_critical_c(), Some(105.0));
+    }
+
+    #[test]
+    fn falls_back_without_a_cpu_trip() {
+        let sys = Fixture::new();
+        sys.write("class/hwmon/hwmon0/name", "nvme\n")
+            .write("class/hwmon/hwmon0/temp1_input", "41850\n")
+            .write("class/hwmon/hwmon0/temp1_crit", "84850\n");
+        let policy = MaxFanPolicy::from_thermal_limits(&ThermalLimits::discover_in(sys.root()));
+        assert_eq!(policy.max_safe_temp_c, Some(FALLBACK_MAX_SAFE_TEMP_C));
+    }
+
+    #[test]
+    fn ignores_placeholder_trips() {
+        let sys = Fixture::new();
+        sys.write("class/thermal/thermal_zone0/type", "x86_pkg_temp\n")
+            .write("class/thermal/thermal_zone0/trip_point_0_type", "critical\n")
+            .write("class/thermal/thermal_zone0/trip_point_0_temp", "255000\n");
+        assert!(ThermalLimits::discover_in(sys.root()).sensors.is_empty());
+    }
+
+    #[test]
+    fn package_temperature_prefers_x86_pkg_temp() {
+        let sys = laptop();
+        assert_eq!(package_temperature_c(sys.root()), Some(88.0));
+    }
+}
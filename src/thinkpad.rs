@@ -0,0 +1,292 @@
+//! ThinkPad fan control through `thinkpad_acpi`'s `/proc/acpi/ibm/fan` interface.
+//! Writing levels requires the module to be loaded with `fan_control=1`.
+
+use std::path::{Path, PathBuf};
+
+use crate::driver::{DriverError, FanDriver};
+use crate::explain::Explanation;
+use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::sysfs;
+
+/// Profile id for the firmware-controlled `auto` level.
+pub const PROFILE_AUTO: ProfileId = 8;
+/// Profile id for `full-speed` (reported by the firmware as `disengaged`).
+pub const PROFILE_FULL_SPEED: ProfileId = 9;
+
+const FAN_CONTROL_HINT: &str = "thinkpad_acpi fan control is disabled; \
+    load the module with fan_control=1 (e.g. `options thinkpad_acpi fan_control=1` in /etc/modprobe.d)";
+
+/// Fan level as reported in the `level:` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThinkpadFanLevel {
+    /// Fixed level 0 (off) through 7 (fastest regulated level).
+    Manual(u8),
+    Auto,
+    /// Unregulated full speed; `full-speed` and `disengaged` are the same mode.
+    FullSpeed,
+}
+
+impl ThinkpadFanLevel {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "auto" => Some(Self::Auto),
+            "disengaged" | "full-speed" => Some(Self::FullSpeed),
+            n => n.parse::<u8>().ok().filter(|n| *n <= 7).map(Self::Manual),
+        }
+    }
+
+    fn from_profile_id(id: ProfileId) -> Option<Self> {
+        match id {
+            0..=7 => Some(Self::Manual(id as u8)),
+            PROFILE_AUTO => Some(Self::Auto),
+            PROFILE_FULL_SPEED => Some(Self::FullSpeed),
+            _ => None,
+        }
+    }
+
+    fn to_profile_id(self) -> ProfileId {
+        match self {
+            Self::Manual(n) => n as ProfileId,
+            Self::Auto => PROFILE_AUTO,
+            Self::FullSpeed => PROFILE_FULL_SPEED,
+        }
+    }
+
+    /// Argument accepted by `echo level <arg> > /proc/acpi/ibm/fan`.
+    fn command_arg(self) -> String {
+        match self {
+            Self::Manual(n) => n.to_string(),
+            Self::Auto => "auto".into(),
+            Self::FullSpeed => "full-speed".into(),
+        }
+    }
+}
+
+/// Parsed contents of `/proc/acpi/ibm/fan`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThinkpadFanStatus {
+    pub enabled: bool,
+    pub speed_rpm: Option<u32>,
+    pub level: Option<ThinkpadFanLevel>,
+    /// `commands:` lines are only listed when writes are allowed (`fan_control=1`).
+    pub writable: bool,
+}
+
+impl ThinkpadFanStatus {
+    pub fn parse(contents: &str) -> Self {
+        let mut status = Self {
+            enabled: false,
+            speed_rpm: None,
+            level: None,
+            writable: false,
+        };
+
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "status" => status.enabled = value == "enabled",
+                "speed" => status.speed_rpm = value.parse().ok(),
+                "level" => status.level = ThinkpadFanLevel::parse(value),
+                "commands" if value.starts_with("level") => status.writable = true,
+                _ => {}
+            }
+        }
+
+        status
+    }
+}
+
+pub struct ThinkpadFanDriver {
+    fan: PathBuf,
+    thermal: PathBuf,
+}
+
+impl ThinkpadFanDriver {
+    /// Probe the live `/proc/acpi/ibm` interface.
+    pub fn new() -> Result<Self, DriverError> {
+        Self::probe(Path::new("/proc/acpi/ibm"))
+    }
+
+    /// Probe an `ibm` directory (normally `/proc/acpi/ibm`) containing `fan` and `thermal`.
+    pub fn probe(ibm_dir: &Path) -> Result<Self, DriverError> {
+        let driver = Self {
+            fan: ibm_dir.join("fan"),
+            thermal: ibm_dir.join("thermal"),
+        };
+
+        let status = driver.status().map_err(|_| {
+            DriverError::NotReady(format!(
+                "{} not found - thinkpad_acpi not loaded or not a ThinkPad?",
+                driver.fan.display()
+            ))
+        })?;
+        if !status.writable {
+            return Err(DriverError::NotReady(FAN_CONTROL_HINT.into()));
+        }
+
+        Ok(driver)
+    }
+
+    pub fn status(&self) -> Result<ThinkpadFanStatus, DriverError> {
+        Ok(ThinkpadFanStatus::parse(&sysfs::read_trimmed(&self.fan)?))
+    }
+
+    /// First value of the `temperatures:` line in `/proc/acpi/ibm/thermal` (CPU on most models).
+    fn read_temperature(&self) -> Option<f32> {
+        let contents = sysfs::read_optional(&self.thermal)?;
+        contents
+            .strip_prefix("temperatures:")?
+            .split_whitespace()
+            .next()?
+            .parse::<i32>()
+            .ok()
+            // -128 marks an absent sensor.
+            .filter(|t| *t > 0)
+            .map(|t| t as f32)
+    }
+}
+
+impl FanDriver for ThinkpadFanDriver {
+    fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
+        let mut profiles: Vec<FanProfile> = (0..=7)
+            .map(|level| FanProfile {
+                id: level,
+                name: if level == 0 {
+                    "Level 0 (Off)".into()
+                } else {
+                    format!("Level {}", level)
+                },
+                is_maximum: false,
+            })
+            .collect();
+        profiles.push(FanProfile {
+            id: PROFILE_AUTO,
+            name: "Auto".into(),
+            is_maximum: false,
+        });
+        profiles.push(FanProfile {
+            id: PROFILE_FULL_SPEED,
+            name: "Full speed (disengaged)".into(),
+            is_maximum: true,
+        });
+
+        Ok(FanCapabilities {
+            supported: true,
+            profiles,
+        })
+    }
+
+    fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
+        Ok(self.status()?.level.map(ThinkpadFanLevel::to_profile_id))
+    }
+
+    fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
+        let level = ThinkpadFanLevel::from_profile_id(profile)
+            .ok_or_else(|| DriverError::Unsupported(format!("Unknown profile: {}", profile)))?;
+
+        if !self.status()?.writable {
+            return Err(DriverError::NotReady(FAN_CONTROL_HINT.into()));
+        }
+        sysfs::write_value(&self.fan, &format!("level {}", level.command_arg()))
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let level = ThinkpadFanLevel::from_profile_id(profile)
+            .ok_or_else(|| DriverError::Unsupported(format!("Unknown profile: {}", profile)))?;
+
+        if !self.status()?.writable {
+            return Err(DriverError::NotReady(FAN_CONTROL_HINT.into()));
+        }
+        Ok(Explanation::new(profile)
+            .note(format!("{} accepts commands (fan_control=1)", self.fan.display()))
+            .write(&self.fan, format!("level {}", level.command_arg())))
+    }
+
+    fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
+        Ok(FanTelemetry {
+            rpm: self.status()?.speed_rpm,
+            temperature_c: self.read_temperature(),
+            fan_duty_percent: None,
+            timestamp: std::time::Instant::now(),
+            captured_at: std::time::SystemTime::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    const WRITABLE: &str = "status:\t\tenabled\nspeed:\t\t2890\nlevel:\t\tauto\n\
+        commands:\tlevel <level> (<level> is 0-7, auto, disengaged, full-speed)\n\
+        commands:\tenable, disable\n\
+        commands:\twatchdog <timeout> (<timeout> is 0 (off), 1-120 (seconds))\n";
+    const READ_ONLY: &str = "status:\t\tenabled\nspeed:\t\t2890\nlevel:\t\tauto\n";
+
+    fn ibm(fan: &str) -> Fixture {
+        let proc = Fixture::new();
+        proc.write("fan", fan)
+            .write("thermal", "temperatures:\t52 -128 38 0 -128 -128 -128 -128\n");
+        proc
+    }
+
+    #[test]
+    fn parses_fan_status() {
+        let status = ThinkpadFanStatus::parse(WRITABLE);
+        assert!(status.enabled && status.writable);
+        assert_eq!(status.speed_rpm, Some(2890));
+        assert_eq!(status.level, Some(ThinkpadFanLevel::Auto));
+
+        let status = ThinkpadFanStatus::parse("status:\tdisabled\nlevel:\tdisengaged\n");
+        assert!(!status.enabled && !status.writable);
+        assert_eq!(status.level, Some(ThinkpadFanLevel::FullSpeed));
+        assert_eq!(ThinkpadFanStatus::parse("level:\t9\n").level, None);
+    }
+
+    #[test]
+    fn probe_needs_fan_control() {
+        assert!(matches!(ThinkpadFanDriver::probe(Fixture::new().root()), Err(DriverError::NotReady(_))));
+        match ThinkpadFanDriver::probe(ibm(READ_ONLY).root()) {
+            Err(DriverError::NotReady(message)) => assert!(message.contains("fan_control=1")),
+            other => panic!("expected NotReady, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn levels_are_written_as_commands() {
+        let proc = ibm(WRITABLE);
+        let driver = ThinkpadFanDriver::probe(proc.root()).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(PROFILE_AUTO));
+        assert_eq!(driver.capabilities().unwrap().profiles.len(), 10);
+
+        driver.set_profile(PROFILE_FULL_SPEED).unwrap();
+        assert_eq!(proc.read("fan"), "level full-speed");
+        assert!(matches!(driver.set_profile(10), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn writes_fail_once_fan_control_is_gone() {
+        let proc = ibm(WRITABLE);
+        let driver = ThinkpadFanDriver::probe(proc.root()).unwrap();
+        proc.write("fan", READ_ONLY);
+        assert!(matches!(driver.set_profile(3), Err(DriverError::NotReady(_))));
+        assert!(matches!(driver.explain_profile(3), Err(DriverError::NotReady(_))));
+        assert_eq!(proc.read("fan"), READ_ONLY.trim());
+    }
+
+    #[test]
+    fn telemetry_reads_speed_and_first_sensor() {
+        let proc = ibm(WRITABLE);
+        let telemetry = ThinkpadFanDriver::probe(proc.root()).unwrap().telemetry().unwrap();
+        assert_eq!(telemetry.rpm, Some(2890));
+        assert_eq!(telemetry.temperature_c, Some(52.0));
+
+        proc.write("thermal", "temperatures:\t-128 40\n");
+        let telemetry = ThinkpadFanDriver::probe(proc.root()).unwrap().telemetry().unwrap();
+        assert_eq!(telemetry.temperature_c, None);
+    }
+}
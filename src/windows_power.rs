@@ -1,78 +1,614 @@
-use std::process::Command;
+use crate::command::{CommandRunner, CommandSpec, SystemRunner};
 use crate::driver::{DriverError, FanDriver};
+use crate::explain::{Action, Explanation};
 use crate::model::{FanCapabilities, FanProfile, FanTelemetry, ProfileId};
+use crate::powercfg::{self, PowerScheme};
 
 /// Windows power plan based fallback driver for systems where direct thermal control isn't available.
-/// Maps fan profiles to Windows power plans: Power Saver (quiet), Balanced (default), High Performance (max).
-pub struct WindowsPowerPlanDriver;
+/// Maps fan profiles to the power plans present on the machine: Power Saver (quiet), Balanced (default),
+/// High/Ultimate Performance (max) and any OEM or custom plans.
+pub struct WindowsPowerPlanDriver<R: CommandRunner = SystemRunner> {
+    runner: R,
+}
 
 impl WindowsPowerPlanDriver {
     pub fn new() -> Result<Self, DriverError> {
-        // Verify powercfg is available
-        match Command::new("powercfg").arg("/?").output() {
-            Ok(_) => Ok(Self),
+        Self::with_runner(SystemRunner)
+    }
+}
+
+/// Profile ids of the stock schemes. Other (OEM or custom) plans get an id derived from their
+/// GUID in `CUSTOM_PLAN_BASE..CUSTOM_PLAN_BASE + CUSTOM_PLAN_SPAN`, so it stays the same when
+/// plans are added or removed.
+const BALANCED_ID: ProfileId = 0;
+const HIGH_PERFORMANCE_ID: ProfileId = 1;
+const POWER_SAVER_ID: ProfileId = 2;
+const ULTIMATE_PERFORMANCE_ID: ProfileId = 3;
+const EDGE_OPTIMIZER_ID: ProfileId = 4;
+pub const CUSTOM_PLAN_BASE: ProfileId = 100;
+const CUSTOM_PLAN_SPAN: ProfileId = 9900;
+
+/// Stable id for a custom plan: FNV-1a of the lower-case GUID folded into the custom range.
+/// Colliding plans take the next free id in `powercfg /list` order.
+fn custom_plan_id(guid: &str, taken: &[ProfileId]) -> ProfileId {
+    let hash = guid
+        .to_ascii_lowercase()
+        .bytes()
+        .fold(0x811c_9dc5_u32, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193));
+    let mut id = CUSTOM_PLAN_BASE + hash % CUSTOM_PLAN_SPAN;
+    while taken.contains(&id) {
+        id = CUSTOM_PLAN_BASE + (id - CUSTOM_PLAN_BASE + 1) % CUSTOM_PLAN_SPAN;
+    }
+    id
+}
+
+/// A power plan offered as a fan profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanProfile {
+    pub profile: FanProfile,
+    pub guid: String,
+}
+
+/// Build profiles from the schemes actually present. The maximum profile is the dedicated
+/// Edge Optimizer plan if installed, else Ultimate Performance, else High Performance; machines
+/// with none of these declare no maximum.
+pub fn plan_profiles(schemes: &[PowerScheme]) -> Vec<PlanProfile> {
+    let has = |guid: &str| schemes.iter().any(|s| s.guid == guid);
+    let max_guid = if has(EDGE_OPTIMIZER_PLAN) {
+        Some(EDGE_OPTIMIZER_PLAN)
+    } else if has(powercfg::ULTIMATE_PERFORMANCE) {
+        Some(powercfg::ULTIMATE_PERFORMANCE)
+    } else if has(powercfg::HIGH_PERFORMANCE) {
+        Some(powercfg::HIGH_PERFORMANCE)
+    } else {
+        None
+    };
+
+    let mut custom_ids = Vec::new();
+    schemes
+        .iter()
+        .map(|scheme| {
+            let (id, name) = match scheme.guid.as_str() {
+                powercfg::BALANCED => (BALANCED_ID, "Balanced (Default)".to_string()),
+                powercfg::HIGH_PERFORMANCE => (HIGH_PERFORMANCE_ID, "High Performance".to_string()),
+                powercfg::POWER_SAVER => (POWER_SAVER_ID, "Power Saver (Quiet)".to_string()),
+                powercfg::ULTIMATE_PERFORMANCE => (ULTIMATE_PERFORMANCE_ID, "Ultimate Performance".to_string()),
+                EDGE_OPTIMIZER_PLAN => (EDGE_OPTIMIZER_ID, scheme.name.clone()),
+                _ => {
+                    let id = custom_plan_id(&scheme.guid, &custom_ids);
+                    custom_ids.push(id);
+                    (id, scheme.name.clone())
+                }
+            };
+            let is_maximum = max_guid == Some(scheme.guid.as_str());
+            PlanProfile {
+                profile: FanProfile {
+                    id,
+                    name: if is_maximum { format!("{} (Max)", name) } else { name },
+                    is_maximum,
+                },
+                guid: scheme.guid.clone(),
+            }
+        })
+        .collect()
+}
+
+/// GUID given to the dedicated plan so it can be found (and removed) again across runs.
+pub const EDGE_OPTIMIZER_PLAN: &str = "5e0a6b1c-7f2d-4c3e-9a81-ed6e0b7a1c42";
+
+/// Processor cooling policy (`SYSCOOLPOL`): whether the OS raises fan speed before throttling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoolingPolicy {
+    /// Slow the processor first, then raise fan speed.
+    Passive = 0,
+    /// Raise fan speed first, then slow the processor.
+    Active = 1,
+}
+
+/// Processor performance boost mode (`PERFBOOSTMODE`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoostMode {
+    Disabled = 0,
+    Enabled = 1,
+    Aggressive = 2,
+    EfficientEnabled = 3,
+    EfficientAggressive = 4,
+}
+
+/// Settings written into the dedicated plan with `/setacvalueindex` and `/setdcvalueindex`, so
+/// they hold both plugged in and on battery; `None` keeps the base value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanTuning {
+    /// Maximum processor state in percent (`PROCTHROTTLEMAX`).
+    pub max_processor_state: Option<u8>,
+    /// Minimum processor state in percent (`PROCTHROTTLEMIN`).
+    pub min_processor_state: Option<u8>,
+    pub boost_mode: Option<BoostMode>,
+    pub cooling_policy: Option<CoolingPolicy>,
+}
+
+/// The Edge Optimizer plan: a copy of `base` with a name and tuned settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedicatedPlan {
+    /// GUID of the scheme to duplicate.
+    pub base: String,
+    pub name: String,
+    pub description: String,
+    pub tuning: PlanTuning,
+}
+
+impl DedicatedPlan {
+    /// Full processor performance with active cooling, based on High Performance (see
+    /// `base_scheme` for machines without it).
+    pub fn max_cooling() -> Self {
+        Self {
+            base: powercfg::HIGH_PERFORMANCE.into(),
+            name: "Edge Optimizer".into(),
+            description: "Active cooling with full processor performance".into(),
+            tuning: PlanTuning {
+                max_processor_state: Some(100),
+                min_processor_state: Some(5),
+                boost_mode: Some(BoostMode::Aggressive),
+                cooling_policy: Some(CoolingPolicy::Active),
+            },
+        }
+    }
+
+    /// Scheme to duplicate among `schemes`: `base` if installed, else Balanced, else the active
+    /// scheme. Modern-standby machines often ship without High Performance.
+    pub fn base_scheme<'a>(&self, schemes: &'a [PowerScheme]) -> Option<&'a PowerScheme> {
+        let installed = |guid: &str| schemes.iter().find(|s| s.guid == guid);
+        installed(&self.base)
+            .or_else(|| installed(powercfg::BALANCED))
+            .or_else(|| schemes.iter().find(|s| s.active && s.guid != EDGE_OPTIMIZER_PLAN))
+    }
+
+    /// `powercfg` invocations that create and tune the plan, in order.
+    pub fn commands(&self) -> Result<Vec<CommandSpec>, DriverError> {
+        let mut commands = vec![
+            CommandSpec::new("powercfg").args(["/duplicatescheme", self.base.as_str(), EDGE_OPTIMIZER_PLAN]),
+            CommandSpec::new("powercfg").args([
+                "/changename",
+                EDGE_OPTIMIZER_PLAN,
+                self.name.as_str(),
+                self.description.as_str(),
+            ]),
+        ];
+        commands.extend(self.tuning_commands()?);
+        Ok(commands)
+    }
+
+    fn tuning_commands(&self) -> Result<Vec<CommandSpec>, DriverError> {
+        let tuning = &self.tuning;
+        for (label, percent) in [
+            ("max processor state", tuning.max_processor_state),
+            ("min processor state", tuning.min_processor_state),
+        ] {
+            if percent.is_some_and(|p| p > 100) {
+                return Err(DriverError::Unsupported(format!("{} must be 0-100%", label)));
+            }
+        }
+        if let (Some(min), Some(max)) = (tuning.min_processor_state, tuning.max_processor_state) {
+            if min > max {
+                return Err(DriverError::Unsupported("min processor state exceeds max".into()));
+            }
+        }
+
+        let settings = [
+            ("PROCTHROTTLEMAX", tuning.max_processor_state.map(u32::from)),
+            ("PROCTHROTTLEMIN", tuning.min_processor_state.map(u32::from)),
+            ("PERFBOOSTMODE", tuning.boost_mode.map(|m| m as u32)),
+            ("SYSCOOLPOL", tuning.cooling_policy.map(|p| p as u32)),
+        ];
+        Ok(settings
+            .into_iter()
+            .filter_map(|(alias, value)| Some((alias, value?.to_string())))
+            .flat_map(|(alias, value)| {
+                ["/setacvalueindex", "/setdcvalueindex"].map(|command| {
+                    CommandSpec::new("powercfg").args([command, EDGE_OPTIMIZER_PLAN, "SUB_PROCESSOR", alias, value.as_str()])
+                })
+            })
+            .collect())
+    }
+}
+
+/// What `install_plan` did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanInstall {
+    /// The plan was created as a copy of this scheme.
+    Created { base: PowerScheme },
+    /// The plan already existed; its settings were rewritten.
+    Retuned,
+}
+
+impl<R: CommandRunner> WindowsPowerPlanDriver<R> {
+    /// Build the driver on top of a specific command runner (e.g. `ScriptedRunner` in tests).
+    pub fn with_runner(runner: R) -> Result<Self, DriverError> {
+        let driver = Self { runner };
+        match driver.schemes() {
+            Ok(schemes) if !schemes.is_empty() => Ok(driver),
+            Ok(_) => Err(DriverError::NotReady("powercfg /list reported no power schemes".into())),
             Err(e) => Err(DriverError::NotReady(format!("powercfg not available: {}", e))),
         }
     }
 
-    fn set_power_plan(&self, guid: &str) -> Result<(), DriverError> {
-        let output = Command::new("powercfg")
-            .args(&["/setactive", guid])
-            .output()
-            .map_err(|e| DriverError::Io(format!("Failed to run powercfg: {}", e)))?;
+    fn powercfg(&self, args: &[&str]) -> Result<String, DriverError> {
+        let output = self
+            .runner
+            .run(&CommandSpec::new("powercfg").args(args.iter().copied()))?;
 
-        if output.status.success() {
-            Ok(())
+        if output.success() {
+            Ok(output.stdout)
         } else {
-            Err(DriverError::Internal(format!(
-                "powercfg failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )))
+            Err(DriverError::Internal(format!("powercfg failed: {}", output.stderr.trim())))
+        }
+    }
+
+    /// Power schemes installed on this machine.
+    pub fn schemes(&self) -> Result<Vec<PowerScheme>, DriverError> {
+        Ok(powercfg::parse_list(&self.powercfg(&["/list"])?))
+    }
+
+    fn plan_profiles(&self) -> Result<Vec<PlanProfile>, DriverError> {
+        Ok(plan_profiles(&self.schemes()?))
+    }
+
+    fn run_all(&self, commands: &[CommandSpec]) -> Result<(), DriverError> {
+        for command in commands {
+            let output = self.runner.run(command)?;
+            if !output.success() {
+                return Err(DriverError::Internal(format!(
+                    "powercfg {} failed: {}",
+                    command.args.first().map(String::as_str).unwrap_or_default(),
+                    output.stderr.trim()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Create the dedicated plan from `plan.base_scheme`, or re-tune it if it already exists.
+    /// Leaves the active plan unchanged unless the dedicated plan is the active one.
+    pub fn install_plan(&self, plan: &DedicatedPlan) -> Result<PlanInstall, DriverError> {
+        let schemes = self.schemes()?;
+        let existing = schemes.iter().find(|s| s.guid == EDGE_OPTIMIZER_PLAN);
+
+        let (commands, outcome) = match existing {
+            Some(_) => (plan.tuning_commands()?, PlanInstall::Retuned),
+            None => {
+                let base = plan
+                    .base_scheme(&schemes)
+                    .ok_or_else(|| DriverError::Unsupported("no power scheme to base the plan on".into()))?;
+                let plan = DedicatedPlan {
+                    base: base.guid.clone(),
+                    ..plan.clone()
+                };
+                (plan.commands()?, PlanInstall::Created { base: base.clone() })
+            }
+        };
+        self.run_all(&commands)?;
+
+        // Settings of the active scheme only take effect once it is re-applied.
+        if existing.is_some_and(|s| s.active) {
+            self.powercfg(&["/setactive", EDGE_OPTIMIZER_PLAN])?;
+        }
+        Ok(outcome)
+    }
+
+    /// Delete the dedicated plan, switching to Balanced first if it is active. Returns whether
+    /// there was a plan to delete.
+    pub fn uninstall_plan(&self) -> Result<bool, DriverError> {
+        let schemes = self.schemes()?;
+        let Some(plan) = schemes.iter().find(|s| s.guid == EDGE_OPTIMIZER_PLAN) else {
+            return Ok(false);
+        };
+
+        // powercfg refuses to delete the active scheme.
+        if plan.active {
+            self.powercfg(&["/setactive", powercfg::BALANCED])?;
         }
+        self.powercfg(&["/delete", EDGE_OPTIMIZER_PLAN]).map(|_| true)
     }
 }
 
-impl FanDriver for WindowsPowerPlanDriver {
+impl<R: CommandRunner> FanDriver for WindowsPowerPlanDriver<R> {
     fn capabilities(&self) -> Result<FanCapabilities, DriverError> {
         Ok(FanCapabilities {
             supported: true,
-            profiles: vec![
-                FanProfile {
-                    id: 0,
-                    name: "Balanced (Default)".into(),
-                    is_maximum: false,
-                },
-                FanProfile {
-                    id: 1,
-                    name: "High Performance (Max)".into(),
-                    is_maximum: true,
-                },
-                FanProfile {
-                    id: 2,
-                    name: "Power Saver (Quiet)".into(),
-                    is_maximum: false,
-                },
-            ],
+            profiles: self.plan_profiles()?.into_iter().map(|p| p.profile).collect(),
         })
     }
 
     fn current_profile(&self) -> Result<Option<ProfileId>, DriverError> {
-        // Could parse powercfg /getactivescheme but return None for simplicity
-        Ok(None)
+        let Some(active) = powercfg::parse_active(&self.powercfg(&["/getactivescheme"])?) else {
+            return Ok(None);
+        };
+        Ok(self
+            .plan_profiles()?
+            .into_iter()
+            .find(|p| p.guid == active.guid)
+            .map(|p| p.profile.id))
     }
 
     fn set_profile(&self, profile: ProfileId) -> Result<(), DriverError> {
-        let guid = match profile {
-            0 => "381b4222-f694-41f0-9685-ff5bb260df2e", // Balanced
-            1 => "8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c", // High Performance
-            2 => "a1841308-3541-4fab-bc81-f71556f20b4a", // Power Saver
-            _ => return Err(DriverError::Unsupported(format!("Unknown profile: {}", profile))),
-        };
+        let plan = self
+            .plan_profiles()?
+            .into_iter()
+            .find(|p| p.profile.id == profile)
+            .ok_or_else(|| DriverError::Unsupported(format!("Unknown profile: {}", profile)))?;
+
+        self.powercfg(&["/setactive", &plan.guid]).map(|_| ())
+    }
+
+    fn explain_profile(&self, profile: ProfileId) -> Result<Explanation, DriverError> {
+        let plan = self
+            .plan_profiles()?
+            .into_iter()
+            .find(|p| p.profile.id == profile)
+            .ok_or_else(|| DriverError::Unsupported(format!("Unknown profile: {}", profile)))?;
 
-        self.set_power_plan(guid)
+        Ok(Explanation::new(profile)
+            .action(Action::SetPowerScheme {
+                guid: plan.guid,
+                name: plan.profile.name,
+            })
+            .verify("powercfg exits with status 0"))
     }
 
     fn telemetry(&self) -> Result<FanTelemetry, DriverError> {
         Err(DriverError::Unsupported("telemetry not available via power plans".into()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{CommandOutput, ScriptedRunner};
+
+    fn scheme(guid: &str, name: &str, active: bool) -> PowerScheme {
+        PowerScheme {
+            guid: guid.into(),
+            name: name.into(),
+            active,
+        }
+    }
+
+    fn ids(profiles: &[PlanProfile]) -> Vec<(String, ProfileId, bool)> {
+        profiles
+            .iter()
+            .map(|p| (p.guid.clone(), p.profile.id, p.profile.is_maximum))
+            .collect()
+    }
+
+    const OEM: &str = "2a8c7f6b-4e3d-4b1a-9c0f-3e2d1c0b9a87";
+    const CUSTOM: &str = "0b1c2d3e-4f50-6172-8394-a5b6c7d8e9f0";
+
+    #[test]
+    fn stock_plans_get_fixed_ids_and_high_performance_is_max() {
+        let profiles = plan_profiles(&[
+            scheme(powercfg::BALANCED, "Balanced", true),
+            scheme(powercfg::HIGH_PERFORMANCE, "High performance", false),
+            scheme(powercfg::POWER_SAVER, "Power saver", false),
+        ]);
+        assert_eq!(
+            ids(&profiles),
+            [
+                (powercfg::BALANCED.to_string(), BALANCED_ID, false),
+                (powercfg::HIGH_PERFORMANCE.to_string(), HIGH_PERFORMANCE_ID, true),
+                (powercfg::POWER_SAVER.to_string(), POWER_SAVER_ID, false),
+            ]
+        );
+        assert_eq!(profiles[1].profile.name, "High Performance (Max)");
+    }
+
+    #[test]
+    fn dedicated_plan_wins_over_ultimate_performance() {
+        let profiles = plan_profiles(&[
+            scheme(powercfg::ULTIMATE_PERFORMANCE, "Ultimate", false),
+            scheme(EDGE_OPTIMIZER_PLAN, "Edge Optimizer", false),
+        ]);
+        assert_eq!(profiles.iter().find(|p| p.profile.is_maximum).unwrap().guid, EDGE_OPTIMIZER_PLAN);
+    }
+
+    #[test]
+    fn modern_standby_machine_has_no_maximum() {
+        let profiles = plan_profiles(&[
+            scheme(powercfg::BALANCED, "Balanced", false),
+            scheme(OEM, "HP Optimized (Modern Standby)", true),
+        ]);
+        assert!(profiles.iter().all(|p| !p.profile.is_maximum));
+        assert_eq!(profiles[1].profile.name, "HP Optimized (Modern Standby)");
+        assert!(profiles[1].profile.id >= CUSTOM_PLAN_BASE);
+    }
+
+    #[test]
+    fn custom_plan_ids_do_not_depend_on_list_position() {
+        let alone = plan_profiles(&[scheme(CUSTOM, "Mine", false)]);
+        let with_more = plan_profiles(&[
+            scheme(OEM, "OEM", false),
+            scheme(powercfg::BALANCED, "Balanced", false),
+            scheme(CUSTOM, "Mine", false),
+        ]);
+        assert_eq!(alone[0].profile.id, with_more[2].profile.id);
+        assert_ne!(with_more[0].profile.id, with_more[2].profile.id);
+        // Case differences in the GUID don't change the id.
+        assert_eq!(custom_plan_id(&CUSTOM.to_ascii_uppercase(), &[]), alone[0].profile.id);
+    }
+
+    #[test]
+    fn colliding_custom_ids_take_the_next_free_one() {
+        let first = custom_plan_id(CUSTOM, &[]);
+        assert_eq!(custom_plan_id(CUSTOM, &[first]), first + 1);
+        let last = CUSTOM_PLAN_BASE + CUSTOM_PLAN_SPAN - 1;
+        assert_eq!(custom_plan_id(CUSTOM, &(first..=last).collect::<Vec<_>>()), CUSTOM_PLAN_BASE);
+    }
+
+    const LIST: &str = "
+Existing Power Schemes (* Active)
+-----------------------------------
+Power Scheme GUID: 381b4222-f694-41f0-9685-ff5bb260df2e  (Balanced) *
+Power Scheme GUID: 8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c  (High performance)
+";
+
+    fn list() -> Result<CommandOutput, DriverError> {
+        Ok(CommandOutput::ok(LIST))
+    }
+
+    fn args(spec: &CommandSpec) -> Vec<&str> {
+        spec.args.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn probe_needs_powercfg_and_a_scheme() {
+        let missing = ScriptedRunner::new().expect("powercfg", Err(DriverError::NotReady("not found".into())));
+        assert!(matches!(WindowsPowerPlanDriver::with_runner(missing), Err(DriverError::NotReady(_))));
+        let empty = ScriptedRunner::new().expect("powercfg", Ok(CommandOutput::ok("Existing Power Schemes\n")));
+        assert!(matches!(WindowsPowerPlanDriver::with_runner(empty), Err(DriverError::NotReady(_))));
+        let denied = ScriptedRunner::new().expect("powercfg", Ok(CommandOutput::failed(1, "Access is denied.")));
+        assert!(matches!(WindowsPowerPlanDriver::with_runner(denied), Err(DriverError::NotReady(_))));
+    }
+
+    #[test]
+    fn set_profile_activates_the_plan_by_guid() {
+        let runner = ScriptedRunner::new()
+            .expect("powercfg", list())
+            .expect("powercfg", list())
+            .expect("powercfg", Ok(CommandOutput::ok("")));
+        let driver = WindowsPowerPlanDriver::with_runner(&runner).unwrap();
+        driver.set_profile(HIGH_PERFORMANCE_ID).unwrap();
+        let calls = runner.calls();
+        assert_eq!(args(&calls[2]), ["/setactive", powercfg::HIGH_PERFORMANCE]);
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn current_profile_maps_the_active_scheme() {
+        let runner = ScriptedRunner::new()
+            .expect("powercfg", list())
+            .expect(
+                "powercfg",
+                Ok(CommandOutput::ok("Power Scheme GUID: 381b4222-f694-41f0-9685-ff5bb260df2e  (Balanced)\n")),
+            )
+            .expect("powercfg", list());
+        let driver = WindowsPowerPlanDriver::with_runner(&runner).unwrap();
+        assert_eq!(driver.current_profile().unwrap(), Some(BALANCED_ID));
+        assert_eq!(args(&runner.calls()[1]), ["/getactivescheme"]);
+    }
+
+    #[test]
+    fn unknown_profiles_and_powercfg_failures_are_errors() {
+        let runner = ScriptedRunner::new()
+            .expect("powercfg", list())
+            .expect("powercfg", list())
+            .expect("powercfg", list())
+            .expect("powercfg", Ok(CommandOutput::failed(1, "Access is denied.")));
+        let driver = WindowsPowerPlanDriver::with_runner(&runner).unwrap();
+        assert!(matches!(driver.set_profile(POWER_SAVER_ID), Err(DriverError::Unsupported(_))));
+        match driver.set_profile(BALANCED_ID) {
+            Err(DriverError::Internal(message)) => assert!(message.contains("Access is denied.")),
+            other => panic!("expected Internal, got {:?}", other),
+        }
+    }
+
+    fn ok() -> Result<CommandOutput, DriverError> {
+        Ok(CommandOutput::ok(""))
+    }
+
+    #[test]
+    fn tuning_is_written_for_ac_and_dc() {
+        let commands = DedicatedPlan::max_cooling().commands().unwrap();
+        let lines: Vec<String> = commands.iter().map(|c| c.args.join(" ")).collect();
+        assert_eq!(lines[0], format!("/duplicatescheme {} {}", powercfg::HIGH_PERFORMANCE, EDGE_OPTIMIZER_PLAN));
+        assert!(lines[1].starts_with(&format!("/changename {} Edge Optimizer", EDGE_OPTIMIZER_PLAN)));
+        assert_eq!(
+            lines[2..],
+            [
+                "PROCTHROTTLEMAX 100",
+                "PROCTHROTTLEMIN 5",
+                "PERFBOOSTMODE 2",
+                "SYSCOOLPOL 1",
+            ]
+            .iter()
+            .flat_map(|setting| {
+                ["/setacvalueindex", "/setdcvalueindex"]
+                    .map(|command| format!("{} {} SUB_PROCESSOR {}", command, EDGE_OPTIMIZER_PLAN, setting))
+            })
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn invalid_tuning_is_rejected() {
+        let mut plan = DedicatedPlan::max_cooling();
+        plan.tuning.min_processor_state = Some(100);
+        plan.tuning.max_processor_state = Some(50);
+        assert!(matches!(plan.commands(), Err(DriverError::Unsupported(_))));
+        plan.tuning.max_processor_state = Some(101);
+        assert!(matches!(plan.commands(), Err(DriverError::Unsupported(_))));
+    }
+
+    #[test]
+    fn install_falls_back_to_balanced_without_high_performance() {
+        let standby = "Power Scheme GUID: 381b4222-f694-41f0-9685-ff5bb260df2e  (Balanced)\n\
+                       Power Scheme GUID: 2a8c7f6b-4e3d-4b1a-9c0f-3e2d1c0b9a87  (HP Optimized) *\n";
+        let mut runner = ScriptedRunner::new()
+            .expect("powercfg", Ok(CommandOutput::ok(standby)))
+            .expect("powercfg", Ok(CommandOutput::ok(standby)));
+        for _ in 0..10 {
+            runner = runner.expect("powercfg", ok());
+        }
+        let driver = WindowsPowerPlanDriver::with_runner(&runner).unwrap();
+        match driver.install_plan(&DedicatedPlan::max_cooling()).unwrap() {
+            PlanInstall::Created { base } => assert_eq!(base.guid, powercfg::BALANCED),
+            other => panic!("expected Created, got {:?}", other),
+        }
+        let calls = runner.calls();
+        assert_eq!(args(&calls[2]), ["/duplicatescheme", powercfg::BALANCED, EDGE_OPTIMIZER_PLAN]);
+        assert_eq!(calls.len(), 12);
+        assert!(runner.is_exhausted());
+
+        let oem_only = [scheme(OEM, "HP Optimized", true)];
+        assert_eq!(DedicatedPlan::max_cooling().base_scheme(&oem_only).unwrap().guid, OEM);
+    }
+
+    #[test]
+    fn reinstalling_the_active_plan_retunes_and_reapplies_it() {
+        let installed = format!("Power Scheme GUID: {}  (Edge Optimizer) *\n", EDGE_OPTIMIZER_PLAN);
+        let mut runner = ScriptedRunner::new()
+            .expect("powercfg", Ok(CommandOutput::ok(installed.clone())))
+            .expect("powercfg", Ok(CommandOutput::ok(installed)));
+        for _ in 0..9 {
+            runner = runner.expect("powercfg", ok());
+        }
+        let driver = WindowsPowerPlanDriver::with_runner(&runner).unwrap();
+        assert_eq!(driver.install_plan(&DedicatedPlan::max_cooling()).unwrap(), PlanInstall::Retuned);
+        let calls = runner.calls();
+        assert_eq!(args(&calls[2])[0], "/setacvalueindex");
+        assert_eq!(args(calls.last().unwrap()), ["/setactive", EDGE_OPTIMIZER_PLAN]);
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn uninstall_switches_away_from_the_active_plan_first() {
+        let installed = format!(
+            "Power Scheme GUID: 381b4222-f694-41f0-9685-ff5bb260df2e  (Balanced)\n\
+             Power Scheme GUID: {}  (Edge Optimizer) *\n",
+            EDGE_OPTIMIZER_PLAN
+        );
+        let runner = ScriptedRunner::new()
+            .expect("powercfg", Ok(CommandOutput::ok(installed.clone())))
+            .expect("powercfg", Ok(CommandOutput::ok(installed)))
+            .expect("powercfg", ok())
+            .expect("powercfg", ok())
+            .expect("powercfg", list());
+        let driver = WindowsPowerPlanDriver::with_runner(&runner).unwrap();
+        assert!(driver.uninstall_plan().unwrap());
+        let calls = runner.calls();
+        assert_eq!(args(&calls[2]), ["/setactive", powercfg::BALANCED]);
+        assert_eq!(args(&calls[3]), ["/delete", EDGE_OPTIMIZER_PLAN]);
+
+        // Nothing to do once it's gone.
+        assert!(!driver.uninstall_plan().unwrap());
+        assert!(runner.is_exhausted());
+    }
+}
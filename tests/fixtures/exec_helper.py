@@ -0,0 +1,49 @@
+#!/usr/bin/env python3
+"""Stub `exec_driver` helper used by the unit tests in src/exec_driver.rs.
+
+Usage: exec_helper.py [normal|old-protocol|stale-id|slow]
+  normal        answers every request
+  old-protocol  announces protocol 0 in `hello`
+  stale-id      answers every request after `hello` with a stray reply carrying another id first
+  slow          never answers `set_profile`
+"""
+import json
+import sys
+import time
+
+mode = sys.argv[1] if len(sys.argv) > 1 else "normal"
+profile = 0
+
+
+def send(message):
+    sys.stdout.write(json.dumps(message) + "\n")
+    sys.stdout.flush()
+
+
+for line in sys.stdin:
+    request = json.loads(line)
+    rid, method, params = request["id"], request["method"], request.get("params", {})
+    if method == "hello":
+        send({"id": rid, "result": {"protocol": 0 if mode == "old-protocol" else 1, "name": "Stub board"}})
+        continue
+    if mode == "stale-id":
+        send({"id": rid + 1000, "result": {"profile": 99}})
+    if method == "capabilities":
+        send({"id": rid, "result": {"supported": True, "profiles": [
+            {"id": 0, "name": "Auto"},
+            {"id": 1, "name": "Full", "is_maximum": True},
+        ]}})
+    elif method == "current_profile":
+        send({"id": rid, "result": {"profile": profile}})
+    elif method == "set_profile":
+        if mode == "slow":
+            time.sleep(30)
+        if params.get("profile") not in (0, 1):
+            send({"id": rid, "error": {"kind": "unsupported", "message": "unknown profile"}})
+            continue
+        profile = params["profile"]
+        send({"id": rid, "result": {}})
+    elif method == "telemetry":
+        send({"id": rid, "error": {"kind": "unsupported", "message": "no tachometer"}})
+    else:
+        send({"id": rid, "error": {"kind": "internal", "message": "unknown method " + method}})
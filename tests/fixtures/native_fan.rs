@@ -0,0 +1,25 @@
+//! Stand-in vendor library for the unit tests in src/native_lib.rs. Exports `set_profile`,
+//! `get_profile` and `fan_rpm` operations but no temperature.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static PROFILE: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+pub extern "C" fn fan_set_profile(profile: u32) -> i32 {
+    if profile > 1 {
+        return -22;
+    }
+    PROFILE.store(profile, Ordering::SeqCst);
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn fan_get_profile() -> i32 {
+    PROFILE.load(Ordering::SeqCst) as i32
+}
+
+#[no_mangle]
+pub extern "C" fn fan_rpm() -> i32 {
+    4200
+}